@@ -0,0 +1,153 @@
+use relm::{
+    Relm,
+    Widget
+};
+use relm_attributes::widget;
+
+use gtk::{
+    LabelExt,
+    OrientableExt,
+    Orientation::*,
+    WidgetExt,
+    EntryExt,
+    ButtonExt,
+    ToggleButtonExt,
+};
+
+use copper::state::schema::component::Field;
+use copper::state::schema::component_instance::{
+    FieldEdit,
+    REFERENCE_FIELD,
+    VALUE_FIELD,
+    FOOTPRINT_FIELD,
+    DATASHEET_FIELD,
+    FIRST_USER_FIELD,
+};
+
+use self::Msg::*;
+
+pub struct Model {
+    relm: Relm<FieldRow>,
+
+    field: Field,
+    /// Set if the inspected instances disagree on the text of the field.
+    mixed: bool,
+}
+
+#[derive(Msg)]
+pub enum Msg {
+    TextChanged(String),
+    NameChanged(String),
+    VisibilityChanged(bool),
+    OffsetXChanged(i32),
+    OffsetYChanged(i32),
+    RemoveClicked,
+    FieldEdited(Field, FieldEdit),
+    FieldRemoved(Field),
+}
+
+/// Returns the title of one of the fields every component instance has.
+fn get_title(n: isize) -> &'static str {
+    match n {
+        REFERENCE_FIELD => "Reference",
+        VALUE_FIELD => "Value",
+        FOOTPRINT_FIELD => "Footprint",
+        DATASHEET_FIELD => "Datasheet",
+        _ => "",
+    }
+}
+
+/// One editable field of the component instances in the `ComponentInspector`.
+/// An edit is emitted together with the field as it was before, such that the edit can be applied to the same field of every instance.
+/// Only user defined fields can be renamed and removed.
+#[widget]
+impl Widget for FieldRow {
+    // The initial model.
+    fn model(relm: &Relm<FieldRow>, (field, mixed): (Field, bool)) -> Model {
+        Model {
+            relm: relm.clone(),
+
+            field: field,
+            mixed: mixed,
+        }
+    }
+
+    // Update the model according to the message received.
+    fn update(&mut self, event: Msg) {
+        let before = self.model.field.clone();
+        let was_mixed = self.model.mixed;
+        let edit = match event {
+            // Confirming the empty entry of a mixed field keeps the differing texts.
+            TextChanged(ref text) if self.model.mixed && text.is_empty() => return,
+            TextChanged(text) => {
+                self.model.mixed = false;
+                FieldEdit::Text(text)
+            },
+            // Fields without a name can not be told apart.
+            NameChanged(ref name) if name.is_empty() => return,
+            NameChanged(name) => FieldEdit::Name(name),
+            VisibilityChanged(visible) => FieldEdit::Visible(visible),
+            OffsetXChanged(x) => FieldEdit::OffsetX(x as f32),
+            OffsetYChanged(y) => FieldEdit::OffsetY(y as f32),
+            RemoveClicked => {
+                self.model.relm.stream().emit(FieldRemoved(before));
+                return;
+            },
+            _ => return,
+        };
+        match edit {
+            FieldEdit::Text(ref text) => self.model.field.text = text.clone(),
+            FieldEdit::Name(ref name) => self.model.field.name = Some(name.clone()),
+            FieldEdit::Visible(visible) => self.model.field.visible = visible,
+            FieldEdit::OffsetX(x) => self.model.field.position.x = x,
+            FieldEdit::OffsetY(y) => self.model.field.position.y = y,
+        }
+
+        // Setting the displayed values triggers the signals too, so only real changes are passed on.
+        // Any text confirmed for a mixed field is a change for some of the instances.
+        if self.model.field != before || (was_mixed && !self.model.mixed) {
+            self.model.relm.stream().emit(FieldEdited(before, edit));
+        }
+    }
+
+    view! {
+        gtk::Box {
+            orientation: Horizontal,
+
+            gtk::Label {
+                visible: self.model.field.n < FIRST_USER_FIELD,
+                text: get_title(self.model.field.n),
+            },
+            gtk::Entry {
+                visible: self.model.field.n >= FIRST_USER_FIELD,
+                activate(entry) => NameChanged(entry.get_text().unwrap()),
+                text: &self.model.field.name.clone().unwrap_or_default(),
+            },
+            gtk::Entry {
+                activate(entry) => TextChanged(entry.get_text().unwrap()),
+                placeholder_text: if self.model.mixed { "Mixed" } else { "" },
+                text: if self.model.mixed { "" } else { &self.model.field.text },
+            },
+            gtk::CheckButton {
+                label: "Visible",
+                active: self.model.field.visible,
+                toggled(button) => VisibilityChanged(button.get_active()),
+            },
+            gtk::Label { text: "X:" },
+            gtk::Entry {
+                activate(entry) => OffsetXChanged(entry.get_text().unwrap().parse().unwrap_or(0)),
+                text: &self.model.field.position.x.to_string(),
+            },
+            gtk::Label { text: "Y:" },
+            gtk::Entry {
+                activate(entry) => OffsetYChanged(entry.get_text().unwrap().parse().unwrap_or(0)),
+                text: &self.model.field.position.y.to_string(),
+            },
+            gtk::Button {
+                visible: self.model.field.n >= FIRST_USER_FIELD,
+                label: "Remove",
+                clicked => RemoveClicked,
+            },
+        }
+    }
+}
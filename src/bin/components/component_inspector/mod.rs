@@ -1,9 +1,12 @@
-use std::f32;
-use std::f32::consts;
+mod field_row;
 
+use std::collections::HashMap;
+
+use uuid::Uuid;
 use relm::{
     Relm,
-    Widget
+    Widget,
+    ContainerWidget,
 };
 use relm_attributes::widget;
 
@@ -12,31 +15,85 @@ use gtk::{
     OrientableExt,
     Orientation::*,
     WidgetExt,
-    BoxExt,
+    ContainerExt,
     EntryExt,
-    EditableSignals
+    ButtonExt,
 };
 
-use copper::state::schema::component_instance::ComponentInstance;
-use copper::geometry::*;
+use copper::state::schema::component_instance::{
+    ComponentInstance,
+    FieldEdit,
+    get_shared_fields,
+};
+use copper::state::schema::component::Field;
 
 use self::Msg::*;
+use self::field_row::FieldRow;
 
 pub struct Model {
     relm: Relm<ComponentInspector>,
 
-    component_instance: Option<ComponentInstance>,
+    component_instances: Vec<ComponentInstance>,
+    unit_limits: HashMap<Uuid, UnitLimits>,
+    field_rows: Vec<::relm::Component<FieldRow>>,
+}
+
+/// How many units and body styles the component of an inspected instance has.
+/// The unit and the body style of the instance can not be set beyond them, as nothing of the component would be drawn.
+#[derive(Clone, Copy, Debug)]
+pub struct UnitLimits {
+    pub units: usize,
+    pub body_styles: usize,
 }
 
 #[derive(Msg)]
 pub enum Msg {
-    UpdateComponentInstance(Option<ComponentInstance>),
-    ComponentInstanceUpdated(ComponentInstance),
+    UpdateComponentInstances(Vec<(ComponentInstance, UnitLimits)>),
+    ComponentInstancesUpdated(Vec<ComponentInstance>),
     PosXChanged(i32),
     PosYChanged(i32),
     RotChanged(i32),
+    MirrorClicked,
+    UnitChanged(usize),
+    ConvertChanged(usize),
+    FieldEdited(Field, FieldEdit),
+    AddField(String),
+    RemoveField(Field),
+}
+
+/// Returns the value all the `instances` share or `None` if they disagree.
+fn get_shared_value<T: PartialEq, F: Fn(&ComponentInstance) -> T>(instances: &[ComponentInstance], get: F) -> Option<T> {
+    let mut values = instances.iter().map(get);
+    let first = values.next()?;
+    if values.all(|value| value == first) {
+        Some(first)
+    } else {
+        None
+    }
+}
+
+/// Returns the text of an entry which displays a value of the `instances`. The entry is empty if they disagree.
+fn get_shared_text<T: PartialEq + ToString, F: Fn(&ComponentInstance) -> T>(instances: &[ComponentInstance], get: F) -> String {
+    get_shared_value(instances, get).map_or(String::new(), |value| value.to_string())
 }
 
+/// Returns the placeholder of an entry which displays a value of the `instances`.
+fn get_placeholder<T: PartialEq, F: Fn(&ComponentInstance) -> T>(instances: &[ComponentInstance], get: F) -> &'static str {
+    if !instances.is_empty() && get_shared_value(instances, get).is_none() { "Mixed" } else { "" }
+}
+
+/// Returns true if the inspector changed the instance.
+fn is_changed(before: &ComponentInstance, after: &ComponentInstance) -> bool {
+    before.position != after.position
+        || before.rotation != after.rotation
+        || before.unit != after.unit
+        || before.convert != after.convert
+        || before.fields != after.fields
+}
+
+/// Displays the properties of the selected `ComponentInstance`s and lets the user edit them.
+/// Values the instances disagree on are shown as mixed and an edit applies to all of the instances.
+/// An entry applies its value once Enter is pressed. Every edit is emitted as one `ComponentInstancesUpdated`.
 #[widget]
 impl Widget for ComponentInspector {
     // The initial model.
@@ -44,90 +101,153 @@ impl Widget for ComponentInspector {
         Model {
             relm: relm.clone(),
 
-            component_instance: None,
+            component_instances: Vec::new(),
+            unit_limits: HashMap::new(),
+            field_rows: Vec::new(),
         }
     }
 
     // Update the model according to the message received.
     fn update(&mut self, event: Msg) {
         match event {
-            UpdateComponentInstance(component_instance) => self.model.component_instance = component_instance,
-            PosXChanged(x) => {
-                self.model.component_instance.as_mut().map(|ci| ci.position.x = x as f32);
-                self.model.component_instance.clone().map(|ci| self.model.relm.stream().emit(ComponentInstanceUpdated(ci)));
+            UpdateComponentInstances(component_instances) => {
+                self.model.unit_limits = component_instances.iter().map(|(ci, limits)| (ci.uuid, *limits)).collect();
+                self.model.component_instances = component_instances.into_iter().map(|(ci, _)| ci).collect();
+                self.update_field_rows();
+            },
+            PosXChanged(x) => self.edit_all(|ci| ci.position.x = x as f32),
+            PosYChanged(y) => self.edit_all(|ci| ci.position.y = y as f32),
+            RotChanged(a) => self.edit_all(|ci| {
+                let mirrored = ci.is_mirrored();
+                // Components can only be rotated in steps of 90 degrees.
+                ci.set_orientation((a as f32 / 90.0).round() * 90.0, mirrored);
+            }),
+            MirrorClicked => self.edit_all(|ci| {
+                let angle = ci.get_angle();
+                let mirrored = !ci.is_mirrored();
+                ci.set_orientation(angle, mirrored);
+            }),
+            UnitChanged(unit) => {
+                let limits = self.model.unit_limits.clone();
+                self.edit_all(|ci| ci.unit = unit.min(limits.get(&ci.uuid).map_or(1, |l| l.units)).max(1));
             },
-            PosYChanged(y) => {
-                self.model.component_instance.as_mut().map(|ci| ci.position.y = y as f32);
-                self.model.component_instance.clone().map(|ci| self.model.relm.stream().emit(ComponentInstanceUpdated(ci)));
+            ConvertChanged(convert) => {
+                let limits = self.model.unit_limits.clone();
+                self.edit_all(|ci| ci.convert = convert.min(limits.get(&ci.uuid).map_or(1, |l| l.body_styles)).max(1));
             },
-            RotChanged(a) => {
-                let mat = Matrix4::from_euler_angles(0.0, 0.0, ((a as f32 * (2.0 * consts::PI) / 360.0) as i32) as f32);
-                self.model.component_instance.as_mut().map(|ci| ci.rotation = mat);
-                self.model.component_instance.clone().map(|ci| self.model.relm.stream().emit(ComponentInstanceUpdated(ci)));
-            }
-            _ => ()
+            FieldEdited(field, edit) => {
+                self.edit_all(|ci| ci.edit_field(&field, &edit));
+                // A name which is already taken is refused, so the row has to show the old one again.
+                if let FieldEdit::Name(_) = edit {
+                    self.update_field_rows();
+                }
+            },
+            AddField(name) => {
+                if !name.is_empty() {
+                    self.edit_all(|ci| ci.ensure_user_field(&name));
+                }
+            },
+            RemoveField(field) => self.edit_all(|ci| ci.remove_matching_user_field(&field)),
+            ComponentInstancesUpdated(_) => (),
         }
     }
 
-//  pub uuid: Uuid,
-//     pub name: String,
-//     pub reference: String,
-//     pub position: Point2,
-//     pub rotation: Matrix4,
-//     #[derivative(Debug="ignore", Clone(clone_with="clone_cached_aabb"))]
-//     pub bounding_box: Cell<Option<AABB>>
+    /// Applies `edit` to a copy of every inspected instance and emits the ones which changed.
+    /// The inspector is updated once the edited instances are sent back through `UpdateComponentInstances`.
+    fn edit_all<F: Fn(&mut ComponentInstance)>(&mut self, edit: F) {
+        let changed: Vec<_> = self.model.component_instances.iter().filter_map(|before| {
+            let mut after = before.clone();
+            edit(&mut after);
+            if is_changed(before, &after) { Some(after) } else { None }
+        }).collect();
+        if !changed.is_empty() {
+            self.model.relm.stream().emit(ComponentInstancesUpdated(changed));
+        }
+    }
+
+    /// Creates a `FieldRow` for every field the inspected instances share.
+    fn update_field_rows(&mut self) {
+        for child in self.fields_box.get_children() {
+            self.fields_box.remove(&child);
+        }
+        self.model.field_rows.clear();
+
+        for shared_field in get_shared_fields(&self.model.component_instances) {
+            let row = self.fields_box.add_widget::<FieldRow>(shared_field);
+            connect!(row@field_row::Msg::FieldEdited(ref field, ref edit), &self.model.relm, FieldEdited(field.clone(), edit.clone()));
+            connect!(row@field_row::Msg::FieldRemoved(ref field), &self.model.relm, RemoveField(field.clone()));
+            self.model.field_rows.push(row);
+        }
+    }
 
     view! {
         gtk::Box {
-            #[name="libraries_listbox"]
-            gtk::ListBox {
-                visible: self.model.component_instance.is_some(),
-                child: {
-                    fill: true,
-                    expand: true,
+            orientation: Vertical,
+            visible: !self.model.component_instances.is_empty(),
+
+            gtk::Label {
+                text: &match self.model.component_instances.len() {
+                    1 => format!("UUID: {}", self.model.component_instances[0].uuid),
+                    n => format!("{} components", n),
+                }
+            },
+            gtk::Label {
+                text: &format!("Component: {}", get_shared_value(&self.model.component_instances, |ci| ci.name.clone()).unwrap_or("Mixed".to_owned()))
+            },
+            // Moving several instances onto the same spot is of no use.
+            gtk::Box {
+                orientation: Horizontal,
+                visible: self.model.component_instances.len() == 1,
+
+                gtk::Label { text: "X:" },
+                gtk::Entry {
+                    activate(entry) => PosXChanged(entry.get_text().unwrap().parse().unwrap_or(0)),
+                    text: &get_shared_text(&self.model.component_instances, |ci| ci.position.x)
                 },
-                gtk::Label {
-                    text: {
-                        let u = if let Some(ci) = &self.model.component_instance { ci.uuid.to_string() } else { "".to_owned() };
-                        &format!("UUID: {}", u)
-                    }
+                gtk::Label { text: "Y:" },
+                gtk::Entry {
+                    activate(entry) => PosYChanged(entry.get_text().unwrap().parse().unwrap_or(0)),
+                    text: &get_shared_text(&self.model.component_instances, |ci| ci.position.y)
                 },
-                gtk::Label {
-                    text: {
-                        let u = if let Some(ci) = &self.model.component_instance { ci.reference.to_string() } else { "".to_owned() };
-                        &format!("Reference: {}", u)
-                    }
+            },
+            gtk::Box {
+                orientation: Horizontal,
+
+                gtk::Label { text: "Angle: " },
+                gtk::Entry {
+                    activate(entry) => RotChanged(entry.get_text().unwrap().parse().unwrap_or(0)),
+                    placeholder_text: get_placeholder(&self.model.component_instances, |ci| ci.get_angle().round()),
+                    text: &get_shared_text(&self.model.component_instances, |ci| ci.get_angle().round())
                 },
-                gtk::Box {
-                    orientation: Horizontal,
-
-                    gtk::Label { text: "X:" },
-                    gtk::Entry {
-                        changed(entry) => {
-                            PosXChanged(entry.get_text().unwrap().parse().unwrap_or(0))
-                        },
-                        text: &if let Some(ci) = &self.model.component_instance { ci.position.x.to_string() } else { "".to_owned() }
-                    },
-                    gtk::Label { text: "Y:" },
-                    gtk::Entry {
-                        changed(entry) => {
-                            PosYChanged(entry.get_text().unwrap().parse().unwrap_or(0))
-                        },
-                        text: &if let Some(ci) = &self.model.component_instance { ci.position.y.to_string() } else { "".to_owned() }
-                    },
+                gtk::Button {
+                    label: "Mirror",
+                    clicked => MirrorClicked,
+                },
+            },
+            gtk::Box {
+                orientation: Horizontal,
+
+                gtk::Label { text: "Unit:" },
+                gtk::Entry {
+                    activate(entry) => UnitChanged(entry.get_text().unwrap().parse().unwrap_or(1)),
+                    placeholder_text: get_placeholder(&self.model.component_instances, |ci| ci.unit),
+                    text: &get_shared_text(&self.model.component_instances, |ci| ci.unit)
                 },
-                gtk::Box {
-                    orientation: Horizontal,
-
-                    gtk::Label { text: "Angle: " },
-                    gtk::Entry {
-                        changed(entry) => {
-                            RotChanged(entry.get_text().unwrap().parse().unwrap_or(0))
-                        },
-                        text: &if let Some(ci) = &self.model.component_instance { f32::acos((ci.rotation.trace() - 1.0) / 2.0 / (2.0 * consts::PI) * 360.0).to_string() } else { "".to_owned() }
-                    },
+                gtk::Label { text: "Convert:" },
+                gtk::Entry {
+                    activate(entry) => ConvertChanged(entry.get_text().unwrap().parse().unwrap_or(1)),
+                    placeholder_text: get_placeholder(&self.model.component_instances, |ci| ci.convert),
+                    text: &get_shared_text(&self.model.component_instances, |ci| ci.convert)
                 },
             },
+            #[name="fields_box"]
+            gtk::Box {
+                orientation: Vertical,
+            },
+            gtk::Entry {
+                placeholder_text: "New field name",
+                activate(entry) => AddField(entry.get_text().unwrap()),
+            },
         }
     }
-}
\ No newline at end of file
+}
@@ -11,14 +11,19 @@ use gtk::{
 
 use copper::geometry::*;
 use copper::state::schema::*;
+use copper::viewing::grid::GridUnit;
 
 use self::Msg::*;
 
 pub struct Model {
     current_cursor_position_screen: Point2,
     current_cursor_position_schema: Point2,
+    /// The unit the schema coordinates are displayed in.
+    unit: GridUnit,
     current_hovered_component: String,
     current_selected_component: String,
+    current_hovered_net: String,
+    current_highlighted_net: String,
 }
 
 #[derive(Msg)]
@@ -33,8 +38,11 @@ impl Widget for CursorInfo {
         Model {
             current_cursor_position_screen: Point2::new(0.0, 0.0),
             current_cursor_position_schema: Point2::new(0.0, 0.0),
+            unit: GridUnit::Mil,
             current_hovered_component: String::new(),
             current_selected_component: String::new(),
+            current_hovered_net: String::new(),
+            current_highlighted_net: String::new(),
         }
     }
 
@@ -44,6 +52,9 @@ impl Widget for CursorInfo {
             ViewStateChanged(vs) => {
                 self.model.current_cursor_position_screen = vs.get_cursor();
                 self.model.current_cursor_position_schema = vs.get_cursor_in_schema_space();
+                self.model.unit = vs.grid.unit;
+                self.model.current_hovered_net = vs.hovered_net.clone().unwrap_or(String::new());
+                self.model.current_highlighted_net = vs.highlighted_net.clone().unwrap_or(String::new());
 //                self.model.current_hovered_component = vs.hovered_component_reference.unwrap_or(String::new());
 //                self.model.current_selected_component = vs.selected_component_reference.unwrap_or(String::new());
             },
@@ -64,7 +75,8 @@ impl Widget for CursorInfo {
             gtk::Label {
                 text: &{
                     let pos = self.model.current_cursor_position_schema;
-                    format!("Schema: {{{:.0} / {:.0}}} | ", pos.x, pos.y)
+                    let unit = self.model.unit;
+                    format!("Schema: {{{} / {}}} {} | ", unit.format(pos.x), unit.format(pos.y), unit.get_symbol())
                 }
             },
             gtk::Label {
@@ -76,7 +88,19 @@ impl Widget for CursorInfo {
             gtk::Label {
                 text: &{
                     let cc = &self.model.current_selected_component;
-                    format!("Selected: {}", cc)
+                    format!("Selected: {} | ", cc)
+                }
+            },
+            gtk::Label {
+                text: &{
+                    let n = &self.model.current_hovered_net;
+                    format!("Net: {} | ", n)
+                }
+            },
+            gtk::Label {
+                text: &{
+                    let n = &self.model.current_highlighted_net;
+                    format!("Highlighted: {}", n)
                 }
             },
         }
@@ -0,0 +1,160 @@
+use relm::{
+    Relm,
+    Widget
+};
+use relm_attributes::widget;
+
+use gtk::{
+    LabelExt,
+    OrientableExt,
+    Orientation::*,
+    WidgetExt,
+    EntryExt,
+    ComboBoxExt,
+    ComboBoxTextExt,
+    ToggleButtonExt,
+};
+
+use copper::viewing::grid::{
+    GridSettings,
+    GridUnit,
+    GridPreset,
+    GRID_UNITS,
+    GRID_PRESETS,
+};
+
+use self::Msg::*;
+
+pub struct Model {
+    relm: Relm<GridPanel>,
+
+    settings: GridSettings,
+}
+
+#[derive(Msg)]
+pub enum Msg {
+    /// Displays the given settings, for example after the alternative grid was toggled with a key.
+    Update(GridSettings),
+    UnitChanged(Option<String>),
+    PresetChanged(Option<String>),
+    SizeEntered(String),
+    VisibleToggled(bool),
+    SnapToGridToggled(bool),
+    SnapToPinsToggled(bool),
+    GridChanged(GridSettings),
+}
+
+/// A toolbar to choose the grid size, the unit sizes and coordinates are displayed in and what the cursor snaps to.
+/// The size entry takes a size in the chosen unit and applies to the grid currently in use, which may be the alternative one.
+#[widget]
+impl Widget for GridPanel {
+    fn init_view(&mut self) {
+        for unit in GRID_UNITS.iter() {
+            self.unit.append(Some(unit.get_symbol()), unit.get_symbol());
+        }
+        for preset in GRID_PRESETS.iter() {
+            let name = preset.get_name();
+            self.preset.append(Some(name.as_str()), &name);
+        }
+        self.update_combo_boxes();
+    }
+
+    // The initial model.
+    fn model(relm: &Relm<GridPanel>, _: ()) -> Model {
+        Model {
+            relm: relm.clone(),
+
+            settings: GridSettings::new(),
+        }
+    }
+
+    // Update the model according to the message received.
+    fn update(&mut self, event: Msg) {
+        let mut settings = self.model.settings.clone();
+        match event {
+            Update(settings) => {
+                self.model.settings = settings;
+                self.update_combo_boxes();
+                return;
+            },
+            UnitChanged(symbol) => {
+                if let Some(unit) = symbol.and_then(|s| GridUnit::from_symbol(&s)) {
+                    settings.unit = unit;
+                }
+            },
+            PresetChanged(name) => {
+                if let Some(preset) = name.and_then(|n| GridPreset::from_name(&n)) {
+                    settings.set_active_size(preset.get_size());
+                }
+            },
+            SizeEntered(text) => {
+                if let Ok(size) = text.trim().parse() {
+                    let size = settings.unit.to_schema(size);
+                    settings.set_active_size(size);
+                }
+            },
+            VisibleToggled(visible) => settings.visible = visible,
+            SnapToGridToggled(snap) => settings.snap_to_grid = snap,
+            SnapToPinsToggled(snap) => settings.snap_to_pins = snap,
+            GridChanged(_) => return,
+        }
+
+        // Setting the displayed values triggers the signals too, so only real changes are passed on.
+        if settings != self.model.settings {
+            self.model.settings = settings.clone();
+            self.update_combo_boxes();
+            self.model.relm.stream().emit(GridChanged(settings));
+        }
+    }
+
+    /// Selects the current unit and the preset matching the grid in use, preferably one in the current unit.
+    fn update_combo_boxes(&self) {
+        let settings = &self.model.settings;
+        self.unit.set_active_id(Some(settings.unit.get_symbol()));
+
+        let size = settings.get_active_size();
+        let matching = |preset: &&GridPreset| (preset.get_size() - size).abs() < 1e-3;
+        let preset = GRID_PRESETS.iter().filter(matching).find(|preset| preset.unit == settings.unit)
+            .or_else(|| GRID_PRESETS.iter().find(matching));
+        if let Some(preset) = preset {
+            self.preset.set_active_id(Some(preset.get_name().as_str()));
+        }
+    }
+
+    view! {
+        gtk::Box {
+            orientation: Horizontal,
+
+            gtk::Label {
+                text: if self.model.settings.use_alt { "Alt grid:" } else { "Grid:" },
+            },
+            #[name="preset"]
+            gtk::ComboBoxText {
+                changed(combo) => PresetChanged(combo.get_active_text()),
+            },
+            gtk::Entry {
+                activate(entry) => SizeEntered(entry.get_text().unwrap_or_default()),
+                text: &self.model.settings.unit.format(self.model.settings.get_active_size()),
+            },
+            #[name="unit"]
+            gtk::ComboBoxText {
+                changed(combo) => UnitChanged(combo.get_active_text()),
+            },
+            gtk::CheckButton {
+                label: "Show grid",
+                active: self.model.settings.visible,
+                toggled(button) => VisibleToggled(button.get_active()),
+            },
+            gtk::CheckButton {
+                label: "Snap to grid",
+                active: self.model.settings.snap_to_grid,
+                toggled(button) => SnapToGridToggled(button.get_active()),
+            },
+            gtk::CheckButton {
+                label: "Snap to pins",
+                active: self.model.settings.snap_to_pins,
+                toggled(button) => SnapToPinsToggled(button.get_active()),
+            },
+        }
+    }
+}
@@ -23,11 +23,14 @@ pub struct Model {
     last_frame_timestamp: Instant,
     framerate_measurements: VecDeque<u64>,
     framerate: u64,
+    /// A message for the user about the last action, such as why it was not carried out.
+    notice: String,
 }
 
 #[derive(Msg)]
 pub enum Msg {
-    FrameTimeCaptured(u64)
+    FrameTimeCaptured(u64),
+    Notice(String),
 }
 
 #[widget]
@@ -42,6 +45,7 @@ impl Widget for InfoBar {
             last_frame_timestamp: Instant::now(),
             framerate_measurements: VecDeque::new(),
             framerate: 0,
+            notice: String::new(),
         }
     }
 
@@ -68,6 +72,7 @@ impl Widget for InfoBar {
                 self.model.framerate = self.model.framerate_measurements.iter().sum::<u64>() / 30;
                 self.model.last_frame_timestamp = now
             },
+            Notice(notice) => self.model.notice = notice,
         }
     }
 
@@ -101,6 +106,9 @@ impl Widget for InfoBar {
                     format!("FPS: {:.0} | ", 1.0 / (v as f32 / 1e6))
                 }
             },
+            gtk::Label {
+                text: &self.model.notice,
+            },
         }
     }
 }
\ No newline at end of file
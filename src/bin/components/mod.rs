@@ -2,4 +2,6 @@ pub mod cursor_info;
 pub mod info_bar;
 pub mod component_selector;
 pub mod library_listbox_entry;
-pub mod component_inspector;
\ No newline at end of file
+pub mod component_inspector;
+pub mod search_panel;
+pub mod grid_panel;
\ No newline at end of file
@@ -0,0 +1,147 @@
+use relm::{
+    Relm,
+    Widget
+};
+use relm_attributes::widget;
+
+use gtk::{
+    LabelExt,
+    OrientableExt,
+    Orientation::*,
+    WidgetExt,
+    EntryExt,
+    ButtonExt,
+    ToggleButtonExt,
+};
+
+use copper::state::schema::search::SearchQuery;
+
+use self::Msg::*;
+
+pub struct Model {
+    relm: Relm<SearchPanel>,
+
+    status: String,
+}
+
+#[derive(Msg)]
+pub enum Msg {
+    Focus,
+    Close,
+    Step(isize),
+    ReplaceClicked,
+    Status(String),
+    /// Asks for the match `step` matches away from the current one.
+    Search(SearchQuery, isize),
+    /// Asks to replace all the matches with the given text.
+    ReplaceAll(SearchQuery, String),
+}
+
+/// A panel to search the references, values, fields, labels and notes of the schema and to replace the matches.
+#[widget]
+impl Widget for SearchPanel {
+    // The initial model.
+    fn model(relm: &Relm<SearchPanel>, _: ()) -> Model {
+        Model {
+            relm: relm.clone(),
+
+            status: String::new(),
+        }
+    }
+
+    // Update the model according to the message received.
+    fn update(&mut self, event: Msg) {
+        match event {
+            Focus => {
+                self.root().show();
+                self.pattern.grab_focus();
+            },
+            Close => self.root().hide(),
+            Step(step) => {
+                let query = self.get_query();
+                self.model.relm.stream().emit(Search(query, step));
+            },
+            ReplaceClicked => {
+                let query = self.get_query();
+                let replacement = self.replacement.get_text().unwrap_or_default();
+                self.model.relm.stream().emit(ReplaceAll(query, replacement));
+            },
+            Status(status) => self.model.status = status,
+            Search(..) | ReplaceAll(..) => (),
+        }
+    }
+
+    /// Builds the query from the state of the panel.
+    fn get_query(&self) -> SearchQuery {
+        let mut query = SearchQuery::new(self.pattern.get_text().unwrap_or_default());
+        query.regex = self.regex.get_active();
+        query.whole_word = self.whole_word.get_active();
+        query.case_sensitive = self.case_sensitive.get_active();
+        query.in_references = self.in_references.get_active();
+        query.in_values = self.in_values.get_active();
+        query.in_fields = self.in_fields.get_active();
+        query.in_labels = self.in_labels.get_active();
+        query.in_notes = self.in_notes.get_active();
+        query
+    }
+
+    view! {
+        gtk::Box {
+            orientation: Vertical,
+
+            gtk::Box {
+                orientation: Horizontal,
+
+                #[name="pattern"]
+                gtk::Entry {
+                    placeholder_text: "Find",
+                    activate(_) => Step(1),
+                },
+                gtk::Button {
+                    label: "Previous",
+                    clicked => Step(-1),
+                },
+                gtk::Button {
+                    label: "Next",
+                    clicked => Step(1),
+                },
+                #[name="replacement"]
+                gtk::Entry {
+                    placeholder_text: "Replace with",
+                    activate(_) => ReplaceClicked,
+                },
+                gtk::Button {
+                    label: "Replace all",
+                    clicked => ReplaceClicked,
+                },
+                gtk::Button {
+                    label: "Close",
+                    clicked => Close,
+                },
+            },
+            gtk::Box {
+                orientation: Horizontal,
+
+                #[name="regex"]
+                gtk::CheckButton { label: "Regex" },
+                #[name="whole_word"]
+                gtk::CheckButton { label: "Whole word" },
+                #[name="case_sensitive"]
+                gtk::CheckButton { label: "Case sensitive" },
+                #[name="in_references"]
+                gtk::CheckButton { label: "References", active: true },
+                #[name="in_values"]
+                gtk::CheckButton { label: "Values", active: true },
+                #[name="in_fields"]
+                gtk::CheckButton { label: "Fields", active: true },
+                #[name="in_labels"]
+                gtk::CheckButton { label: "Labels", active: true },
+                #[name="in_notes"]
+                gtk::CheckButton { label: "Notes", active: true },
+                gtk::Label {
+                    text: &self.model.status,
+                },
+            },
+        }
+    }
+}
@@ -8,7 +8,14 @@ use gdk::{
     EventMotion,
 };
 
-use gtk::WidgetExt;
+use gtk;
+use gtk::{
+    WidgetExt,
+    EntryExt,
+    GtkWindowExt,
+    Cast,
+    Clipboard,
+};
 
 use main_window::{
     Win,
@@ -16,21 +23,55 @@ use main_window::{
 };
 
 use std::time::Instant;
+use ncollide2d::bounding_volume::BoundingVolume;
 use copper::state::event::EventMessage;
 use copper::parsing::kicad::schema::{
     WireType,
     WireSegment,
+    Label,
+    LabelKind,
+    Note,
+    NoteLine,
 };
 use components::info_bar;
-use copper::utils::geometry::*;
 use copper::geometry::*;
-use copper::state::schema::component_instance::ComponentInstance;
+use copper::utils::geometry::segment_aabb;
+use copper::state::schema::component_instance::{
+    ComponentInstance,
+    REFERENCE_FIELD,
+};
+use copper::state::schema::{
+    Drag,
+    CONNECTION_TOLERANCE,
+};
+use copper::state::schema::clipboard;
 use components::component_inspector;
+use components::component_inspector::UnitLimits;
+use components::search_panel;
+use copper::state::schema::search;
+use copper::state::schema::search::SearchQuery;
 use copper::drawing::schema_drawer::SchemaDrawer;
+use copper::viewing::schema_viewer;
+use copper::viewing::schema_viewer::{
+    SchemaViewer,
+    PickedItem,
+    SelectionMode,
+    SnapPoint,
+};
+use copper::viewing::view_state::ViewState;
+use copper::viewing::net::Net;
+use copper::viewing::grid::GridSettings;
+use components::grid_panel;
 use copper::drawing;
 
 
 const LEFT_MOUSE_BUTTON: u32 = 1;
+/// The maximum distance in pixels the cursor may travel between press and release for it to count as a click.
+const CLICK_TOLERANCE: f32 = 3.0;
+/// The margin around the items the view is zoomed onto, in schema units.
+const ZOOM_MARGIN: f32 = 500.0;
+/// The distance in pixels within which the cursor snaps onto pin and wire ends.
+const SNAP_TOLERANCE: f32 = 8.0;
 
 impl Win {
 
@@ -69,6 +110,17 @@ impl Win {
 
     pub fn button_pressed_long(&mut self, _x: f64, _y: f64) {
         // This is always an LMB press.
+        // Grabbing a wire near one of its ends drags just that end.
+        let drags_wire_end = match self.model.edit_mode {
+            EditMode::Component | EditMode::None => self.get_hovered_wire_end(),
+            _ => None,
+        };
+        if let Some(end) = drags_wire_end {
+            self.start_wire_end_drag(end);
+            self.model.button_pressed_location = None;
+            self.notify_view_state_changed();
+            return;
+        }
         {
             let mut view_state = self.model.view_state.write().unwrap();
             let _schema = self.model.schema.read().unwrap();
@@ -88,6 +140,8 @@ impl Win {
                 _ => {}
             };
         }
+        // Grabbed components drag their wires along.
+        self.start_drag(true);
         self.model.button_pressed_location = None;
         self.update_selection_rectangle();
         self.update_hovered_rectangle();
@@ -99,17 +153,26 @@ impl Win {
     pub fn button_released(&mut self, event: EventButton) {
         // If the left button was pressed:
         if event.get_button() == LEFT_MOUSE_BUTTON {
-            let cursor = {
-                let mut view_state = self.model.view_state.write().unwrap();
-                view_state.get_cursor_in_schema_space()
+            let (cursor, is_click) = {
+                let view_state = self.model.view_state.read().unwrap();
+                let cursor = view_state.get_cursor_in_schema_space();
+                let tolerance = view_state.get_schema_distance_from_screen_distance(&Vector2::new(CLICK_TOLERANCE, 0.0)).norm();
+                let is_click = self.model.button_pressed_location.map_or(false, |bp| (bp - cursor).norm() <= tolerance);
+                (cursor, is_click)
             };
+            let releases_grabbed_items = !self.model.view_state.read().unwrap().grabbed_items.is_empty() || self.model.drag.is_some();
             {
                 match self.model.edit_mode.clone() {
-                    EditMode::Wire(wires, _) => {
+                    EditMode::Wire(wires, _, _) => {
+                        // Wires are placed where the preview shows them, which is snapped unlike the plain cursor.
+                        let (cursor, _) = self.get_snapped_cursor(event.get_state());
                         self.update_preview_wires(&cursor);
                         match event.get_event_type() {
                             EventType::ButtonPress => {
-                                if wires.len() > 1 {
+                                if self.model.autoroute && !wires.is_empty() {
+                                    // The autorouter places all the corners, so the second click finishes the wire.
+                                    self.materialize_preview_wire();
+                                } else if wires.len() > 1 {
                                     self.append_one_preview_wire(&cursor)
                                 } else {
                                     self.start_new_preview_wire(&cursor);
@@ -119,28 +182,32 @@ impl Win {
                             _ => {}
                         }
                     },
+                    EditMode::Label(_) => {
+                        // Labels are placed where the preview shows them, like wires.
+                        if is_click {
+                            let (cursor, _) = self.get_snapped_cursor(event.get_state());
+                            self.place_label(&cursor);
+                        }
+                    },
+                    EditMode::Note(_) => {
+                        if is_click {
+                            let (cursor, _) = self.get_snapped_cursor(event.get_state());
+                            self.place_note(&cursor);
+                        }
+                    },
+                    EditMode::NoteLine(_) => {
+                        if is_click {
+                            let (cursor, _) = self.get_snapped_cursor(event.get_state());
+                            self.place_note_line(&cursor);
+                        }
+                    },
                     EditMode::Component => {
                         // Select the currently hovered component.
                         {
                             let mut view_state = self.model.view_state.write().unwrap();
                             if view_state.grabbed_items.is_empty() {
-                                let mut viewer = self.model.viewer.read().unwrap();
-                                view_state.selected_items.clear();
-
-                                if let Some(bp) = self.model.button_pressed_location {
-                                    let start_x = bp.x.min(cursor.x);
-                                    let start_y = bp.y.min(cursor.y);
-                                    let end_x = bp.x.max(cursor.x);
-                                    let end_y = bp.y.max(cursor.y);
-                                    let aabb = AABB::new(
-                                        Point2::new(start_x, start_y),
-                                        Point2::new(end_x, end_y)
-                                    );
-                                    let uuids = viewer.get_component_uuids_in_rect(&aabb);
-                                    for uuid in uuids {
-                                        view_state.selected_items.insert(uuid);
-                                    }
-                                }
+                                let viewer = self.model.viewer.read().unwrap();
+                                Self::select_items(&mut view_state, &viewer, self.model.button_pressed_location, &cursor, is_click, event.get_state());
                             } else {
                                 view_state.selected_items.clear();
                                 view_state.add_grabbed_items_to_selected_items();
@@ -154,23 +221,8 @@ impl Win {
                         {
                             let mut view_state = self.model.view_state.write().unwrap();
                             if view_state.grabbed_items.is_empty() {
-                                let mut viewer = self.model.viewer.read().unwrap();
-                                view_state.selected_items.clear();
-
-                                if let Some(bp) = self.model.button_pressed_location {
-                                    let start_x = bp.x.min(cursor.x);
-                                    let start_y = bp.y.min(cursor.y);
-                                    let end_x = bp.x.max(cursor.x);
-                                    let end_y = bp.y.max(cursor.y);
-                                    let aabb = AABB::new(
-                                        Point2::new(start_x, start_y),
-                                        Point2::new(end_x, end_y)
-                                    );
-                                    let uuids = viewer.get_component_uuids_in_rect(&aabb);
-                                    for uuid in uuids {
-                                        view_state.selected_items.insert(uuid);
-                                    }
-                                }
+                                let viewer = self.model.viewer.read().unwrap();
+                                Self::select_items(&mut view_state, &viewer, self.model.button_pressed_location, &cursor, is_click, event.get_state());
                             } else {
                                 view_state.selected_items.clear();
                                 view_state.add_grabbed_items_to_selected_items();
@@ -181,15 +233,20 @@ impl Win {
                         self.model.edit_mode = EditMode::Component;
                     },
                 };
-                let items = {
-                    let view_state = self.model.view_state.read().unwrap();
-                    view_state.selected_items.get_items().clone()
-                };
-                if items.len() == 1 {
-                    let ci = self.model.schema.read().unwrap().get_component_instance(items.iter().nth(0).unwrap()).clone();
-                    self.send_to_component_inspector(component_inspector::Msg::UpdateComponentInstance(Some(ci)));
-                } else {
-                    self.send_to_component_inspector( component_inspector::Msg::UpdateComponentInstance(None));
+
+                // Moved components can create or break T-connections with their pins.
+                if releases_grabbed_items {
+                    self.finish_drag();
+                }
+
+                self.update_component_inspector();
+
+                // A plain click onto a conductor highlights its net.
+                if is_click {
+                    match self.model.edit_mode {
+                        EditMode::Wire(..) | EditMode::Label(_) | EditMode::Note(_) | EditMode::NoteLine(_) => (),
+                        _ => self.update_highlighted_net(&cursor),
+                    }
                 }
             }
             self.model.button_pressed_location = None;
@@ -203,7 +260,7 @@ impl Win {
 
     pub fn move_cursor(&mut self, event: EventMotion) {
         {
-            let cursor = {
+            {
                 let mut view_state = self.model.view_state.write().unwrap();
 
                 // Get the current cursor position.
@@ -219,30 +276,36 @@ impl Win {
 
                 // Update the view state with the current cursor position.
                 view_state.update_cursor(new_cursor_position);
+            }
 
-                if event.get_state().contains(ModifierType::MOD1_MASK) {
-                    view_state.get_cursor_in_schema_space()
-                } else {
-                    view_state.get_grid_snapped_cursor_in_schema_space()
-                }
-            };
+            let (cursor, snap) = self.get_snapped_cursor(event.get_state());
 
             match &mut self.model.edit_mode {
-                EditMode::Wire(_, _) => {
+                EditMode::Wire(..) => {
                     self.update_preview_wires(&cursor);
                 },
+                EditMode::Label(label) => {
+                    label.position = cursor;
+                    self.model.drawer.write().unwrap().update_label(label.clone());
+                },
+                EditMode::Note(note) => {
+                    note.position = cursor;
+                    self.model.drawer.write().unwrap().update_note(note.clone());
+                },
+                EditMode::NoteLine(Some(line)) => {
+                    line.end = cursor;
+                    self.model.drawer.write().unwrap().update_note_line(line.clone());
+                },
                 EditMode::Component => {
-                    // If a component is currently selected, move it.
-                    let mut view_state = self.model.view_state.read().unwrap();
-                    let mut schema = self.model.schema.write().unwrap();
-                    let new_pos = point_to_vector_2d(&cursor);
-                    for u in &view_state.grabbed_items {
-                        schema.move_component(u, new_pos);
+                    // If components are currently grabbed, move them.
+                    if let Some(drag) = &self.model.drag {
+                        drag.update(&mut self.model.schema.write().unwrap(), cursor);
                     }
                 },
                 _ => ()
             };
 
+            self.update_snap_indicator(snap);
             self.update_selection_rectangle();
             self.update_hovered_rectangle();
             self.update_grabbed_rectangle();
@@ -251,6 +314,55 @@ impl Win {
         self.notify_view_state_changed();
     }
 
+    /// Returns the cursor in schema space snapped as the grid settings ask for, together with the point it snapped onto if any.
+    /// Pins are often off the grid, so their ends and the ones of wires take precedence over it.
+    /// While drawing wires or placing labels, the cursor snaps onto the wires themselves too.
+    /// Dragged items keep to the grid, as they would be pulled off it otherwise. Holding Alt turns all snapping off.
+    fn get_snapped_cursor(&self, modifiers: ModifierType) -> (Point2, Option<SnapPoint>) {
+        let (raw_cursor, grid_cursor, tolerance) = {
+            let view_state = self.model.view_state.read().unwrap();
+            let raw_cursor = view_state.get_cursor_in_schema_space();
+            if modifiers.contains(ModifierType::MOD1_MASK) {
+                return (raw_cursor, None);
+            }
+            let tolerance = view_state.get_schema_distance_from_screen_distance(&Vector2::new(SNAP_TOLERANCE, 0.0)).norm();
+            (raw_cursor, view_state.get_grid_snapped_cursor_in_schema_space(), tolerance)
+        };
+        if !self.model.view_state.read().unwrap().grid.snap_to_pins || self.model.drag.is_some() {
+            return (grid_cursor, None);
+        }
+
+        let viewer = self.model.viewer.read().unwrap();
+        let snap = match self.model.edit_mode {
+            EditMode::Wire(..) | EditMode::Label(_) => viewer.get_wire_snap_point(&raw_cursor, tolerance),
+            _ => viewer.get_nearest_conductor_end(&raw_cursor, tolerance).map(SnapPoint::End),
+        };
+        match snap {
+            // Along a wire the grid still applies wherever the grid point lies on a wire.
+            Some(SnapPoint::Segment(_)) if viewer.get_wire_snap_point(&grid_cursor, CONNECTION_TOLERANCE).is_some() => {
+                (grid_cursor, Some(SnapPoint::Segment(grid_cursor)))
+            },
+            Some(snap) => (snap.get_position(), Some(snap)),
+            None => (grid_cursor, None),
+        }
+    }
+
+    /// Marks the point a wire being drawn or a label being placed snaps onto. Ends are marked differently from points on wires, where a junction is formed.
+    fn update_snap_indicator(&mut self, snap: Option<SnapPoint>) {
+        let snap = match self.model.edit_mode {
+            EditMode::Wire(..) | EditMode::Label(_) => snap,
+            _ => None,
+        };
+        let size = self.model.view_state.read().unwrap().get_schema_distance_from_screen_distance(&Vector2::new(SNAP_TOLERANCE / 2.0, 0.0)).norm();
+        let (aabb, color) = match snap {
+            Some(SnapPoint::End(point)) => (Some(segment_aabb(&point, &point, size)), drawing::Color::new(0.0, 0.6, 0.0, 1.0)),
+            Some(SnapPoint::Segment(point)) => (Some(segment_aabb(&point, &point, size)), drawing::Color::new(0.9, 0.5, 0.0, 1.0)),
+            None => (None, drawing::Color::new(0.0, 0.0, 0.0, 1.0)),
+        };
+        let drawer = &mut self.model.drawer.write().unwrap();
+        Self::update_indicator_rect_from_aabb(drawer, &mut self.model.snap_indicator, &aabb, color);
+    }
+
     pub fn zoom_on_schema(&mut self, _x: f64, y: f64) {
         {
             let mut view_state = self.model.view_state.write().unwrap();
@@ -260,15 +372,124 @@ impl Win {
     }
 
     pub fn key_down(&mut self, event: EventKey) {
+        // Typing into an entry must not trigger the shortcuts.
+        if self.window.get_focus().map_or(false, |widget| widget.is::<gtk::Entry>()) {
+            return;
+        }
+        let mut start_drag = None;
+        let mut cleared_selection = false;
+        let mut paste = None;
+        let mut focus_search = false;
+        let mut focus_goto = false;
+        let mut zoom_to_selection = false;
+        let mut grid_toggled = None;
+        let mut start_labels = None;
+        let mut start_note = false;
+        let mut notice = None;
+        let preview = self.get_placement_preview();
         {
-            use gdk::enums::key::{r, a, w, Escape};
+            use gdk::enums::key::{r, a, w, b, k, g, G, m, n, t, T, i, z, Z, c, C, x, X, v, V, d, D, f, F, y, l, L, h, Delete, Escape, Home, Left, Right};
             let mut schema = self.model.schema.write().unwrap();
-            let view_state = self.model.view_state.read().unwrap();
+            let mut view_state = self.model.view_state.write().unwrap();
+            let ctrl = event.get_state().contains(ModifierType::CONTROL_MASK);
+            let mut delete_selection = false;
             match event.get_keyval() {
-                r => {
+                z | Z if ctrl => {
+                    // Ctrl+Z undoes the last step, Ctrl+Shift+Z redoes it.
+                    if self.model.drag.is_none() {
+                        let changed = if event.get_state().contains(ModifierType::SHIFT_MASK) {
+                            schema.redo()
+                        } else {
+                            schema.undo()
+                        };
+                        if changed {
+                            // The items might not exist anymore.
+                            view_state.selected_items.clear();
+                            view_state.hovered_items.clear();
+                            view_state.grabbed_items.clear();
+                            cleared_selection = true;
+                        }
+                    }
+                },
+                c | C | x | X if ctrl => {
+                    // Ctrl+C copies the selected items to the clipboard, Ctrl+X cuts them.
+                    let items = view_state.selected_items.get_items().iter().cloned().collect::<Vec<_>>();
+                    let anchor = view_state.get_grid_snapped_cursor_in_schema_space();
+                    if let Some(fragment) = clipboard::copy(&schema, &items, &anchor) {
+                        Clipboard::get(&gdk::SELECTION_CLIPBOARD).set_text(&fragment);
+                        delete_selection = event.get_keyval() == x || event.get_keyval() == X;
+                    }
+                },
+                v | V if ctrl => {
+                    // Ctrl+V pastes with unannotated references, Ctrl+Shift+V keeps the references.
+                    paste = Some((None, event.get_state().contains(ModifierType::SHIFT_MASK)));
+                },
+                d | D if ctrl => {
+                    // Ctrl+D duplicates the selected items without going through the clipboard.
+                    let items = view_state.selected_items.get_items().iter().cloned().collect::<Vec<_>>();
+                    let anchor = view_state.get_grid_snapped_cursor_in_schema_space();
+                    if let Some(fragment) = clipboard::copy(&schema, &items, &anchor) {
+                        paste = Some((Some(fragment), false));
+                    }
+                },
+                f | F if ctrl => {
+                    // Ctrl+F opens the search panel.
+                    focus_search = true;
+                },
+                g | G if ctrl => {
+                    // Ctrl+G goes to a reference.
+                    focus_goto = true;
+                },
+                Home => {
+                    // Fit the whole schema into the view.
+                    let bb = schema.get_bounding_box(&self.model.libraries.read().unwrap());
+                    view_state.jump_to_box(bb.loosened(ZOOM_MARGIN));
+                },
+                z => {
+                    // Fit the selected items into the view.
+                    zoom_to_selection = true;
+                },
+                Left if event.get_state().contains(ModifierType::MOD1_MASK) => {
+                    // Alt+Left returns to the previous view, Alt+Right goes forward again.
+                    view_state.go_back();
+                },
+                Right if event.get_state().contains(ModifierType::MOD1_MASK) => {
+                    view_state.go_forward();
+                },
+                Delete => {
+                    // Delete the selected items.
+                    delete_selection = true;
+                },
+                r | x | y => {
+                    // Rotate the selected components or mirror them at the x or y axis.
                     let em = self.model.edit_mode.clone();
                     match em {
-                        EditMode::Component => { view_state.selected_items.iter().for_each(|uuid| schema.rotate_component(&uuid)); },
+                        EditMode::Component => {
+                            let key = event.get_keyval();
+                            view_state.selected_items.iter().for_each(|uuid| match key {
+                                x => schema.mirror_component_x(&uuid),
+                                y => schema.mirror_component_y(&uuid),
+                                _ => schema.rotate_component(&uuid),
+                            });
+                            schema.cleanup_wires(&self.model.libraries.read().unwrap());
+                            schema.commit();
+                        },
+                        EditMode::Label(mut label) => {
+                            // Turn the label being placed.
+                            if event.get_keyval() == r {
+                                label.rotate();
+                                self.model.drawer.write().unwrap().update_label(label.clone());
+                                self.model.edit_mode = EditMode::Label(label);
+                            }
+                        },
+                        EditMode::Note(mut note) => {
+                            // Turn the note being placed.
+                            if event.get_keyval() == r {
+                                note.rotate();
+                                self.model.drawer.write().unwrap().update_note(note.clone());
+                                self.model.edit_mode = EditMode::Note(note);
+                            }
+                        },
                         _ => ()
                     };
                 },
@@ -277,19 +498,138 @@ impl Win {
                     self.model.component_selector.widget().show();
                 },
                 w => {
-                    if let EditMode::Wire(_, _) = self.model.edit_mode {} else {
-                        self.model.edit_mode = EditMode::Wire(vec![], true);
+                    if let EditMode::Wire(..) = self.model.edit_mode {} else {
+                        self.model.edit_mode = EditMode::Wire(vec![], true, WireType::Wire);
+                    }
+                },
+                b => {
+                    if let EditMode::Wire(..) = self.model.edit_mode {} else {
+                        self.model.edit_mode = EditMode::Wire(vec![], true, WireType::Bus);
+                    }
+                },
+                l | L | h => {
+                    // Place local, global or hierarchical labels.
+                    start_labels = Some(match event.get_keyval() {
+                        l => LabelKind::Local,
+                        L => LabelKind::Global,
+                        _ => LabelKind::Hierarchical,
+                    });
+                },
+                T => {
+                    // Place a note with the text of the text entry.
+                    start_note = true;
+                },
+                i => {
+                    // Draw note lines.
+                    if let EditMode::NoteLine(_) = self.model.edit_mode {} else {
+                        self.model.edit_mode = EditMode::NoteLine(None);
+                    }
+                },
+                k => {
+                    // Clean up the wires on demand.
+                    schema.cleanup_wires(&self.model.libraries.read().unwrap());
+                    schema.commit();
+                },
+                n => {
+                    // Switch between the grid and the alternative grid.
+                    view_state.grid.toggle_alt();
+                    grid_toggled = Some(view_state.grid.clone());
+                },
+                t => {
+                    // Toggle the autorouter for new wires.
+                    self.model.autoroute = !self.model.autoroute;
+                },
+                g | m => {
+                    // Grab the selected items. `g` drags the wires of grabbed components along, `m` detaches them.
+                    if self.model.drag.is_none() {
+                        view_state.add_selected_items_to_grabbed_items();
+                        self.model.edit_mode = EditMode::Component;
+                        start_drag = Some(event.get_keyval() == g);
                     }
                 },
                 Escape => {
-                    if let EditMode::Wire(ref mut wires, _) = self.model.edit_mode {
-                        let mut drawer = self.model.drawer.write().unwrap();
+                    if let Some(drag) = self.model.drag.take() {
+                        drag.cancel(&mut schema);
+                        view_state.grabbed_items.clear();
+                    }
+                    let mut drawer = self.model.drawer.write().unwrap();
+                    if let EditMode::Wire(ref mut wires, _, _) = self.model.edit_mode {
                         wires.drain(..).for_each(|wire| drawer.remove_wire(wire));
                     }
+                    drawer.clear_highlighted_net();
+                    if let Some(uuid) = self.model.snap_indicator.take() {
+                        drawer.remove_drawable(&uuid);
+                    }
+                    view_state.highlighted_net = None;
+                    self.model.highlighted_net = None;
                     self.model.edit_mode = EditMode::None;
                 }
                 _ => ()
             }
+
+            if delete_selection && self.model.drag.is_none() && !view_state.selected_items.is_empty() {
+                let libraries = self.model.libraries.read().unwrap();
+                // Junctions which still join three or more ends would be put back right away, so they are kept selected.
+                let (kept, items): (Vec<Uuid>, Vec<Uuid>) = view_state.selected_items.get_items().iter()
+                    .cloned()
+                    .partition(|uuid| schema.is_junction_required(uuid, &libraries));
+                schema.remove_items(&items);
+                schema.cleanup_wires(&libraries);
+                schema.commit();
+                view_state.selected_items.clear();
+                if !kept.is_empty() {
+                    kept.iter().for_each(|uuid| view_state.selected_items.insert(*uuid));
+                    notice = Some(format!(
+                        "{} junction(s) still join three or more wires or pins and were kept. Remove one of the wires to disconnect them.",
+                        kept.len()
+                    ));
+                }
+                view_state.hovered_items.clear();
+                cleared_selection = true;
+            }
+        }
+        if let Some(notice) = notice {
+            self.send_to_info_bar(info_bar::Msg::Notice(notice));
+        }
+        // Leaving a placement mode, whichever way, removes the preview of the item being placed.
+        if let Some(uuid) = preview {
+            if self.get_placement_preview() != Some(uuid) {
+                self.model.drawer.write().unwrap().remove_drawable(&uuid);
+            }
+        }
+        if start_note {
+            self.start_note_placement();
+        }
+        if let Some(kind) = start_labels {
+            self.start_label_placement(kind);
+        }
+        if let Some(rubber_band) = start_drag {
+            self.start_drag(rubber_band);
+        }
+        if let Some((fragment, keep_references)) = paste {
+            // Reading the clipboard runs the main loop, so it must not happen while the locks are held.
+            let fragment = fragment.or_else(|| Clipboard::get(&gdk::SELECTION_CLIPBOARD).wait_for_text());
+            if let Some(fragment) = fragment {
+                self.paste_fragment(&fragment, keep_references);
+            }
+        }
+        if focus_search {
+            self.send_to_search_panel(search_panel::Msg::Focus);
+        }
+        if focus_goto {
+            self.goto_reference.grab_focus();
+        }
+        if zoom_to_selection {
+            self.zoom_to_selection();
+        }
+        if let Some(grid) = grid_toggled {
+            self.send_to_grid_panel(grid_panel::Msg::Update(grid));
+        }
+        if cleared_selection {
+            self.send_to_component_inspector(component_inspector::Msg::UpdateComponentInstances(Vec::new()));
+            self.update_selection_rectangle();
+            self.update_hovered_rectangle();
+            self.update_grabbed_rectangle();
         }
         self.notify_view_state_changed();
     }
@@ -300,26 +640,22 @@ impl Win {
         let pos = view_state.get_cursor_in_schema_space();
         instance.position = pos;
         let uuid = schema.add_component(instance);
+        schema.commit();
         view_state.selected_items.insert(uuid);
         self.model.component_selector.widget().hide();
         self.model.edit_mode = EditMode::Component;
     }
 
-    pub fn grid_changed(&mut self) {
-        let mut vs = self.model.view_state.write().unwrap();
-        use gtk::EntryExt;
-        let x = self.grid_x.get_text().and_then(|t| t.parse().ok());
-        let y = self.grid_y.get_text().and_then(|t| t.parse().ok());
-        if let (Some(x), Some(y)) = (x, y) {
-            vs.set_grid_size(x, y);
-        }
+    pub fn grid_changed(&mut self, settings: GridSettings) {
+        self.model.view_state.write().unwrap().grid = settings;
+        self.notify_view_state_changed();
     }
 
     pub fn update_selection_rectangle(&mut self) {
         let libraries = self.model.libraries.write().unwrap();
         let schema = self.model.schema.write().unwrap();
         let drawer = &mut self.model.drawer.write().unwrap();
-        let aabb = self.model.view_state.read().unwrap().selected_items.get_grouped_component_aabb(&libraries, &schema);
+        let aabb = self.model.view_state.read().unwrap().selected_items.get_grouped_aabb(&libraries, &schema);
         let sr = &mut self.model.selection_rectangle;
         Self::update_indicator_rect_from_aabb(drawer, sr, &aabb, drawing::Color::new(1.0, 0.0, 0.0, 1.0));
     }
@@ -328,7 +664,7 @@ impl Win {
         let libraries = self.model.libraries.write().unwrap();
         let schema = self.model.schema.write().unwrap();
         let drawer = &mut self.model.drawer.write().unwrap();
-        let aabb = self.model.view_state.read().unwrap().grabbed_items.get_grouped_component_aabb(&libraries, &schema);
+        let aabb = self.model.view_state.read().unwrap().grabbed_items.get_grouped_aabb(&libraries, &schema);
         let gr = &mut self.model.grabbed_rectangle;
         Self::update_indicator_rect_from_aabb(drawer, gr, &aabb, drawing::Color::new(232.0 / 255.0, 182.0 / 255.0, 12.0 / 255.0, 1.0));
     }
@@ -337,7 +673,7 @@ impl Win {
         let libraries = self.model.libraries.write().unwrap();
         let schema = self.model.schema.write().unwrap();
         let drawer = &mut self.model.drawer.write().unwrap();
-        let aabb = self.model.view_state.read().unwrap().hovered_items.get_grouped_component_aabb(&libraries, &schema);
+        let aabb = self.model.view_state.read().unwrap().hovered_items.get_grouped_aabb(&libraries, &schema);
         let hr = &mut self.model.hovered_rectangle;
         Self::update_indicator_rect_from_aabb(drawer, hr, &aabb, drawing::Color::new(0.0, 127.0 / 255.0, 45.0 / 255.0, 1.0));
     }
@@ -354,7 +690,12 @@ impl Win {
                 Point2::new(start_x, start_y),
                 Point2::new(end_x, end_y)
             );
-            Self::update_indicator_rect_from_aabb(drawer, sr, &Some(aabb), drawing::Color::new(1.0, 1.0, 1.0, 1.0));
+            // A window is drawn in blue and a crossing selection in green.
+            let color = match SelectionMode::from_drag(&bp, cursor) {
+                SelectionMode::Window => drawing::Color::new(0.4, 0.6, 1.0, 1.0),
+                SelectionMode::Crossing => drawing::Color::new(0.4, 1.0, 0.4, 1.0),
+            };
+            Self::update_indicator_rect_from_aabb(drawer, sr, &Some(aabb), color);
         } else {
             Self::update_indicator_rect_from_aabb(drawer, sr, &None, drawing::Color::new(1.0, 1.0, 1.0, 1.0));
         }
@@ -377,8 +718,55 @@ impl Win {
         }
     }
 
+    /// Redraws the markers of the unconnected pin and wire ends and the highlighted net if conductors changed since they were drawn.
+    /// The highlighted net is traced again from one of its conductors which is still there, as edits split, merge and remove them.
+    pub fn update_connectivity_markers(&mut self) {
+        let (ends, net) = {
+            let mut viewer = self.model.viewer.write().unwrap();
+            if !viewer.take_connectivity_changed() {
+                return;
+            }
+            let net = self.model.highlighted_net.as_ref().and_then(|net| {
+                net.conductors.iter()
+                    .find(|conductor| viewer.has_conductor(conductor))
+                    .map(|conductor| viewer.get_net(conductor))
+            });
+            (viewer.get_unconnected_ends(), net)
+        };
+        self.model.drawer.write().unwrap().show_unconnected_ends(&ends);
+        if self.model.highlighted_net.is_some() {
+            self.set_highlighted_net(net);
+        }
+    }
+
+    /// Highlights the net of the conductor underneath the cursor.
+    /// Clears the highlighted net if there is no conductor underneath the cursor.
+    fn update_highlighted_net(&mut self, cursor: &Point2) {
+        let net = {
+            let viewer = self.model.viewer.read().unwrap();
+            let conductors = viewer.get_currently_hovered_conductor_uuid(cursor);
+            conductors.first().map(|conductor| viewer.get_net(conductor))
+        };
+        self.set_highlighted_net(net);
+    }
+
+    fn set_highlighted_net(&mut self, net: Option<Net>) {
+        {
+            let mut drawer = self.model.drawer.write().unwrap();
+            match &net {
+                Some(net) => drawer.highlight_net(net),
+                None => drawer.clear_highlighted_net(),
+            }
+        }
+        self.model.view_state.write().unwrap().highlighted_net = net.as_ref().map(|net| net.get_display_name());
+        self.model.highlighted_net = net;
+    }
+
     fn update_preview_wires(&mut self, cursor: &Point2) {
-        if let EditMode::Wire(wires, lw_is_horizontal) = &mut self.model.edit_mode {
+        if self.model.autoroute && self.update_autorouted_preview_wires(cursor) {
+            return;
+        }
+        if let EditMode::Wire(wires, lw_is_horizontal, _) = &mut self.model.edit_mode {
             if wires.len() > 1 {
                 let mid = wires.len() - 1;
                 let (first, second) = wires[..].split_at_mut(mid);
@@ -401,20 +789,55 @@ impl Win {
 
                 drawer.update_wire(previous_wire.clone());
                 drawer.update_wire(current_wire.clone());
+            }
+        }
+    }
+
+    /// Replaces the previewed wire segments with an autorouted path from the start of the first segment to the cursor.
+    /// Both ends snap onto nearby pins and wire ends, even when snapping the cursor is turned off.
+    /// Returns false if no path was found, in which case the preview is left as it is.
+    fn update_autorouted_preview_wires(&mut self, cursor: &Point2) -> bool {
+        let (start, kind) = match &self.model.edit_mode {
+            EditMode::Wire(wires, _, kind) if !wires.is_empty() => (wires[0].start, kind.clone()),
+            _ => return false,
+        };
+        let (grid, tolerance) = {
+            let view_state = self.model.view_state.read().unwrap();
+            let tolerance = view_state.get_schema_distance_from_screen_distance(&Vector2::new(SNAP_TOLERANCE, 0.0)).norm();
+            (view_state.get_grid_size(), tolerance)
+        };
+        let path = self.model.viewer.read().unwrap().route_wire(&start, cursor, &Vector2::new(grid.x, grid.y), tolerance);
+        let path = match path {
+            Some(path) => path,
+            None => return false,
+        };
+        if path.len() < 2 {
+            return false;
+        }
 
-                // Check if we find an electrical conductor near the cursor.
-                let conductors = self.model.viewer.read().unwrap().get_currently_hovered_conductor_uuid(cursor);
-                println!("{:?}", conductors);
+        if let EditMode::Wire(wires, _, _) = &mut self.model.edit_mode {
+            let mut drawer = self.model.drawer.write().unwrap();
+            wires.drain(..).for_each(|wire| drawer.remove_wire(wire));
+            for corners in path.windows(2) {
+                let wire = WireSegment {
+                    uuid: Uuid::new_v4(),
+                    kind: kind.clone(),
+                    start: corners[0],
+                    end: corners[1],
+                };
+                drawer.add_wire(wire.clone());
+                wires.push(wire);
             }
         }
+        true
     }
 
     fn append_one_preview_wire(&mut self, cursor: &Point2) {
-        if let EditMode::Wire(wires, lw_is_horizontal) = &mut self.model.edit_mode {
+        if let EditMode::Wire(wires, lw_is_horizontal, kind) = &mut self.model.edit_mode {
             // Create a new wire.
             let ws = WireSegment {
                 uuid: Uuid::new_v4(),
-                kind: WireType::Wire,
+                kind: kind.clone(),
                 start: cursor.clone(),
                 end: cursor.clone(),
             };
@@ -429,17 +852,19 @@ impl Win {
     }
 
     fn start_new_preview_wire(&mut self, cursor: &Point2) {
+        let kind = if let EditMode::Wire(_, _, kind) = &self.model.edit_mode { kind.clone() } else { WireType::Wire };
+
         // Create two new wires.
         let wires = vec![
             WireSegment {
                 uuid: Uuid::new_v4(),
-                kind: WireType::Wire,
+                kind: kind.clone(),
                 start: cursor.clone(),
                 end: cursor.clone(),
             },
             WireSegment {
                 uuid: Uuid::new_v4(),
-                kind: WireType::Wire,
+                kind: kind.clone(),
                 start: cursor.clone(),
                 end: cursor.clone(),
             }
@@ -449,11 +874,11 @@ impl Win {
         let mut drawer = self.model.drawer.write().unwrap();
         drawer.add_wire(wires[wires.len() - 1].clone());
         drawer.add_wire(wires[wires.len() - 2].clone());
-        self.model.edit_mode = EditMode::Wire(wires, true);
+        self.model.edit_mode = EditMode::Wire(wires, true, kind);
     }
 
     fn materialize_preview_wire(&mut self) {
-        if let EditMode::Wire(wires, _) = &mut self.model.edit_mode {
+        if let EditMode::Wire(wires, _, _) = &mut self.model.edit_mode {
             // First remove all the previewed wire segments from the drawer.
             // Make sure that we don't aquire the lock for too long.
             {
@@ -467,6 +892,436 @@ impl Win {
             wires.drain(..).for_each(|wire| {
                 schema.add_wire(wire);
             });
+            schema.cleanup_wires(&self.model.libraries.read().unwrap());
+            schema.commit();
+        }
+    }
+
+    /// Starts dragging the grabbed items from the current cursor position on.
+    /// With `rubber_band` set, the wires attached to the pins of grabbed components follow them, otherwise they are detached from their wires.
+    fn start_drag(&mut self, rubber_band: bool) {
+        let (origin, items) = {
+            let view_state = self.model.view_state.read().unwrap();
+            let items = view_state.grabbed_items.get_items().iter().cloned().collect::<Vec<_>>();
+            (view_state.get_grid_snapped_cursor_in_schema_space(), items)
+        };
+        if items.is_empty() {
+            return;
+        }
+        let attached_wires = if rubber_band {
+            let viewer = self.model.viewer.read().unwrap();
+            items.iter().flat_map(|uuid| {
+                let mut attached = viewer.get_wires_attached_to_pins(uuid);
+                attached.extend(viewer.get_wires_attached_to_wire(uuid));
+                attached
+            }).collect()
+        } else {
+            Vec::new()
+        };
+        let mut schema = self.model.schema.write().unwrap();
+        self.model.drag = Some(Drag::new(&mut schema, origin, &items, &attached_wires));
+    }
+
+    /// Returns the end of the wire underneath the cursor if the cursor is close to one of its ends.
+    fn get_hovered_wire_end(&self) -> Option<Point2> {
+        let (cursor, tolerance) = {
+            let view_state = self.model.view_state.read().unwrap();
+            let tolerance = view_state.get_schema_distance_from_screen_distance(&Vector2::new(schema_viewer::PICK_TOLERANCE, 0.0)).norm();
+            (view_state.get_cursor_in_schema_space(), tolerance)
+        };
+        let viewer = self.model.viewer.read().unwrap();
+        match viewer.pick(&cursor, tolerance) {
+            Some(PickedItem::Wire(uuid)) => viewer.get_wire_end_near(&uuid, &cursor, tolerance),
+            _ => None,
+        }
+    }
+
+    /// Starts dragging the end of a wire which lies at `end`. All the wires which end there follow along.
+    fn start_wire_end_drag(&mut self, end: Point2) {
+        if self.model.drag.is_some() {
+            return;
+        }
+        let attached_wires = self.model.viewer.read().unwrap().get_wires_ending_at(&end).into_iter()
+            .map(|uuid| (uuid, end))
+            .collect::<Vec<_>>();
+        let origin = self.model.view_state.read().unwrap().get_grid_snapped_cursor_in_schema_space();
+        let mut schema = self.model.schema.write().unwrap();
+        self.model.drag = Some(Drag::new(&mut schema, origin, &[], &attached_wires));
+        self.model.edit_mode = EditMode::Component;
+    }
+
+    /// Updates the selection with the items in the area between the location where the button was pressed and the cursor.
+    /// Dragging from left to right selects the enclosed items, dragging from right to left selects the crossed items.
+    /// A click without moving the cursor picks the item underneath the cursor instead.
+    /// Shift adds the items to the selection and Ctrl toggles them, otherwise they replace the selection.
+    fn select_items(view_state: &mut ViewState, viewer: &SchemaViewer, pressed: Option<Point2>, cursor: &Point2, is_click: bool, modifiers: ModifierType) {
+        let items = match pressed {
+            Some(_) if is_click => {
+                let tolerance = view_state.get_schema_distance_from_screen_distance(&Vector2::new(schema_viewer::PICK_TOLERANCE, 0.0)).norm();
+                viewer.pick(cursor, tolerance).map(|item| item.get_uuid()).into_iter().collect()
+            },
+            Some(pressed) => {
+                let aabb = AABB::new(
+                    Point2::new(pressed.x.min(cursor.x), pressed.y.min(cursor.y)),
+                    Point2::new(pressed.x.max(cursor.x), pressed.y.max(cursor.y))
+                );
+                viewer.get_items_in_rect(&aabb, SelectionMode::from_drag(&pressed, cursor))
+            },
+            None => Vec::new(),
+        };
+
+        if modifiers.contains(ModifierType::CONTROL_MASK) {
+            for uuid in items {
+                view_state.selected_items.toggle(uuid);
+            }
+        } else {
+            if !modifiers.contains(ModifierType::SHIFT_MASK) {
+                view_state.selected_items.clear();
+            }
+            for uuid in items {
+                view_state.selected_items.insert(uuid);
+            }
+        }
+    }
+
+    /// Pastes a schema fragment at the cursor and grabs the pasted items, such that they follow the cursor until the next click.
+    fn paste_fragment(&mut self, fragment: &str, keep_references: bool) {
+        if let EditMode::Wire(..) = self.model.edit_mode {
+            return;
+        }
+        if self.model.drag.is_some() {
+            return;
+        }
+        let uuids = {
+            let cursor = self.model.view_state.read().unwrap().get_grid_snapped_cursor_in_schema_space();
+            let mut schema = self.model.schema.write().unwrap();
+            match clipboard::paste(&mut schema, fragment, &cursor, keep_references) {
+                Some(uuids) => uuids,
+                None => return,
+            }
+        };
+        {
+            let mut view_state = self.model.view_state.write().unwrap();
+            view_state.selected_items.clear();
+            view_state.hovered_items.clear();
+            view_state.grabbed_items.clear();
+            for uuid in uuids {
+                view_state.grabbed_items.insert(uuid);
+            }
+        }
+        self.model.edit_mode = EditMode::Component;
+        self.start_drag(false);
+        self.send_to_component_inspector(component_inspector::Msg::UpdateComponentInstances(Vec::new()));
+        self.update_selection_rectangle();
+        self.update_hovered_rectangle();
+        self.update_grabbed_rectangle();
+    }
+
+    /// Places the power port with the given name from the loaded libraries at the cursor and grabs it, such that it follows the cursor until the next click.
+    /// Nothing happens if none of the libraries has a power symbol of that name.
+    pub fn place_power_port(&mut self, name: &str) {
+        if self.model.drag.is_some() {
+            return;
+        }
+        let mut instance = {
+            let libraries = self.model.libraries.read().unwrap();
+            match libraries.get_power_component(name) {
+                Some(component) => {
+                    let mut instance = component.instantiate();
+                    // Power ports are annotated like any other component, `#PWR?` becomes `#PWR01` and so on.
+                    instance.reference = format!("{}?", component.reference);
+                    let reference = instance.reference.clone();
+                    instance.set_field_text(REFERENCE_FIELD, &reference);
+                    instance
+                },
+                None => return,
+            }
+        };
+        let uuid = {
+            instance.position = self.model.view_state.read().unwrap().get_grid_snapped_cursor_in_schema_space();
+            self.model.schema.write().unwrap().add_component(instance)
+        };
+        {
+            let mut view_state = self.model.view_state.write().unwrap();
+            view_state.selected_items.clear();
+            view_state.hovered_items.clear();
+            view_state.grabbed_items.clear();
+            view_state.grabbed_items.insert(uuid);
+        }
+        if let Some(uuid) = self.get_placement_preview() {
+            self.model.drawer.write().unwrap().remove_drawable(&uuid);
+        }
+        self.model.edit_mode = EditMode::Component;
+        self.start_drag(false);
+        self.send_to_component_inspector(component_inspector::Msg::UpdateComponentInstances(Vec::new()));
+        self.update_selection_rectangle();
+        self.update_hovered_rectangle();
+        self.update_grabbed_rectangle();
+        self.notify_view_state_changed();
+    }
+
+    /// Starts placing labels of the given kind with the text of the label text entry, or switches the kind of the labels being placed.
+    /// The entry is focused, such that the text of the first label can be typed right away.
+    fn start_label_placement(&mut self, kind: LabelKind) {
+        let label = match self.model.edit_mode.clone() {
+            EditMode::Label(mut label) => {
+                label.kind = kind;
+                label
+            },
+            _ => {
+                let (cursor, _) = self.get_snapped_cursor(ModifierType::empty());
+                let mut label = Label::new(kind, self.label_text.get_text().unwrap_or_default(), cursor);
+                // The preview is drawn under a uuid of its own, the schema hands out new ones to the placed labels.
+                label.uuid = Uuid::new_v4();
+                label
+            },
+        };
+        self.model.drawer.write().unwrap().update_label(label.clone());
+        self.model.edit_mode = EditMode::Label(label);
+        self.label_text.grab_focus();
+    }
+
+    /// Sets the text of the labels or the note being placed and hands the focus back to the schema, such that the shortcuts work again.
+    /// Outside of the placement modes the text replaces the one of the selected note, if exactly one note is selected.
+    /// Notes can span several lines, which are separated by `\n` in the single line entry.
+    pub fn set_label_text(&mut self, text: String) {
+        match self.model.edit_mode {
+            EditMode::Label(ref mut label) => {
+                label.text = text;
+                self.model.drawer.write().unwrap().update_label(label.clone());
+            },
+            EditMode::Note(ref mut note) => {
+                note.text = text.replace("\\n", "\n");
+                self.model.drawer.write().unwrap().update_note(note.clone());
+            },
+            _ => {
+                let view_state = self.model.view_state.read().unwrap();
+                let mut schema = self.model.schema.write().unwrap();
+                let selected = view_state.selected_items.get_items();
+                if selected.len() == 1 {
+                    if let Some(mut note) = selected.iter().next().and_then(|uuid| schema.find_note(uuid)).cloned() {
+                        note.text = text.replace("\\n", "\n");
+                        schema.update_note(note);
+                        schema.commit();
+                    }
+                }
+            },
+        }
+        self.window.set_focus(None::<&gtk::Widget>);
+        self.notify_view_state_changed();
+    }
+
+    /// Places a copy of the previewed label at `cursor`.
+    /// A text ending with a number is incremented afterwards, such that a row of labels like `D0`, `D1`, ... is placed with one click each.
+    fn place_label(&mut self, cursor: &Point2) {
+        let text = if let EditMode::Label(ref mut label) = self.model.edit_mode {
+            label.position = *cursor;
+            if label.text.is_empty() {
+                return;
+            }
+            {
+                let mut schema = self.model.schema.write().unwrap();
+                schema.add_label(label.clone());
+                schema.commit();
+            }
+            label.increment_text();
+            self.model.drawer.write().unwrap().update_label(label.clone());
+            label.text.clone()
+        } else {
+            return;
+        };
+        self.label_text.set_text(&text);
+    }
+
+    /// Returns the `Uuid` of the preview drawn for the label, note or note line being placed, if any.
+    fn get_placement_preview(&self) -> Option<Uuid> {
+        match &self.model.edit_mode {
+            EditMode::Label(label) => Some(label.uuid),
+            EditMode::Note(note) => Some(note.uuid),
+            EditMode::NoteLine(Some(line)) => Some(line.uuid),
+            _ => None,
+        }
+    }
+
+    /// Starts placing a note with the text of the text entry, which is focused such that the text can be typed right away.
+    fn start_note_placement(&mut self) {
+        if let EditMode::Note(_) = self.model.edit_mode {
+            self.label_text.grab_focus();
+            return;
+        }
+        let (cursor, _) = self.get_snapped_cursor(ModifierType::empty());
+        let mut note = Note::new(self.label_text.get_text().unwrap_or_default().replace("\\n", "\n"), cursor);
+        // Like labels, the preview is drawn under a uuid of its own.
+        note.uuid = Uuid::new_v4();
+        self.model.drawer.write().unwrap().update_note(note.clone());
+        self.model.edit_mode = EditMode::Note(note);
+        self.label_text.grab_focus();
+    }
+
+    /// Places the previewed note at `cursor` and leaves the note mode, as notes are rarely placed in rows like labels.
+    fn place_note(&mut self, cursor: &Point2) {
+        if let EditMode::Note(mut note) = self.model.edit_mode.clone() {
+            if note.text.is_empty() {
+                return;
+            }
+            self.model.drawer.write().unwrap().remove_drawable(&note.uuid);
+            note.position = *cursor;
+            let mut schema = self.model.schema.write().unwrap();
+            schema.add_note(note);
+            schema.commit();
+            self.model.edit_mode = EditMode::None;
+        }
+    }
+
+    /// Starts a note line at `cursor` or ends the previewed one there. Each line ends where the next one starts.
+    fn place_note_line(&mut self, cursor: &Point2) {
+        if let EditMode::NoteLine(Some(line)) = self.model.edit_mode.clone() {
+            // A second click on the same spot only restarts the line.
+            if line.start != *cursor {
+                let mut schema = self.model.schema.write().unwrap();
+                schema.add_note_line(NoteLine { end: *cursor, ..line });
+                schema.commit();
+            }
+        }
+        let line = NoteLine {
+            uuid: match self.model.edit_mode {
+                EditMode::NoteLine(Some(ref line)) => line.uuid,
+                _ => Uuid::new_v4(),
+            },
+            start: *cursor,
+            end: *cursor,
+        };
+        self.model.drawer.write().unwrap().update_note_line(line.clone());
+        self.model.edit_mode = EditMode::NoteLine(Some(line));
+    }
+
+    /// Replaces component instances with the versions edited in the component inspector.
+    /// All of the edits are undone at once.
+    pub fn update_component_instances(&mut self, instances: Vec<ComponentInstance>) {
+        {
+            let libraries = self.model.libraries.read().unwrap();
+            let mut schema = self.model.schema.write().unwrap();
+            for instance in instances {
+                schema.update_component(instance);
+            }
+            // A moved or rotated component can connect or disconnect its pins.
+            schema.cleanup_wires(&libraries);
+            schema.commit();
+        }
+        self.update_component_inspector();
+        self.update_selection_rectangle();
+        self.notify_view_state_changed();
+    }
+
+    /// Selects the match `step` matches away from the current match of the `query` and pans and zooms the view onto it.
+    /// A new query starts at its first match.
+    pub fn search(&mut self, query: SearchQuery, step: isize) {
+        let matches = match search::find(&self.model.schema.read().unwrap(), &query) {
+            Ok(matches) => matches,
+            Err(error) => {
+                self.send_to_search_panel(search_panel::Msg::Status(format!("Invalid pattern: {}", error)));
+                return;
+            },
+        };
+        if matches.is_empty() {
+            self.model.last_search = None;
+            self.send_to_search_panel(search_panel::Msg::Status("No matches".to_owned()));
+            return;
+        }
+
+        let count = matches.len() as isize;
+        let index = match self.model.last_search {
+            Some((ref last_query, index)) if *last_query == query => (((index as isize + step) % count + count) % count) as usize,
+            _ => 0,
+        };
+        self.model.last_search = Some((query, index));
+        self.send_to_search_panel(search_panel::Msg::Status(format!("{} of {}", index + 1, count)));
+
+        {
+            let mut view_state = self.model.view_state.write().unwrap();
+            view_state.selected_items.clear();
+            view_state.selected_items.insert(matches[index].get_uuid());
+        }
+        self.zoom_to_selection();
+        self.update_component_inspector();
+        self.update_selection_rectangle();
+        self.notify_view_state_changed();
+    }
+
+    /// Selects the component with the given reference and zooms onto it.
+    pub fn go_to_reference(&mut self, reference: &str) {
+        let uuid = self.model.schema.read().unwrap().find_component_by_reference(reference.trim()).map(|instance| instance.uuid);
+        if let Some(uuid) = uuid {
+            {
+                let mut view_state = self.model.view_state.write().unwrap();
+                view_state.selected_items.clear();
+                view_state.selected_items.insert(uuid);
+            }
+            self.zoom_to_selection();
+            self.update_component_inspector();
+            self.update_selection_rectangle();
+            self.notify_view_state_changed();
+        }
+        // Hand the keyboard back to the schema, such that the shortcuts work again.
+        self.window.set_focus(None::<&gtk::Widget>);
+    }
+
+    /// Fits the selected items into the view. Some of their surroundings are shown too.
+    fn zoom_to_selection(&mut self) {
+        let libraries = self.model.libraries.read().unwrap();
+        let schema = self.model.schema.read().unwrap();
+        let mut view_state = self.model.view_state.write().unwrap();
+        if let Some(aabb) = view_state.selected_items.get_grouped_aabb(&libraries, &schema) {
+            view_state.jump_to_box(aabb.loosened(ZOOM_MARGIN));
+        }
+    }
+
+    /// Replaces all the matches of the `query` in one undoable step.
+    pub fn replace_all(&mut self, query: SearchQuery, replacement: &str) {
+        let status = {
+            let mut schema = self.model.schema.write().unwrap();
+            let result = search::replace_all(&mut schema, &query, replacement);
+            schema.commit();
+            match result {
+                Ok(count) => format!("Replaced {} texts", count),
+                Err(error) => format!("Invalid pattern: {}", error),
+            }
+        };
+        self.model.last_search = None;
+        self.send_to_search_panel(search_panel::Msg::Status(status));
+        self.update_component_inspector();
+        self.notify_view_state_changed();
+    }
+
+    /// Shows the selected component instances in the component inspector.
+    fn update_component_inspector(&mut self) {
+        let instances = {
+            let view_state = self.model.view_state.read().unwrap();
+            let schema = self.model.schema.read().unwrap();
+            let libraries = self.model.libraries.read().unwrap();
+            view_state.selected_items.get_items().iter()
+                .filter_map(|uuid| schema.find_component_instance(uuid).cloned())
+                .map(|instance| {
+                    let limits = libraries.get_component_by_name(&instance.name).map_or(
+                        UnitLimits { units: 1, body_styles: 1 },
+                        |c| UnitLimits { units: (c.unit_count as usize).max(1), body_styles: c.get_body_style_count() }
+                    );
+                    (instance, limits)
+                })
+                .collect()
+        };
+        self.send_to_component_inspector(component_inspector::Msg::UpdateComponentInstances(instances));
+    }
+
+    /// Finishes the current drag if there is one.
+    /// Merges and splits wires and places or removes junctions such that they match the new state of the schema.
+    fn finish_drag(&mut self) {
+        let libraries = self.model.libraries.read().unwrap();
+        let mut schema = self.model.schema.write().unwrap();
+        match self.model.drag.take() {
+            Some(drag) => drag.finish(&mut schema, &libraries),
+            None => schema.cleanup_wires(&libraries),
         }
+        schema.commit();
     }
 }
\ No newline at end of file
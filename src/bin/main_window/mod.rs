@@ -23,8 +23,8 @@ use gtk::{
     CssProvider,
     CssProviderExt,
     OverlayExt,
-    EditableSignals,
     EntryExt,
+    ButtonExt,
     GestureLongPress,
     GestureLongPressExt,
     GridExt,
@@ -60,7 +60,13 @@ use components::component_selector;
 use components::component_selector::ComponentSelector;
 use components::component_inspector;
 use components::component_inspector::ComponentInspector;
+use components::search_panel;
+use components::search_panel::SearchPanel;
+use components::grid_panel;
+use components::grid_panel::GridPanel;
+use copper::state::schema::search::SearchQuery;
 use copper::state::schema::component_instance::ComponentInstance;
+use copper::viewing::grid::GridSettings;
 
 use copper::state::schema::*;
 use copper::state::component_libraries::*;
@@ -68,20 +74,34 @@ use copper::state::event::{ EventBus, EventMessage };
 
 use copper::loading::schema_loader;
 use copper::viewing::schema_viewer;
+use copper::viewing::net::Net;
 use copper::drawing::schema_drawer;
 
 use copper::loading::component_libraries_loader;
 use copper::drawing::schema_drawer::SchemaDrawer;
 use copper::parsing::kicad::schema::{
     WireSegment,
+    WireType,
+    Label,
+    Note,
+    NoteLine,
 };
 
 use copper::geometry::*;
 
+/// The environment variable which sets the number of steps that can be undone, instead of `DEFAULT_HISTORY_DEPTH`.
+const HISTORY_DEPTH_VARIABLE: &str = "COPPER_HISTORY_DEPTH";
+
 #[derive(Clone, Debug)]
 pub enum EditMode {
-    Wire(Vec<WireSegment>, bool), // wires, last wire is horizontal
+    Wire(Vec<WireSegment>, bool, WireType), // wires, last wire is horizontal, kind of the drawn wires (wire or bus)
     Component,
+    /// Places copies of the label, which is previewed at the cursor.
+    Label(Label),
+    /// Places the note, which is previewed at the cursor.
+    Note(Note),
+    /// Draws note lines, chained end to end. Holds the line previewed from the last click to the cursor, if there was a click.
+    NoteLine(Option<NoteLine>),
     None,
 }
 
@@ -105,7 +125,15 @@ pub struct Model {
     pub grabbed_rectangle: Option<Uuid>,
     pub hovered_rectangle: Option<Uuid>,
     pub span_rectangle: Option<Uuid>,
+    /// Marks the point the wire being drawn snaps onto.
+    pub snap_indicator: Option<Uuid>,
     pub button_pressed_location: Option<Point2>,
+    pub drag: Option<Drag>,
+    pub autoroute: bool,
+    /// The net which was clicked last. It is traced again whenever the conductors change.
+    pub highlighted_net: Option<Net>,
+    /// The last query of the search panel and the index of the match it is at.
+    pub last_search: Option<(SearchQuery, usize)>,
 }
 
 #[derive(Msg)]
@@ -122,8 +150,13 @@ pub enum Msg {
     ZoomOnSchema(f64, f64),
     KeyDown(EventKey),
     InstantiateComponent(ComponentInstance),
-    GridChanged,
-    ComponentInstanceUpdated(ComponentInstance)
+    GridChanged(GridSettings),
+    ComponentInstancesUpdated(Vec<ComponentInstance>),
+    Search(SearchQuery, isize),
+    GoToReference(String),
+    ReplaceAll(SearchQuery, String),
+    LabelTextEntered(String),
+    PlacePowerPort(String),
 }
 
 #[widget]
@@ -164,12 +197,34 @@ impl Widget for Win {
 
         let cs = &self.component_inspector;
         connect!(
-            cs@component_inspector::Msg::ComponentInstanceUpdated(ref ci),
+            cs@component_inspector::Msg::ComponentInstancesUpdated(ref instances),
+            &self.model.relm,
+            ComponentInstancesUpdated(instances.clone())
+        );
+
+        let sp = &self.search_panel;
+        connect!(
+            sp@search_panel::Msg::Search(ref query, step),
+            &self.model.relm,
+            Search(query.clone(), step)
+        );
+        connect!(
+            sp@search_panel::Msg::ReplaceAll(ref query, ref replacement),
             &self.model.relm,
-            ComponentInstanceUpdated(ci.clone())
+            ReplaceAll(query.clone(), replacement.clone())
         );
 
+        let gp = &self.grid_panel;
+        connect!(
+            gp@grid_panel::Msg::GridChanged(ref settings),
+            &self.model.relm,
+            GridChanged(settings.clone())
+        );
+        let grid = self.model.view_state.read().unwrap().grid.clone();
+        self.grid_panel.emit(grid_panel::Msg::Update(grid));
+
         self.model.component_selector.widget().hide();
+        self.search_panel.widget().hide();
 
         self.window.get_window().unwrap().set_event_compression(false);
 
@@ -186,6 +241,13 @@ impl Widget for Win {
         let schema = Arc::new(RwLock::new(Schema::new(event_bus.get_handle())));
         let libraries = Arc::new(RwLock::new(ComponentLibraries::new(event_bus.get_handle())));
 
+        if let Ok(depth) = env::var(HISTORY_DEPTH_VARIABLE) {
+            match depth.trim().parse() {
+                Ok(depth) => schema.write().unwrap().set_history_depth(depth),
+                Err(_) => println!("{} must be a number of undo steps, but it is {:?}.", HISTORY_DEPTH_VARIABLE, depth),
+            }
+        }
+
         let args: Vec<String> = env::args().collect();
         if args.len() != 3 {
             println!("Please specify a .lib and a .sch file.");
@@ -226,7 +288,12 @@ impl Widget for Win {
             grabbed_rectangle: None,
             hovered_rectangle: None,
             span_rectangle: None,
+            snap_indicator: None,
             button_pressed_location: None,
+            drag: None,
+            autoroute: false,
+            highlighted_net: None,
+            last_search: None,
         }
     }
 
@@ -251,8 +318,13 @@ impl Widget for Win {
             ZoomOnSchema(x, y) => self.zoom_on_schema(x, y),
             KeyDown(event) => self.key_down(event),
             InstantiateComponent(comp) => self.instantiate_component(comp),
-            GridChanged => self.grid_changed(),
-            ComponentInstanceUpdated(ci) => self.model.event_bus.get_handle().send(&EventMessage::UpdateComponent(ci)),
+            GridChanged(settings) => self.grid_changed(settings),
+            ComponentInstancesUpdated(instances) => self.update_component_instances(instances),
+            Search(query, step) => self.search(query, step),
+            GoToReference(reference) => self.go_to_reference(&reference),
+            ReplaceAll(query, replacement) => self.replace_all(query, &replacement),
+            LabelTextEntered(text) => self.set_label_text(text),
+            PlacePowerPort(name) => self.place_power_port(&name),
         }
     }
 
@@ -262,8 +334,10 @@ impl Widget for Win {
     }
 
     /// Notifies all `Listeners` and the `CursorInfo` of the changed ViewState.
+    /// The markers of unconnected ends and the highlighted net are brought up to date too, as every action which changes the schema ends here.
     fn notify_view_state_changed(&mut self) {
         self.model.event_bus.get_handle().send(&EventMessage::ViewStateChanged);
+        self.update_connectivity_markers();
         let view_state = self.model.view_state.read().unwrap();
         self.cursor_info.emit(cursor_info::Msg::ViewStateChanged(view_state.clone()));
         self.gl_area.queue_draw();
@@ -284,6 +358,14 @@ impl Widget for Win {
         self.component_inspector.emit(msg);
     }
 
+    pub fn send_to_search_panel(&mut self, msg: search_panel::Msg) {
+        self.search_panel.emit(msg);
+    }
+
+    pub fn send_to_grid_panel(&mut self, msg: grid_panel::Msg) {
+        self.grid_panel.emit(msg);
+    }
+
     /// Loads a `Schema` from a file given in the `env::args`.
     fn load_schema(schema_loader: &mut schema_loader::SchemaLoader, schema: Arc<RwLock<Schema>>, view_state: Arc<RwLock<ViewState>>, libraries: Arc<RwLock<ComponentLibraries>>) {
         /*
@@ -335,16 +417,42 @@ impl Widget for Win {
                     gtk::Box {
                         orientation: Horizontal,
 
-                        #[name="grid_x"]
+                        #[name="grid_panel"]
+                        GridPanel {
+
+                        },
+                        #[name="goto_reference"]
                         gtk::Entry {
-                            changed(_) => GridChanged,
-                            text: &self.model.view_state.read().unwrap().get_grid_size().x.to_string(),
+                            placeholder_text: "Go to reference",
+                            activate(entry) => GoToReference(entry.get_text().unwrap()),
                         },
-                        #[name="grid_y"]
+                        #[name="label_text"]
                         gtk::Entry {
-                            changed(_) => GridChanged,
-                            text: &self.model.view_state.read().unwrap().get_grid_size().y.to_string(),
+                            placeholder_text: "Label or note text",
+                            activate(entry) => LabelTextEntered(entry.get_text().unwrap()),
+                        },
+                        // A palette of the most common power ports.
+                        gtk::Button {
+                            label: "GND",
+                            clicked => PlacePowerPort("GND".to_owned()),
                         },
+                        gtk::Button {
+                            label: "+3V3",
+                            clicked => PlacePowerPort("+3V3".to_owned()),
+                        },
+                        gtk::Button {
+                            label: "+5V",
+                            clicked => PlacePowerPort("+5V".to_owned()),
+                        },
+                        gtk::Button {
+                            label: "VCC",
+                            clicked => PlacePowerPort("VCC".to_owned()),
+                        },
+                    },
+
+                    #[name="search_panel"]
+                    SearchPanel {
+
                     },
 
                     gtk::Grid {
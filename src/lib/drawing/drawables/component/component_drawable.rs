@@ -1,6 +1,8 @@
 use drawing;
 use geometry::Matrix4;
 use drawing::drawables::loaders;
+use drawing::drawables::loaders::pin::PIN_RADIUS;
+use parsing::kicad::component_library::GraphicElement;
 use state::schema::component::Component;
 
 
@@ -14,10 +16,25 @@ impl ComponentDrawable {
         component: &Component
     ) -> Self {
         // Generate all shapes for the component
-        let drawables = component.get_graphic_elements()
-                                 .iter()
-                                 .filter_map(|shape| loaders::load_drawable_from_graphic_element(component_id, &shape))
-                                 .collect::<Vec<_>>();
+        let mut drawables = component.get_graphic_elements()
+                                     .iter()
+                                     .filter_map(|shape| loaders::load_drawable_from_graphic_element(component_id, &shape))
+                                     .collect::<Vec<_>>();
+        drawables.extend(component.get_graphic_elements()
+                                  .iter()
+                                  .filter_map(|shape| loaders::load_text_from_graphic_element(component_id, &shape, None)));
+        // The component viewer marks the ends of all the pins, the schema only marks the unconnected ones.
+        drawables.extend(component.get_graphic_elements().iter().filter_map(|shape| match shape {
+            GraphicElement::Pin { position, .. } => {
+                Some(Box::new(loaders::load_circle(component_id, loaders::COMPONENT_COLOR, position, PIN_RADIUS, false)) as Box<super::super::Drawable>)
+            },
+            _ => None,
+        }));
+        // The fields of the component itself are shown untransformed.
+        drawables.extend(component.fields.iter().filter(|field| field.visible).map(|field| {
+            let placement = loaders::place_component_text(None, &field.position, &field.orientation, field.hjustify.clone(), field.vjustify.clone());
+            Box::new(loaders::load_field(component_id, field, &placement)) as Box<super::super::Drawable>
+        }));
 
         Self {
             drawables: drawables,
@@ -9,15 +9,72 @@ use drawing;
 use drawing::drawables;
 
 
+pub const LINE_WIDTH: f32 = 6.5;
+/// The length of a dash and of the gap after it in a dashed line.
+pub const DASH_LENGTH: f32 = 30.0;
+
 pub fn load_line(
     component_id: u32,
     color: drawing::Color,
     start: &geometry::Point2,
     end: &geometry::Point2,
+) -> drawables::ShapeDrawable {
+    load_line_with_width(component_id, color, start, end, LINE_WIDTH)
+}
+
+/// Loads a line which is split into dashes of `DASH_LENGTH`, starting with a dash at `start`.
+/// The dashes are tessellated instead of being cut out by a fragment shader, such that they keep their length in schema space at every zoom and along lines of every direction.
+pub fn load_dashed_line(
+    component_id: u32,
+    color: drawing::Color,
+    start: &geometry::Point2,
+    end: &geometry::Point2,
+) -> drawables::ShapeDrawable {
+    let mut mesh = VertexBuffers::new();
+
+    let w = StrokeOptions::default().with_line_width(LINE_WIDTH);
+
+    let direction = end - start;
+    let length = direction.norm();
+    // A line of zero length has no direction to dash along.
+    let step = if length > 0.0 { direction / length } else { direction };
+
+    let mut position = 0.0;
+    while position < length || length == 0.0 {
+        let dash_end = (position + DASH_LENGTH).min(length);
+        let a = start + step * position;
+        let b = start + step * dash_end;
+        let points = vec![Point::new(a.x, a.y), Point::new(b.x, b.y)];
+        let _ = stroke_polyline(points.into_iter(), false, &w, &mut BuffersBuilder::new(&mut mesh, drawing::VertexCtor));
+        if length == 0.0 {
+            break;
+        }
+        position += 2.0 * DASH_LENGTH;
+    }
+
+    let buffers = drawing::Buffers {
+        vbo: mesh.vertices.iter().map(|v| drawing::Vertex {
+            position: v.position.clone(),
+            color: color.color,
+            id: component_id,
+        }).collect(),
+        ibo: mesh.indices.iter().map(|i: &u16| *i as u32).collect(),
+        abo: vec![]
+    };
+
+    drawables::ShapeDrawable::new(buffers)
+}
+
+pub fn load_line_with_width(
+    component_id: u32,
+    color: drawing::Color,
+    start: &geometry::Point2,
+    end: &geometry::Point2,
+    width: f32,
 ) -> drawables::ShapeDrawable {
     let mut mesh = VertexBuffers::new();
 
-    let w = StrokeOptions::default().with_line_width(6.5);
+    let w = StrokeOptions::default().with_line_width(width);
 
     let is_closed = false;
 
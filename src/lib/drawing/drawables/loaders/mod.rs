@@ -1,6 +1,6 @@
 mod arc;
 mod circle;
-mod line;
+pub mod line;
 pub mod pin;
 mod polygon;
 mod rectangle;
@@ -9,16 +9,28 @@ mod text;
 
 pub use self::arc::load_arc;
 pub use self::circle::load_circle;
-pub use self::line::load_line;
-pub use self::pin::load_pin;
+pub use self::line::{
+    load_line,
+    load_line_with_width,
+    load_dashed_line,
+};
+pub use self::pin::{
+    load_pin,
+    load_pin_texts,
+};
 pub use self::polygon::load_polygon;
 pub use self::rectangle::load_rectangle;
-pub use self::text::load_text;
+pub use self::text::{
+    load_text,
+    load_field,
+    place_component_text,
+};
 
 use drawing;
 use drawing::drawables;
 use geometry::*;
 use parsing::kicad::component_library::*;
+use state::schema::component_instance::ComponentInstance;
 
 pub const VS_CODE: &[u8] = include_bytes!("../../shaders/shape.glslv");
 pub const FS_CODE: &[u8] = include_bytes!("../../shaders/shape.glslf");
@@ -26,6 +38,9 @@ pub const FS_CODE: &[u8] = include_bytes!("../../shaders/shape.glslf");
 pub const VS_RENDER_CODE: &[u8] = include_bytes!("../../shaders/render.glslv");
 pub const FS_RENDER_CODE: &[u8] = include_bytes!("../../shaders/render.glslf");
 
+/// The color KiCad draws the bodies, pins and texts of components with.
+pub const COMPONENT_COLOR: drawing::Color = drawing::Color { color: [0.61, 0.05, 0.04, 1.0] };
+
 pub fn load_drawable_from_graphic_element(
     component_id: u32,
     shape: &GraphicElement,
@@ -34,7 +49,7 @@ pub fn load_drawable_from_graphic_element(
         &GraphicElement::CircleArc { ref center, radius, filled, start_angle, end_angle, .. } => {
             Some(Box::new(drawables::loaders::load_arc(
                 component_id,
-                drawing::Color::new(0.61, 0.05, 0.04, 1.0),
+                COMPONENT_COLOR,
                 &center.clone(),
                 radius,
                 filled,
@@ -57,34 +72,70 @@ pub fn load_drawable_from_graphic_element(
             );
             Some(Box::new(drawables::loaders::load_rectangle(
                 component_id,
-                if !filled { None } else { Some(drawing::Color::new(0.61, 0.05, 0.04, 1.0)) },
-                if !filled { Some(drawing::Color::new(0.61, 0.05, 0.04, 1.0)) } else { None },
+                if !filled { None } else { Some(COMPONENT_COLOR) },
+                if !filled { Some(COMPONENT_COLOR) } else { None },
                 &r
             )))
         }
         &GraphicElement::Circle { ref center, radius, filled, .. } => {
             Some(Box::new(drawables::loaders::load_circle(
                 component_id,
-                drawing::Color::new(0.61, 0.05, 0.04, 1.0),
+                COMPONENT_COLOR,
                 &center.clone(),
                 radius, filled
             )))
         },
-        &GraphicElement::Pin { ref orientation, ref position, length, ref name, number, number_size, name_size, .. } => {
+        &GraphicElement::Pin { ref orientation, ref position, length, .. } => {
             Some(Box::new(drawables::loaders::load_pin(
                 component_id,
                 &(position.clone()),
-                length as f32, orientation, name.clone(), number, number_size, name_size
+                length as f32, orientation
             )))
         },
+        // Texts are loaded by `load_text_from_graphic_element`.
+        &GraphicElement::TextField { .. } => None,
         &GraphicElement::Polygon { ref points, filled, .. } => {
             Some(Box::new(drawables::loaders::load_polygon(
                 component_id,
-                drawing::Color::new(0.61, 0.05, 0.04, 1.0),
+                COMPONENT_COLOR,
                 &points.iter().map(|point| Point2::new(point.x, point.y)).collect(),
                 filled
             )))
         },
-        _ => None
     }
-}
\ No newline at end of file
+}
+
+/// Loads the texts of a graphic element, which are the name and the number of a pin or the content of a text field.
+/// The texts of an `instance` are loaded in schema space, such that they are drawn without the transform of the instance and stay readable.
+pub fn load_text_from_graphic_element(
+    component_id: u32,
+    shape: &GraphicElement,
+    instance: Option<&ComponentInstance>,
+) -> Option<Box<drawables::Drawable>> {
+    match shape {
+        &GraphicElement::Pin { ref orientation, ref position, length, ref name, number, number_size, name_size, .. } => {
+            Some(Box::new(drawables::loaders::load_pin_texts(
+                component_id,
+                instance,
+                position,
+                length as f32, orientation, name.clone(), number, number_size, name_size
+            )))
+        },
+        &GraphicElement::TextField { ref content, ref orientation, ref position, dimension, italic, bold, ref hjustify, ref vjustify, .. } => {
+            let placement = place_component_text(instance, position, orientation, hjustify.clone(), vjustify.clone());
+            Some(Box::new(drawables::loaders::load_text(
+                component_id,
+                COMPONENT_COLOR,
+                &placement.position,
+                content,
+                dimension as f32,
+                &placement.orientation,
+                placement.hjustify,
+                placement.vjustify,
+                italic,
+                bold
+            )))
+        },
+        _ => None,
+    }
+}
@@ -2,27 +2,47 @@ use geometry;
 use drawing;
 use drawing::drawables;
 use parsing::kicad::component_library::*;
+use state::schema::component_instance::ComponentInstance;
 
 
+/// The radius of the circle which marks an unconnected pin end in the schema.
 pub const PIN_RADIUS: f32 = 10.0;
 
+/// Loads the line of a pin. Its name and number are loaded apart by `load_pin_texts`.
 pub fn load_pin(
     component_id: u32,
     position: &geometry::Point2,
     length: f32,
     orientation: &PinOrientation,
+) -> drawables::GroupDrawable {
+    // Create a new group drawable
+    let mut group = drawables::GroupDrawable::default();
+
+    let end_position = get_end_position(position, length, orientation);
+    let line = super::load_line(component_id, super::COMPONENT_COLOR, position, &end_position);
+
+    group.add(line);
+
+    group
+}
+
+/// Loads the name and the number of a pin.
+/// The texts of a pin of an `instance` are loaded in schema space, such that they stay readable however the instance is turned.
+pub fn load_pin_texts(
+    component_id: u32,
+    instance: Option<&ComponentInstance>,
+    position: &geometry::Point2,
+    length: f32,
+    orientation: &PinOrientation,
     name: Option<String>,
     number: usize,
     number_size: usize,
     name_size: usize
 ) -> drawables::GroupDrawable {
-    // Create a new group drawable
     let mut group = drawables::GroupDrawable::default();
 
-    let circle = super::load_circle(component_id, drawing::Color::new(0.61, 0.05, 0.04, 1.0), position, PIN_RADIUS, false);
-
     let orientation_vec = geometry::Vector2::new(orientation.unit_vec().x, orientation.unit_vec().y);
-    let end_position = position.clone() + (orientation_vec * length);
+    let end_position = get_end_position(position, length, orientation);
 
     let number_pos = end_position + (orientation_vec * -10.0);
     let number_pos = geometry::Point2::new(number_pos.x, number_pos.y + 60.0);
@@ -41,7 +61,11 @@ pub fn load_pin(
         &PinOrientation::Left => Justify::Left
     };
 
-    let number_text = super::load_text(&number_pos, &format!("{}", number), number_size as f32, &number_orientation, number_hjustify, Justify::Center);
+    let placement = super::place_component_text(instance, &number_pos, &number_orientation, number_hjustify, Justify::Center);
+    let number_text = super::load_text(
+        component_id, super::COMPONENT_COLOR, &placement.position, &format!("{}", number), number_size as f32,
+        &placement.orientation, placement.hjustify, placement.vjustify, false, false
+    );
 
     if let Some(name) = name {
         let name_pos = end_position + orientation_vec * 20.0;
@@ -52,15 +76,21 @@ pub fn load_pin(
             &PinOrientation::Right => Justify::Left,
             &PinOrientation::Left => Justify::Right
         };
-        let name_text = super::load_text(&name_pos, &name, name_size as f32, &number_orientation, name_hjustify, Justify::Center);
+        let placement = super::place_component_text(instance, &name_pos, &number_orientation, name_hjustify, Justify::Center);
+        let name_text = super::load_text(
+            component_id, drawing::Color::new(0.0, 0.52, 0.52, 1.0), &placement.position, &name, name_size as f32,
+            &placement.orientation, placement.hjustify, placement.vjustify, false, false
+        );
         group.add(name_text);
     }
 
-    let line = super::load_line(component_id, drawing::Color::new(0.61, 0.05, 0.04, 1.0), position, &end_position);
-
-    group.add(line);
-    group.add(circle);
     group.add(number_text);
 
     group
-}
\ No newline at end of file
+}
+
+/// Returns the end of the pin which lies at the body of the component.
+fn get_end_position(position: &geometry::Point2, length: f32, orientation: &PinOrientation) -> geometry::Point2 {
+    let orientation_vec = geometry::Vector2::new(orientation.unit_vec().x, orientation.unit_vec().y);
+    position.clone() + (orientation_vec * length)
+}
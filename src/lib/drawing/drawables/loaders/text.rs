@@ -1,22 +1,215 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use lyon::tessellation::{
+    FillOptions,
+    FillTessellator,
+    StrokeOptions,
+    StrokeTessellator,
+    geometry_builder::{
+        VertexBuffers,
+        BuffersBuilder,
+    }
+};
+
+use drawing;
 use drawing::drawables;
 use geometry;
+use geometry::Vector2;
 use parsing::kicad::component_library::*;
+use state::schema::component::Field;
+use state::schema::component_instance::{
+    ComponentInstance,
+    TextPlacement,
+    REFERENCE_FIELD,
+    VALUE_FIELD,
+};
+use utils::text::{
+    layout_text,
+    get_descent,
+    glyph_path,
+};
+
+
+/// Glyphs are tessellated once at this size and scaled to the size of every text they appear in.
+const GLYPH_SIZE: f32 = 1000.0;
+/// The largest deviation of a tessellated outline from the true one at `GLYPH_SIZE`, which is far below a pixel at any sensible zoom.
+const GLYPH_TOLERANCE: f32 = 0.5;
+/// The width of the stroke which thickens bold glyphs, relative to the text size.
+const BOLD_WIDTH: f32 = 0.08;
+/// How far italic glyphs lean to the right per unit of height.
+const ITALIC_SLANT: f32 = 0.2;
+
+/// The triangles of a tessellated glyph at `GLYPH_SIZE`.
+struct GlyphMesh {
+    positions: Vec<[f32; 2]>,
+    indices: Vec<u32>,
+}
 
+thread_local! {
+    /// Tessellated glyphs by character and boldness.
+    static GLYPH_MESHES: RefCell<HashMap<(char, bool), GlyphMesh>> = RefCell::new(HashMap::new());
+}
 
+/// Loads `content` as filled glyph outlines.
+/// The justification tells which side of the text lies at `position`, vertical text reads from bottom to top.
+/// The text spans `dimension` from the lowest to the highest point of the font, like the boxes of `utils::text::text_aabb`.
 pub fn load_text(
+    component_id: u32,
+    color: drawing::Color,
     position: &geometry::Point2,
-    content: &String,
+    content: &str,
     dimension: f32,
     orientation: &TextOrientation,
     hjustify: Justify,
-    vjustify: Justify
+    vjustify: Justify,
+    italic: bool,
+    bold: bool,
+) -> drawables::TextDrawable {
+    let (characters, width) = layout_text(content, dimension);
+    let x0 = match hjustify {
+        Justify::Left => 0.0,
+        Justify::Right => -width,
+        _ => -width / 2.0,
+    };
+    let y0 = match vjustify {
+        Justify::Top => -dimension,
+        Justify::Bottom => 0.0,
+        _ => -dimension / 2.0,
+    };
+    // The baseline lies above the bottom of the text by the depth of the descenders.
+    let baseline = y0 - get_descent(dimension);
+    let scale = dimension / GLYPH_SIZE;
+    let slant = if italic { ITALIC_SLANT } else { 0.0 };
+
+    let mut buffers = drawing::Buffers {
+        vbo: vec![],
+        ibo: vec![],
+        abo: vec![]
+    };
+    for (character, origin) in characters {
+        with_glyph_mesh(character, bold, |mesh| {
+            let offset = buffers.vbo.len() as u32;
+            buffers.vbo.extend(mesh.positions.iter().map(|p| {
+                let x = x0 + origin + (p[0] + p[1] * slant) * scale;
+                let y = baseline + p[1] * scale;
+                let point = position + match orientation {
+                    TextOrientation::Horizontal => Vector2::new(x, y),
+                    TextOrientation::Vertical => Vector2::new(-y, x),
+                };
+                drawing::Vertex {
+                    position: [point.x, point.y],
+                    color: color.color,
+                    id: component_id,
+                }
+            }));
+            buffers.ibo.extend(mesh.indices.iter().map(|i| i + offset));
+        });
+    }
+
+    drawables::TextDrawable::new(buffers)
+}
+
+/// Loads a field with the given `placement`, which differs from the one of the field when it belongs to a turned or mirrored instance.
+/// The reference and the value are drawn in the color KiCad uses for them, all the other fields in gray.
+pub fn load_field(
+    component_id: u32,
+    field: &Field,
+    placement: &TextPlacement,
 ) -> drawables::TextDrawable {
-    drawables::TextDrawable {
-        position: position.clone(),
-        content: content.clone(),
-        dimension: dimension,
-        orientation: orientation.clone(),
-        hjustify: hjustify,
-        vjustify: vjustify
+    let color = match field.n {
+        REFERENCE_FIELD | VALUE_FIELD => drawing::Color::new(0.0, 0.52, 0.52, 1.0),
+        _ => drawing::Color::new(0.52, 0.52, 0.52, 1.0),
+    };
+    load_text(
+        component_id,
+        color,
+        &placement.position,
+        &field.text,
+        field.dimension as f32,
+        &placement.orientation,
+        placement.hjustify.clone(),
+        placement.vjustify.clone(),
+        field.italic,
+        field.bold
+    )
+}
+
+/// Returns the placement of a text which is given in the coordinate system of a component.
+/// The texts of an `instance` are placed in schema space, see `ComponentInstance::place_text`, the texts of a component stay as they are.
+pub fn place_component_text(
+    instance: Option<&ComponentInstance>,
+    position: &geometry::Point2,
+    orientation: &TextOrientation,
+    hjustify: Justify,
+    vjustify: Justify,
+) -> TextPlacement {
+    match instance {
+        Some(instance) => instance.place_text(instance.transform_point(position), orientation, &hjustify, &vjustify),
+        None => TextPlacement {
+            position: *position,
+            orientation: orientation.clone(),
+            hjustify: hjustify,
+            vjustify: vjustify,
+        },
+    }
+}
+
+/// Calls `f` with the mesh of the glyph of `character`, which is tessellated on first use.
+fn with_glyph_mesh<F: FnOnce(&GlyphMesh)>(character: char, bold: bool, f: F) {
+    GLYPH_MESHES.with(|meshes| {
+        let mut meshes = meshes.borrow_mut();
+        let mesh = meshes.entry((character, bold)).or_insert_with(|| tessellate_glyph(character, bold));
+        f(mesh)
+    })
+}
+
+/// Fills the outline of the glyph of `character` at `GLYPH_SIZE`. Bold glyphs get their outline stroked on top, which makes them thicker.
+fn tessellate_glyph(character: char, bold: bool) -> GlyphMesh {
+    let mut mesh = VertexBuffers::new();
+    if let Some(path) = glyph_path(character, GLYPH_SIZE) {
+        let _ = FillTessellator::new().tessellate_path(
+            path.path_iter(),
+            &FillOptions::default().with_tolerance(GLYPH_TOLERANCE),
+            &mut BuffersBuilder::new(&mut mesh, drawing::VertexCtor)
+        );
+        if bold {
+            let _ = StrokeTessellator::new().tessellate_path(
+                path.path_iter(),
+                &StrokeOptions::default().with_tolerance(GLYPH_TOLERANCE).with_line_width(GLYPH_SIZE * BOLD_WIDTH),
+                &mut BuffersBuilder::new(&mut mesh, drawing::VertexCtor)
+            );
+        }
+    }
+    GlyphMesh {
+        positions: mesh.vertices.iter().map(|v: &drawing::Vertex| v.position).collect(),
+        indices: mesh.indices.iter().map(|i: &u16| *i as u32).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use drawing::drawables::Drawable;
+    use utils::text::text_aabb;
+
+    #[test]
+    fn text_lies_in_its_box() {
+        let position = geometry::Point2::new(100.0, 200.0);
+        let color = drawing::Color::new(0.0, 0.0, 0.0, 1.0);
+        for orientation in &[TextOrientation::Horizontal, TextOrientation::Vertical] {
+            for (hjustify, vjustify) in vec![(Justify::Left, Justify::Bottom), (Justify::Center, Justify::Center), (Justify::Right, Justify::Top)] {
+                let aabb = text_aabb(&position, "Ag1", 50.0, orientation, &hjustify, &vjustify);
+                let text = load_text(0, color, &position, "Ag1", 50.0, orientation, hjustify, vjustify, false, false);
+                let mut buffers = drawing::Buffers { vbo: vec![], ibo: vec![], abo: vec![] };
+                text.draw(&mut buffers, false);
+                assert!(!buffers.ibo.is_empty());
+                // Glyphs may overhang their advance a little.
+                assert!(buffers.vbo.iter().all(|v| {
+                    v.position[0] > aabb.mins().x - 5.0 && v.position[0] < aabb.maxs().x + 5.0
+                    && v.position[1] > aabb.mins().y - 5.0 && v.position[1] < aabb.maxs().y + 5.0
+                }));
+            }
+        }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,44 @@
+use drawing;
+use drawing::drawables;
+use geometry;
+use geometry::*;
+use parsing::kicad::schema::*;
+use drawing::drawables::loaders::line::LINE_WIDTH;
+use super::wire_drawable::BUS_WIDTH;
+use super::super::Drawable;
+
+pub struct BusEntryDrawable {
+    pub entry: Box<Drawable>,
+    transform: Matrix4,
+}
+
+impl BusEntryDrawable {
+    pub fn from_schema(component_id: u32, entry: &BusEntry) -> BusEntryDrawable {
+        let (color, width) = match entry.kind {
+            BusEntryType::WireToBus => (drawing::Color::new(0.0, 0.28, 0.0, 1.0), LINE_WIDTH),
+            BusEntryType::BusToBus => (drawing::Color::new(0.0, 0.0, 0.52, 1.0), BUS_WIDTH),
+        };
+        BusEntryDrawable {
+            entry: Box::new(drawables::loaders::load_line_with_width(component_id, color, &entry.start, &entry.end, width)),
+            transform: Matrix4::identity(),
+        }
+    }
+}
+
+impl Drawable for BusEntryDrawable {
+    fn draw(&self, buffers: &mut drawing::Buffers, _transform: bool){
+        buffers.abo.push(drawing::Attributes {
+            transform: geometry::Matrix4::identity().into()
+        });
+        self.entry.draw(buffers, false);
+    }
+    fn get_transform(&self) -> Matrix4 {
+        self.transform
+    }
+    fn set_transform(&mut self, transform: &Matrix4) {
+        self.transform = transform.clone();
+    }
+    fn set_id(&mut self, id: u32) {
+        self.entry.set_id(id);
+    }
+}
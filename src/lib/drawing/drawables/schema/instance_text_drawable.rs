@@ -0,0 +1,55 @@
+use drawing;
+use drawing::drawables;
+use geometry;
+use geometry::*;
+use state::schema::component::Component;
+use state::schema::component_instance::ComponentInstance;
+use super::super::Drawable;
+
+/// The visible fields of a component instance together with the names and numbers of its pins and the texts of its component.
+/// They are drawn apart from the instance itself, as they must not be turned upside down or mirrored with it.
+pub struct InstanceTextDrawable {
+    pub texts: Vec<Box<Drawable>>,
+    transform: Matrix4,
+}
+
+impl InstanceTextDrawable {
+    /// Loads the texts of the `instance`. Only the fields are loaded if its `component` is not known.
+    pub fn from_schema(component_id: u32, instance: &ComponentInstance, component: Option<&Component>) -> InstanceTextDrawable {
+        let mut texts = instance.fields.iter().filter(|field| field.visible).map(|field| {
+            let placement = instance.get_field_placement(field);
+            Box::new(drawables::loaders::load_field(component_id, field, &placement)) as Box<Drawable>
+        }).collect::<Vec<_>>();
+        if let Some(component) = component {
+            texts.extend(component.get_graphic_elements()
+                .iter()
+                .filter_map(|shape| drawables::loaders::load_text_from_graphic_element(component_id, shape, Some(instance))));
+        }
+        InstanceTextDrawable {
+            texts: texts,
+            transform: Matrix4::identity(),
+        }
+    }
+}
+
+impl Drawable for InstanceTextDrawable {
+    fn draw(&self, buffers: &mut drawing::Buffers, _transform: bool){
+        buffers.abo.push(drawing::Attributes {
+            transform: geometry::Matrix4::identity().into()
+        });
+        for text in &self.texts {
+            text.draw(buffers, false);
+        }
+    }
+    fn get_transform(&self) -> Matrix4 {
+        self.transform
+    }
+    fn set_transform(&mut self, transform: &Matrix4) {
+        self.transform = transform.clone();
+    }
+    fn set_id(&mut self, id: u32) {
+        for text in &mut self.texts {
+            text.set_id(id);
+        }
+    }
+}
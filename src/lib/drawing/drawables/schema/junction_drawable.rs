@@ -0,0 +1,42 @@
+use drawing;
+use drawing::drawables;
+use geometry;
+use geometry::*;
+use parsing::kicad::schema::*;
+use super::super::Drawable;
+
+/// KiCad draws junctions as dots with a diameter of 40 mils.
+pub const JUNCTION_RADIUS: f32 = 20.0;
+
+pub struct JunctionDrawable {
+    pub junction: Box<Drawable>,
+    transform: Matrix4,
+}
+
+impl JunctionDrawable {
+    pub fn from_schema(component_id: u32, junction: &Junction) -> JunctionDrawable {
+        let color = drawing::Color::new(0.0, 0.28, 0.0, 1.0);
+        JunctionDrawable {
+            junction: Box::new(drawables::loaders::load_circle(component_id, color, &junction.position, JUNCTION_RADIUS, true)),
+            transform: Matrix4::identity(),
+        }
+    }
+}
+
+impl Drawable for JunctionDrawable {
+    fn draw(&self, buffers: &mut drawing::Buffers, _transform: bool){
+        buffers.abo.push(drawing::Attributes {
+            transform: geometry::Matrix4::identity().into()
+        });
+        self.junction.draw(buffers, false);
+    }
+    fn get_transform(&self) -> Matrix4 {
+        self.transform
+    }
+    fn set_transform(&mut self, transform: &Matrix4) {
+        self.transform = transform.clone();
+    }
+    fn set_id(&mut self, id: u32) {
+        self.junction.set_id(id);
+    }
+}
@@ -0,0 +1,115 @@
+use drawing;
+use drawing::drawables;
+use geometry;
+use geometry::*;
+use parsing::kicad::schema::*;
+use parsing::kicad::component_library::{
+    TextOrientation,
+    Justify,
+};
+use utils::text::text_width;
+use super::super::Drawable;
+
+/// The distance of the text of a local label from the wire it sits on, relative to the text size.
+const TEXT_OFFSET: f32 = 0.2;
+
+pub struct LabelDrawable {
+    pub text: Box<Drawable>,
+    pub outline: Option<Box<Drawable>>,
+    transform: Matrix4,
+}
+
+impl LabelDrawable {
+    pub fn from_schema(component_id: u32, label: &Label) -> LabelDrawable {
+        let color = match label.kind {
+            LabelKind::Local => drawing::Color::new(0.0, 0.0, 0.0, 1.0),
+            LabelKind::Global => drawing::Color::new(0.52, 0.0, 0.0, 1.0),
+            LabelKind::Hierarchical => drawing::Color::new(0.52, 0.52, 0.0, 1.0),
+        };
+        let orientation = if label.orientation % 2 == 0 { TextOrientation::Horizontal } else { TextOrientation::Vertical };
+        // The text of a label pointing left or down still reads from left to right or upwards, so it ends at the position.
+        let hjustify = if label.orientation < 2 { Justify::Left } else { Justify::Right };
+
+        let (text_position, vjustify, outline) = match label.kind {
+            LabelKind::Local => {
+                // The text sits above or left of the wire it names.
+                let offset = if label.orientation % 2 == 0 { Vector2::new(0.0, 1.0) } else { Vector2::new(-1.0, 0.0) };
+                (label.position + offset * label.size * TEXT_OFFSET, Justify::Bottom, None)
+            },
+            _ => {
+                let half_height = label.size * 0.7;
+                let length = text_width(&label.text, label.size) + half_height;
+                let mut points = get_outline(label.shape, half_height, length).iter()
+                    .map(|point| label.position + rotate_quarter_turns(point, label.orientation))
+                    .collect::<Vec<_>>();
+                // `load_polygon` strokes an open polyline, so the outline is closed by returning to its first point.
+                let first = points[0];
+                points.push(first);
+                let outline = drawables::loaders::load_polygon(component_id, color, &points, false);
+                let text_position = label.position + rotate_quarter_turns(&Vector2::new(half_height * 1.5, 0.0), label.orientation);
+                (text_position, Justify::Center, Some(Box::new(outline) as Box<Drawable>))
+            },
+        };
+
+        LabelDrawable {
+            text: Box::new(drawables::loaders::load_text(component_id, color, &text_position, &label.text, label.size, &orientation, hjustify, vjustify, false, false)),
+            outline: outline,
+            transform: Matrix4::identity(),
+        }
+    }
+}
+
+/// Returns the outline of a global or hierarchical label running to the right from the origin.
+/// The outline points away from the origin for outputs, towards it for inputs and both ways for bidirectional ones.
+fn get_outline(shape: LabelShape, half_height: f32, length: f32) -> Vec<Vector2> {
+    let h = half_height;
+    let l = length;
+    match shape {
+        LabelShape::Input => vec![
+            Vector2::new(0.0, 0.0), Vector2::new(h, h), Vector2::new(l + h, h), Vector2::new(l + h, -h), Vector2::new(h, -h),
+        ],
+        LabelShape::Output => vec![
+            Vector2::new(0.0, h), Vector2::new(l, h), Vector2::new(l + h, 0.0), Vector2::new(l, -h), Vector2::new(0.0, -h),
+        ],
+        LabelShape::BiDi | LabelShape::TriState => vec![
+            Vector2::new(0.0, 0.0), Vector2::new(h, h), Vector2::new(l, h), Vector2::new(l + h, 0.0), Vector2::new(l, -h), Vector2::new(h, -h),
+        ],
+        LabelShape::Passive => vec![
+            Vector2::new(0.0, h), Vector2::new(l + h, h), Vector2::new(l + h, -h), Vector2::new(0.0, -h),
+        ],
+    }
+}
+
+/// Turns `vector` by the given number of quarter turns counter clockwise.
+fn rotate_quarter_turns(vector: &Vector2, turns: usize) -> Vector2 {
+    match turns % 4 {
+        0 => *vector,
+        1 => Vector2::new(-vector.y, vector.x),
+        2 => -*vector,
+        _ => Vector2::new(vector.y, -vector.x),
+    }
+}
+
+impl Drawable for LabelDrawable {
+    fn draw(&self, buffers: &mut drawing::Buffers, _transform: bool){
+        buffers.abo.push(drawing::Attributes {
+            transform: geometry::Matrix4::identity().into()
+        });
+        if let Some(outline) = &self.outline {
+            outline.draw(buffers, false);
+        }
+        self.text.draw(buffers, false);
+    }
+    fn get_transform(&self) -> Matrix4 {
+        self.transform
+    }
+    fn set_transform(&mut self, transform: &Matrix4) {
+        self.transform = transform.clone();
+    }
+    fn set_id(&mut self, id: u32) {
+        if let Some(outline) = &mut self.outline {
+            outline.set_id(id);
+        }
+        self.text.set_id(id);
+    }
+}
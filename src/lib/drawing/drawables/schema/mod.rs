@@ -1,5 +1,17 @@
 mod wire_drawable;
+mod bus_entry_drawable;
+mod junction_drawable;
+mod label_drawable;
+mod note_drawable;
+mod note_line_drawable;
 mod component_instance_drawable;
+mod instance_text_drawable;
 
 pub use self::wire_drawable::WireDrawable;
-pub use self::component_instance_drawable::ComponentInstanceDrawable;
\ No newline at end of file
+pub use self::bus_entry_drawable::BusEntryDrawable;
+pub use self::junction_drawable::JunctionDrawable;
+pub use self::label_drawable::LabelDrawable;
+pub use self::note_drawable::NoteDrawable;
+pub use self::note_line_drawable::NoteLineDrawable;
+pub use self::component_instance_drawable::ComponentInstanceDrawable;
+pub use self::instance_text_drawable::InstanceTextDrawable;
\ No newline at end of file
@@ -0,0 +1,52 @@
+use drawing;
+use drawing::drawables;
+use geometry;
+use geometry::*;
+use parsing::kicad::schema::*;
+use parsing::kicad::component_library::Justify;
+use super::super::Drawable;
+
+pub struct NoteDrawable {
+    pub lines: Vec<Box<Drawable>>,
+    transform: Matrix4,
+}
+
+impl NoteDrawable {
+    pub fn from_schema(component_id: u32, note: &Note) -> NoteDrawable {
+        let color = drawing::Color::new(0.0, 0.0, 0.52, 1.0);
+        let (orientation, hjustify) = note.get_text_placement();
+
+        NoteDrawable {
+            lines: note.get_lines().into_iter().map(|(position, line)| {
+                // KiCad stores the pen width of notes, but only tells bold from normal text by it.
+                let text = drawables::loaders::load_text(
+                    component_id, color, &position, line, note.size, &orientation, hjustify.clone(), Justify::Bottom, note.italic, note.thickness != 0
+                );
+                Box::new(text) as Box<Drawable>
+            }).collect(),
+            transform: Matrix4::identity(),
+        }
+    }
+}
+
+impl Drawable for NoteDrawable {
+    fn draw(&self, buffers: &mut drawing::Buffers, _transform: bool){
+        buffers.abo.push(drawing::Attributes {
+            transform: geometry::Matrix4::identity().into()
+        });
+        for line in &self.lines {
+            line.draw(buffers, false);
+        }
+    }
+    fn get_transform(&self) -> Matrix4 {
+        self.transform
+    }
+    fn set_transform(&mut self, transform: &Matrix4) {
+        self.transform = transform.clone();
+    }
+    fn set_id(&mut self, id: u32) {
+        for line in &mut self.lines {
+            line.set_id(id);
+        }
+    }
+}
@@ -0,0 +1,39 @@
+use drawing;
+use drawing::drawables;
+use geometry;
+use geometry::*;
+use parsing::kicad::schema::*;
+use super::super::Drawable;
+
+pub struct NoteLineDrawable {
+    pub line: Box<Drawable>,
+    transform: Matrix4,
+}
+
+impl NoteLineDrawable {
+    pub fn from_schema(component_id: u32, line: &NoteLine) -> NoteLineDrawable {
+        let color = drawing::Color::new(0.0, 0.0, 0.48, 1.0);
+        NoteLineDrawable {
+            line: Box::new(drawables::loaders::load_dashed_line(component_id, color, &line.start, &line.end)),
+            transform: Matrix4::identity(),
+        }
+    }
+}
+
+impl Drawable for NoteLineDrawable {
+    fn draw(&self, buffers: &mut drawing::Buffers, _transform: bool){
+        buffers.abo.push(drawing::Attributes {
+            transform: geometry::Matrix4::identity().into()
+        });
+        self.line.draw(buffers, false);
+    }
+    fn get_transform(&self) -> Matrix4 {
+        self.transform
+    }
+    fn set_transform(&mut self, transform: &Matrix4) {
+        self.transform = transform.clone();
+    }
+    fn set_id(&mut self, id: u32) {
+        self.line.set_id(id);
+    }
+}
@@ -3,8 +3,12 @@ use drawing::drawables;
 use geometry;
 use geometry::*;
 use parsing::kicad::schema::*;
+use drawing::drawables::loaders::line::LINE_WIDTH;
 use super::super::Drawable;
 
+/// Buses are drawn a lot thicker than wires to tell them apart.
+pub const BUS_WIDTH: f32 = 20.0;
+
 pub struct WireDrawable {
     pub wire: Box<Drawable>,
     transform: Matrix4,
@@ -16,11 +20,14 @@ impl WireDrawable {
         let end = geometry::Point2::new(wire.end.x, wire.end.y);
         let color = match wire.kind {
             WireType::Wire => drawing::Color::new(0.0, 0.28, 0.0, 1.0),
-            WireType::Dotted => drawing::Color::new(0.0, 0.0, 0.48, 1.0),
-            _ => drawing::Color::new(0.0, 0.28, 0.0, 1.0)
+            WireType::Bus => drawing::Color::new(0.0, 0.0, 0.52, 1.0),
+        };
+        let width = match wire.kind {
+            WireType::Bus => BUS_WIDTH,
+            _ => LINE_WIDTH,
         };
         WireDrawable {
-            wire: Box::new(drawables::loaders::load_line(component_id, color, &start, &end)),
+            wire: Box::new(drawables::loaders::load_line_with_width(component_id, color, &start, &end, width)),
             transform: Matrix4::identity(),
         }
     }
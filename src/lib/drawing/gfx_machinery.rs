@@ -22,7 +22,6 @@ use drawing::drawables;
 use drawing::drawables::Drawable;
 
 use state::schema::ViewState;
-use utils::geometry::point_to_vector_2d;
 use geometry::*;
 
 /* Defines for gfx-rs/OGL pipeline */
@@ -32,6 +31,8 @@ pub type DepthFormat = gfx::format::DepthStencil;
 
 const CLEAR_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.0];
 const BACKGROUND_COLOR: [f32; 4] = [0.8, 0.8, 0.8, 1.0];
+/// The alpha component is how strongly the grid lines are blended over the background.
+const GRID_COLOR: [f32; 4] = [0.3, 0.3, 0.3, 0.5];
 
 const RENDER_CANVAS: [drawing::VertexRender; 6] = [
     drawing::VertexRender { position: [ -1.0, -1.0 ] },
@@ -232,12 +233,19 @@ impl GfxMachinery {
             }
         );
 
-        let grid_size = view_state.get_canvas_distance_from_schema_distance(&point_to_vector_2d(&view_state.get_grid_size().clone())).clone();
+        // The grid lines get sparser when zooming out, such that they never clutter the canvas.
+        let spacing = view_state.get_grid_line_spacing();
+        let grid_size = view_state.get_canvas_distance_from_schema_distance(&Vector2::new(spacing, spacing));
         let grid_origin = view_state.get_canvas_location_from_schema_location(&Point2::new(0.0, 0.0)) + grid_size * 0.5;
+        let mut grid_color = GRID_COLOR;
+        if !view_state.grid.visible {
+            grid_color[3] = 0.0;
+        }
 
         let globals = drawing::GlobalsRender {
             grid_size: grid_size.into(),
             background_color: BACKGROUND_COLOR.clone(),
+            grid_color: grid_color,
             grid_origin: grid_origin.coords.into(),
         };
 
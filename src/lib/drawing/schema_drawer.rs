@@ -2,6 +2,7 @@ use std::sync::{
     Arc,
     RwLock,
 };
+use std::collections::HashMap;
 
 use uuid::Uuid;
 
@@ -12,20 +13,49 @@ use state::component_libraries::*;
 use state::event::{Listener, EventMessage};
 
 use drawing;
-use drawing::drawables::loaders::load_rectangle;
+use drawing::drawables::loaders::{
+    load_rectangle,
+    load_line,
+    load_circle,
+};
+use drawing::drawables::loaders::pin::PIN_RADIUS;
 use drawing::drawables::Drawable;
 use drawing::drawables::schema::{
     ComponentInstanceDrawable,
-    WireDrawable
+    InstanceTextDrawable,
+    WireDrawable,
+    BusEntryDrawable,
+    JunctionDrawable,
+    LabelDrawable,
+    NoteDrawable,
+    NoteLineDrawable,
+};
+use parsing::kicad::schema::{
+    WireSegment,
+    Label,
+    Note,
+    NoteLine,
 };
-use parsing::kicad::schema::WireSegment;
+use viewing::net::Net;
+use viewing::schema_viewer::UnconnectedEnds;
+use utils::geometry::segment_aabb;
 use geometry::*;
 
+const HIGHLIGHT_COLOR: [f32; 4] = [1.0, 0.55, 0.0, 1.0];
+const UNCONNECTED_PIN_COLOR: [f32; 4] = [0.61, 0.05, 0.04, 1.0];
+const DANGLING_WIRE_COLOR: [f32; 4] = [0.0, 0.28, 0.0, 1.0];
+/// Half the width of the square which marks a dangling wire end.
+const DANGLING_MARKER_SIZE: f32 = 15.0;
+
 pub struct SchemaDrawer {
     _schema: Arc<RwLock<Schema>>,
     view_state: Arc<RwLock<ViewState>>,
     libraries: Arc<RwLock<ComponentLibraries>>,
     gfx_machinery: GfxMachinery,
+    highlighted_net: Vec<Uuid>,
+    unconnected_markers: Vec<Uuid>,
+    /// The `Uuid`s of the drawables which hold the texts of the component instances, by the `Uuid` of the instance.
+    text_drawables: HashMap<Uuid, Uuid>,
 }
 
 impl SchemaDrawer {
@@ -35,6 +65,9 @@ impl SchemaDrawer {
             view_state: view_state,
             libraries: libraries,
             gfx_machinery: GfxMachinery::new(),
+            highlighted_net: Vec::new(),
+            unconnected_markers: Vec::new(),
+            text_drawables: HashMap::new(),
         }
     }
 
@@ -72,6 +105,114 @@ impl SchemaDrawer {
         self.gfx_machinery.add_drawable(&wire.uuid, drawable_wire);
     }
 
+    pub fn add_label(&mut self, label: Label) {
+        let drawable_label = Box::new(LabelDrawable::from_schema(0, &label));
+        self.gfx_machinery.add_drawable(&label.uuid, drawable_label);
+    }
+
+    pub fn remove_label(&mut self, label: Label) {
+        self.gfx_machinery.remove_drawable(&label.uuid);
+    }
+
+    pub fn update_label(&mut self, label: Label) {
+        let drawable_label = Box::new(LabelDrawable::from_schema(0, &label));
+        self.gfx_machinery.remove_drawable(&label.uuid);
+        self.gfx_machinery.add_drawable(&label.uuid, drawable_label);
+    }
+
+    /// Draws the visible fields and the pin and component texts of the `instance`, replacing the ones drawn before.
+    pub fn update_texts(&mut self, instance: &ComponentInstance) {
+        self.remove_texts(&instance.uuid);
+        let drawable = {
+            let libraries = self.libraries.read().unwrap();
+            Box::new(InstanceTextDrawable::from_schema(0, instance, libraries.get_component_by_name(&instance.name)))
+        };
+        let uuid = Uuid::new_v4();
+        self.gfx_machinery.add_drawable(&uuid, drawable);
+        self.text_drawables.insert(instance.uuid, uuid);
+    }
+
+    pub fn remove_texts(&mut self, instance_uuid: &Uuid) {
+        if let Some(uuid) = self.text_drawables.remove(instance_uuid) {
+            self.gfx_machinery.remove_drawable(&uuid);
+        }
+    }
+
+    pub fn add_note(&mut self, note: Note) {
+        let drawable_note = Box::new(NoteDrawable::from_schema(0, &note));
+        self.gfx_machinery.add_drawable(&note.uuid, drawable_note);
+    }
+
+    pub fn remove_note(&mut self, note: Note) {
+        self.gfx_machinery.remove_drawable(&note.uuid);
+    }
+
+    pub fn update_note(&mut self, note: Note) {
+        let drawable_note = Box::new(NoteDrawable::from_schema(0, &note));
+        self.gfx_machinery.remove_drawable(&note.uuid);
+        self.gfx_machinery.add_drawable(&note.uuid, drawable_note);
+    }
+
+    pub fn add_note_line(&mut self, line: NoteLine) {
+        let drawable_line = Box::new(NoteLineDrawable::from_schema(0, &line));
+        self.gfx_machinery.add_drawable(&line.uuid, drawable_line);
+    }
+
+    pub fn remove_note_line(&mut self, line: NoteLine) {
+        self.gfx_machinery.remove_drawable(&line.uuid);
+    }
+
+    pub fn update_note_line(&mut self, line: NoteLine) {
+        let drawable_line = Box::new(NoteLineDrawable::from_schema(0, &line));
+        self.gfx_machinery.remove_drawable(&line.uuid);
+        self.gfx_machinery.add_drawable(&line.uuid, drawable_line);
+    }
+
+    /// Draws every wire, pin and label of the `net` in the highlight color.
+    /// Replaces any previously highlighted net.
+    pub fn highlight_net(&mut self, net: &Net) {
+        self.clear_highlighted_net();
+        let color = drawing::Color { color: HIGHLIGHT_COLOR };
+        for (start, end) in &net.segments {
+            let uuid = Uuid::new_v4();
+            self.gfx_machinery.add_drawable(&uuid, Box::new(load_line(0, color, start, end)));
+            self.highlighted_net.push(uuid);
+        }
+        for point in &net.points {
+            let uuid = Uuid::new_v4();
+            self.gfx_machinery.add_drawable(&uuid, Box::new(load_circle(0, color, point, PIN_RADIUS * 2.0, true)));
+            self.highlighted_net.push(uuid);
+        }
+    }
+
+    /// Removes the highlight of the currently highlighted net.
+    pub fn clear_highlighted_net(&mut self) {
+        for uuid in self.highlighted_net.drain(..) {
+            self.gfx_machinery.remove_drawable(&uuid);
+        }
+    }
+
+    /// Marks the `ends` like KiCad does, unconnected pins with a circle and dangling wire ends with a square.
+    /// Replaces any previously drawn markers.
+    pub fn show_unconnected_ends(&mut self, ends: &UnconnectedEnds) {
+        for uuid in self.unconnected_markers.drain(..) {
+            self.gfx_machinery.remove_drawable(&uuid);
+        }
+        for point in &ends.pins {
+            let uuid = Uuid::new_v4();
+            let color = drawing::Color { color: UNCONNECTED_PIN_COLOR };
+            self.gfx_machinery.add_drawable(&uuid, Box::new(load_circle(0, color, point, PIN_RADIUS, false)));
+            self.unconnected_markers.push(uuid);
+        }
+        for point in &ends.wire_ends {
+            let uuid = Uuid::new_v4();
+            let color = drawing::Color { color: DANGLING_WIRE_COLOR };
+            let square = segment_aabb(point, point, DANGLING_MARKER_SIZE);
+            self.gfx_machinery.add_drawable(&uuid, Box::new(load_rectangle(0, None, Some(color), &square)));
+            self.unconnected_markers.push(uuid);
+        }
+    }
+
     fn get_drawable_mut(&mut self, uuid: &Uuid) -> Option<&mut dyn Drawable> {
         self.gfx_machinery.get_drawable_mut(uuid)
     }
@@ -95,6 +236,7 @@ impl Listener for SchemaDrawer {
                     })
                 };
                 component_instance_drawable_instance.map(|d| self.gfx_machinery.add_drawable(&instance.uuid,d));
+                self.update_texts(instance);
             },
             EventMessage::AddWire(instance) => {
                 let drawable_wire = Box::new(WireDrawable::from_schema(
@@ -103,9 +245,37 @@ impl Listener for SchemaDrawer {
                 ));
                 self.gfx_machinery.add_drawable(&instance.uuid, drawable_wire);
             },
+            EventMessage::AddBusEntry(entry) => {
+                let drawable_entry = Box::new(BusEntryDrawable::from_schema(
+                    0,
+                    &entry
+                ));
+                self.gfx_machinery.add_drawable(&entry.uuid, drawable_entry);
+            },
+            EventMessage::RemoveBusEntry(entry) => {
+                self.gfx_machinery.remove_drawable(&entry.uuid);
+            },
+            EventMessage::UpdateBusEntry(entry) => {
+                let drawable_entry = Box::new(BusEntryDrawable::from_schema(
+                    0,
+                    &entry
+                ));
+                self.gfx_machinery.remove_drawable(&entry.uuid);
+                self.gfx_machinery.add_drawable(&entry.uuid, drawable_entry);
+            },
             EventMessage::RemoveWire(instance) => {
                 self.gfx_machinery.remove_drawable(&instance.uuid);
             },
+            EventMessage::AddJunction(junction) => {
+                let drawable_junction = Box::new(JunctionDrawable::from_schema(
+                    0,
+                    &junction
+                ));
+                self.gfx_machinery.add_drawable(&junction.uuid, drawable_junction);
+            },
+            EventMessage::RemoveJunction(junction) => {
+                self.gfx_machinery.remove_drawable(&junction.uuid);
+            },
             EventMessage::UpdateWire(instance) => {
                 let drawable_wire = Box::new(WireDrawable::from_schema(
                     0,
@@ -114,12 +284,27 @@ impl Listener for SchemaDrawer {
                 self.gfx_machinery.remove_drawable(&instance.uuid);
                 self.gfx_machinery.add_drawable(&instance.uuid, drawable_wire);
             },
+            EventMessage::AddLabel(label) => self.add_label(label.clone()),
+            EventMessage::UpdateLabel(label) => self.update_label(label.clone()),
+            EventMessage::RemoveLabel(label) => self.remove_label(label.clone()),
+            EventMessage::AddNote(note) => self.add_note(note.clone()),
+            EventMessage::UpdateNote(note) => self.update_note(note.clone()),
+            EventMessage::RemoveNote(note) => self.remove_note(note.clone()),
+            EventMessage::AddNoteLine(line) => self.add_note_line(line.clone()),
+            EventMessage::UpdateNoteLine(line) => self.update_note_line(line.clone()),
+            EventMessage::RemoveNoteLine(line) => self.remove_note_line(line.clone()),
             EventMessage::DrawSchema => self.gfx_machinery.draw(&self.view_state.read().unwrap()),
             EventMessage::ResizeDrawArea(w, h) => {
                 self.gfx_machinery.resize_target(*w, *h);
             },
             EventMessage::UpdateComponent(instance) => {
                 self.get_drawable_mut(&instance.uuid).map(|d| d.set_transform(&instance.get_transform()));
+                // The texts follow the instance without its transform and the fields might have been edited too, so they are drawn anew.
+                self.update_texts(instance);
+            },
+            EventMessage::RemoveComponent(instance) => {
+                self.gfx_machinery.remove_drawable(&instance.uuid);
+                self.remove_texts(&instance.uuid);
             },
             _ => (),
         }
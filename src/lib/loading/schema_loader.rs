@@ -23,6 +23,10 @@ impl SchemaLoader {
         let mut schema = self.schema.write().unwrap();
         if let Ok(mut file) = fs::File::open(path) {
             if let Some(schema_file) = parse_schema(&mut file) {
+                for alias in schema_file.bus_aliases {
+                    schema.add_bus_alias(alias);
+                }
+
                 for instance in schema_file.components {
                     schema.add_component(instance);
                 }
@@ -30,6 +34,29 @@ impl SchemaLoader {
                 schema_file.wires.iter().for_each( |w: &WireSegment| {
                     schema.add_wire(w.clone());
                 });
+
+                for label in schema_file.labels {
+                    schema.add_label(label);
+                }
+
+                for junction in schema_file.junctions {
+                    schema.add_junction(junction);
+                }
+
+                for note in schema_file.notes {
+                    schema.add_note(note);
+                }
+
+                for line in schema_file.note_lines {
+                    schema.add_note_line(line);
+                }
+
+                for entry in schema_file.bus_entries {
+                    schema.add_bus_entry(entry);
+                }
+
+                // Loading the file is not a step the user should be able to undo.
+                schema.clear_history();
             } else {
                 println!("Could not parse the schema file.");
             }
@@ -21,6 +21,7 @@ extern crate derivative;
 extern crate bitflags;
 extern crate uuid;
 extern crate owning_ref;
+extern crate regex;
 
 pub mod parsing;
 pub mod drawing;
@@ -50,6 +50,23 @@ named!(pub delimited_text(CompleteByteSlice) -> &str,
     map_res!(delimited!(tag!("\""), take_until!("\""), tag!("\"")), bytes_to_utf8)
 );
 
+/// Parses a text between double quotes in which `\"` stands for a quote and `\\` for a backslash, the way KiCad escapes field texts.
+named!(pub escaped_text(CompleteByteSlice) -> String,
+    map_res!(
+        delimited!(
+            tag!("\""),
+            many0!(alt!(
+                take_while1!(|c: u8| c != b'"' && c != b'\\') |
+                preceded!(tag!("\\"), alt!(tag!("\"") | tag!("\\"))) |
+                // A lone backslash stands for itself.
+                tag!("\\")
+            )),
+            tag!("\"")
+        ),
+        |parts: Vec<CompleteByteSlice>| String::from_utf8(parts.iter().flat_map(|p| p.0.iter().cloned()).collect())
+    )
+);
+
 named!(pub orientation(CompleteByteSlice) -> TextOrientation, 
     map_opt!(anychar, TextOrientation::from_char)
 );
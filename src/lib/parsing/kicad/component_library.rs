@@ -354,6 +354,7 @@ named!(rectangle_def(CompleteByteSlice) -> (GraphicElement),
 );
 
 /// Parses a text in a KiCad component.
+/// Older libraries end the line after the text, newer ones add the style and the justification.
 named!(text_def(CompleteByteSlice) -> (GraphicElement),
     do_parse!(
         tag!("T") >>
@@ -362,20 +363,43 @@ named!(text_def(CompleteByteSlice) -> (GraphicElement),
         space >>
         pos: point >>
         space >>
-        _dimension: uint >>
+        dimension: uint >>
+        space >>
+        _hidden: uint >>
         space >>
         unit: uint >>
         space >>
         convert: uint >>
         space >>
-        text: utf8_str >>
+        text: alt!(delimited_text | utf8_str) >>
+        style: opt!(do_parse!(
+            space >>
+            italic: utf8_str >>
+            space >>
+            bold: uint >>
+            space >>
+            hjustify: justification >>
+            space >>
+            vjustify: justification >>
+            ((italic == "Italic", bold != 0, hjustify, vjustify))
+        )) >>
+        opt!(space) >>
         line_ending >>
-        (GraphicElement::TextField {
-            content: text.to_owned(),
-            orientation: orientation,
-            position: pos,
-            unit: unit,
-            convert: convert,
+        ({
+            let (italic, bold, hjustify, vjustify) = style.unwrap_or((false, false, Justify::Center, Justify::Center));
+            GraphicElement::TextField {
+                // KiCad writes the spaces of unquoted texts as tildes.
+                content: text.replace('~', " "),
+                orientation: orientation,
+                position: pos,
+                dimension: dimension,
+                unit: unit,
+                convert: convert,
+                italic: italic,
+                bold: bold,
+                hjustify: hjustify,
+                vjustify: vjustify,
+            }
         })
     )
 );
@@ -414,7 +438,7 @@ named!(polygon_def(CompleteByteSlice) -> (GraphicElement),
     )
 );
 
-#[derive(Debug, Clone)]
+#[derive(Debug, PartialEq, Clone)]
 pub enum Justify {
     Left,
     Right,
@@ -434,6 +458,17 @@ impl Justify {
             _   => None,
         }
     }
+
+    /// Returns the justification to the opposite side, which keeps a text anchored at the same side when it is turned around.
+    pub fn flipped(&self) -> Justify {
+        match self {
+            Justify::Left => Justify::Right,
+            Justify::Right => Justify::Left,
+            Justify::Top => Justify::Bottom,
+            Justify::Bottom => Justify::Top,
+            Justify::Center => Justify::Center,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -478,9 +513,13 @@ pub enum GraphicElement {
         content: String,
         orientation: TextOrientation,
         position: Point2,
+        dimension: usize,
         unit: usize,
-        convert: usize
-        // TODO: parts, convert, filled
+        convert: usize,
+        italic: bool,
+        bold: bool,
+        hjustify: Justify,
+        vjustify: Justify,
     },
     Pin {
         uuid: Uuid,
@@ -522,8 +561,9 @@ impl TextOrientation {
 }
 
 /// Parses a 0/1 single character to TextOrientation
+/// Parses the angle of a text in tenths of a degree, which is either 0 or 900.
 named!(text_orientation(CompleteByteSlice) -> TextOrientation,
-    map!(alpha, { |i: CompleteByteSlice| if i.0 == &['0' as u8] { TextOrientation::Horizontal } else { TextOrientation::Vertical } })
+    map!(int, { |angle: isize| if angle == 0 { TextOrientation::Horizontal } else { TextOrientation::Vertical } })
 );
 
 #[derive(Debug, Clone)]
@@ -701,6 +741,34 @@ ENDDEF
         }
     }
 
+    #[test]
+    fn parse_text_def() {
+        let (_, text) = text_def(CompleteByteSlice(b"T 900 350 -475 50 0 1 0 USB~Bus Italic 1 L B\n")).unwrap();
+        match text {
+            GraphicElement::TextField { content, orientation, position, dimension, unit, italic, bold, hjustify, vjustify, .. } => {
+                assert_eq!(content, "USB Bus");
+                assert_eq!(orientation, TextOrientation::Vertical);
+                assert_eq!(position, Point2::new(350.0, -475.0));
+                assert_eq!(dimension, 50);
+                assert_eq!(unit, 1);
+                assert!(italic && bold);
+                assert_eq!((hjustify, vjustify), (Justify::Left, Justify::Bottom));
+            },
+            _ => panic!("Unexpected parse result")
+        }
+
+        let (_, text) = text_def(CompleteByteSlice(b"T 0 -65 0 45 0 0 0 CTRL\n")).unwrap();
+        match text {
+            GraphicElement::TextField { content, orientation, italic, bold, hjustify, vjustify, .. } => {
+                assert_eq!(content, "CTRL");
+                assert_eq!(orientation, TextOrientation::Horizontal);
+                assert!(!italic && !bold);
+                assert_eq!((hjustify, vjustify), (Justify::Center, Justify::Center));
+            },
+            _ => panic!("Unexpected parse result")
+        }
+    }
+
     #[test]
     fn parse_pin_name() {
         let inputs = [
@@ -1,8 +1,10 @@
 use std::str;
 use std::cell::Cell;
 use std::io::Read;
+use std::fmt::Write;
 
 use nom::types::CompleteByteSlice;
+use ncollide2d::bounding_volume::BoundingVolume;
 use nom::{
     space,
     line_ending,
@@ -13,7 +15,13 @@ use uuid::Uuid;
 use geometry::*;
 use state::schema::ComponentInstance;
 use state::schema::component::Field;
+use state::schema::component_instance::REFERENCE_FIELD;
+use parsing::kicad::component_library::{
+    Justify,
+    TextOrientation,
+};
 use parsing::common::*;
+use utils::text::text_aabb;
 
 /// Parses an entire KiCad schema file.
 /// Returns a `SchemaFile` if the parse was successful.
@@ -34,6 +42,10 @@ pub struct SchemaFile {
     pub wires: Vec<WireSegment>,
     pub labels: Vec<Label>,
     pub junctions: Vec<Junction>,
+    pub notes: Vec<Note>,
+    pub note_lines: Vec<NoteLine>,
+    pub bus_entries: Vec<BusEntry>,
+    pub bus_aliases: Vec<BusAlias>,
 }
 
 impl SchemaFile {
@@ -47,7 +59,10 @@ impl SchemaFile {
                 let mut labels = Vec::new();
                 let mut junctions = Vec::new();
                 let mut notes = Vec::new();
+                let mut note_lines = Vec::new();
                 let mut no_conns = Vec::new();
+                let mut bus_entries = Vec::new();
+                let mut bus_aliases = Vec::new();
 
                 for e in entries.into_iter() {
                     match e {
@@ -56,7 +71,10 @@ impl SchemaFile {
                         SchemaEntry::Label(label) => labels.push(label),
                         SchemaEntry::Junction(junction) => junctions.push(junction),
                         SchemaEntry::Note(note) => notes.push(note),
+                        SchemaEntry::NoteLine(line) => note_lines.push(line),
                         SchemaEntry::NoConnection(noconn) => no_conns.push(noconn),
+                        SchemaEntry::BusEntry(entry) => bus_entries.push(entry),
+                        SchemaEntry::BusAlias(alias) => bus_aliases.push(alias),
                     }
                 }
 
@@ -65,11 +83,164 @@ impl SchemaFile {
                     wires: wires,
                     labels: labels,
                     junctions: junctions,
+                    notes: notes,
+                    note_lines: note_lines,
+                    bus_entries: bus_entries,
+                    bus_aliases: bus_aliases,
                 })
             },
             _ => None
         }
     }
+
+    /// Writes the schema in the KiCad schema file format, such that it can be read again by `parse`.
+    /// Only the parts the parser understands are written, no-connects are left out.
+    ///
+    /// This is meant for the fragments on the clipboard only, there is no saving of schema files yet.
+    /// The page description, the sheets and the placement and style of most fields are lost, so it must not be used to overwrite a schema file.
+    pub fn serialize(&self) -> String {
+        let mut out = String::new();
+        out.push_str("EESchema Schematic File Version 4\n");
+        out.push_str("$Descr A4 11693 8268\n");
+        out.push_str("$EndDescr\n");
+
+        for alias in &self.bus_aliases {
+            writeln!(out, "BusAlias {} {}", alias.name, alias.members.join(" ")).unwrap();
+        }
+
+        for instance in &self.components {
+            let id = instance.uuid.as_bytes();
+            let r = &instance.rotation;
+            writeln!(out, "$Comp").unwrap();
+            writeln!(out, "L {} {}", instance.name, instance.reference).unwrap();
+            writeln!(out, "U {} {} {:02X}{:02X}{:02X}{:02X}", instance.unit, instance.convert, id[0], id[1], id[2], id[3]).unwrap();
+            writeln!(out, "P {}", format_point(&instance.position)).unwrap();
+            // The reference field always carries the reference of the instance.
+            let mut reference = instance.get_field(REFERENCE_FIELD).cloned().unwrap_or(Field::new(REFERENCE_FIELD, String::new()));
+            reference.text = instance.reference.clone();
+            writeln!(out, "{}", format_field(&reference, &instance.position)).unwrap();
+            for field in instance.fields.iter().filter(|f| f.n != REFERENCE_FIELD) {
+                writeln!(out, "{}", format_field(field, &instance.position)).unwrap();
+            }
+            writeln!(out, "\t1    {}", format_point(&instance.position)).unwrap();
+            // This is the inverse of what `component_rotation` does.
+            writeln!(
+                out,
+                "\t{}    {}    {}    {}",
+                r[(0, 0)].round() + 0.0, -r[(1, 0)].round() + 0.0, r[(0, 1)].round() + 0.0, -r[(1, 1)].round() + 0.0
+            ).unwrap();
+            writeln!(out, "$EndComp").unwrap();
+        }
+
+        for wire in &self.wires {
+            let kind = match wire.kind {
+                WireType::Wire => "Wire",
+                WireType::Bus => "Bus",
+            };
+            writeln!(out, "Wire {} Line", kind).unwrap();
+            writeln!(out, "\t{} {}", format_point(&wire.start), format_point(&wire.end)).unwrap();
+        }
+
+        for line in &self.note_lines {
+            writeln!(out, "Wire Notes Line").unwrap();
+            writeln!(out, "\t{} {}", format_point(&line.start), format_point(&line.end)).unwrap();
+        }
+
+        for entry in &self.bus_entries {
+            let kind = match entry.kind {
+                BusEntryType::WireToBus => "Wire Line",
+                BusEntryType::BusToBus => "Bus Bus",
+            };
+            writeln!(out, "Entry {}", kind).unwrap();
+            writeln!(out, "\t{} {}", format_point(&entry.start), format_point(&entry.end)).unwrap();
+        }
+
+        for label in &self.labels {
+            let shape = match label.kind {
+                LabelKind::Local => String::new(),
+                _ => format!("{} ", label.shape.get_keyword()),
+            };
+            writeln!(
+                out,
+                "Text {} {} {}    {}   {}~ 0",
+                label.kind.get_keyword(), format_point(&label.position), label.orientation, label.size, shape
+            ).unwrap();
+            writeln!(out, "{}", label.text).unwrap();
+        }
+
+        for note in &self.notes {
+            writeln!(
+                out,
+                "Text Notes {} {}    {}   {} {}",
+                format_point(&note.position), note.orientation, note.size, if note.italic { "Italic" } else { "~" }, note.thickness
+            ).unwrap();
+            // Line breaks are stored escaped, as the text has to fit on a single line.
+            writeln!(out, "{}", note.text.replace('\n', "\\n")).unwrap();
+        }
+
+        for junction in &self.junctions {
+            writeln!(out, "Connection ~ {}", format_point(&junction.position)).unwrap();
+        }
+
+        out.push_str("$EndSCHEMATC\n");
+        out
+    }
+}
+
+/// Makes the positions of the fields of an instance at `position` relative to it.
+fn relative_fields(fields: Vec<Field>, position: &Point2) -> Vec<Field> {
+    let mut fields: Vec<_> = fields.into_iter().map(|mut field| {
+        field.position = Point2::origin() + (field.position - *position);
+        field
+    }).collect();
+    fields.sort_by_key(|f| f.n);
+    fields
+}
+
+/// Writes a field of an instance at `position` in the KiCad format.
+fn format_field(field: &Field, position: &Point2) -> String {
+    let orientation = match field.orientation {
+        TextOrientation::Horizontal => 'H',
+        TextOrientation::Vertical => 'V',
+    };
+    let mut line = format!(
+        "F {} \"{}\" {} {} {}  {} {} {}{}{}",
+        field.n,
+        escape_text(&field.text),
+        orientation,
+        format_point(&(*position + field.position.coords)),
+        field.dimension,
+        if field.visible { "0000" } else { "0001" },
+        justify_char(&field.hjustify),
+        justify_char(&field.vjustify),
+        if field.italic { 'I' } else { 'N' },
+        if field.bold { 'B' } else { 'N' }
+    );
+    if let Some(ref name) = field.name {
+        line.push_str(&format!(" \"{}\"", escape_text(name)));
+    }
+    line
+}
+
+/// Escapes the quotes and backslashes in a field text or name, the way KiCad writes them.
+fn escape_text(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn justify_char(justify: &Justify) -> char {
+    match justify {
+        Justify::Left => 'L',
+        Justify::Right => 'R',
+        Justify::Top => 'T',
+        Justify::Bottom => 'B',
+        Justify::Center => 'C',
+    }
+}
+
+/// Formats a point the way KiCad stores it, with the y axis pointing down.
+fn format_point(p: &Point2) -> String {
+    // Adding zero turns a negative zero into a plain one.
+    format!("{} {}", p.x + 0.0, -p.y + 0.0)
 }
 
 #[derive(Debug)]
@@ -79,7 +250,10 @@ enum SchemaEntry {
     Label(Label),
     Junction(Junction),
     Note(Note),
+    NoteLine(NoteLine),
     NoConnection(NoConnection),
+    BusEntry(BusEntry),
+    BusAlias(BusAlias),
 }
 
 named!(schema_file(CompleteByteSlice) -> Vec<SchemaEntry>,
@@ -90,12 +264,15 @@ named!(schema_file(CompleteByteSlice) -> Vec<SchemaEntry>,
         line_ending >>
         take_until_and_consume_s!("$EndDescr") >> line_ending >>
         components: many1!(alt!(
+            bus_alias_entry |
             component_instance |
             wire_instance |
+            note_line_entry |
             label_entry |
             junction_entry |
             note_entry |
-            no_conn_entry
+            no_conn_entry |
+            bus_entry
             )) >>
         tag_s!("$EndSCHEMATC") >> line_ending >>
         (components)
@@ -106,9 +283,9 @@ named!(component_instance(CompleteByteSlice) -> SchemaEntry,
     do_parse!(
         tag_s!("$Comp") >> line_ending >>
         tag_s!("L") >> space >> name: utf8_str >> space >> reference: utf8_str >> line_ending >>
-        tag_s!("U") >> take_until_either!("\r\n") >> line_ending >>
+        tag_s!("U") >> space >> unit: uint >> space >> convert: uint >> take_until_either!("\r\n") >> line_ending >>
         tag_s!("P") >> space >> position: point >> line_ending >>
-        _fields: many0!(field_entry) >>
+        fields: many0!(field_entry) >>
         take_until_either!("\r\n") >> line_ending >>
         rotation: component_rotation >>
         take_until_and_consume_s!("$EndComp") >> line_ending >>
@@ -118,7 +295,10 @@ named!(component_instance(CompleteByteSlice) -> SchemaEntry,
             reference: reference.to_owned(),
             position: Point2::new(position.x, -position.y),
             bounding_box: Cell::new(None),
-            rotation: rotation
+            rotation: rotation,
+            unit: unit,
+            convert: convert,
+            fields: relative_fields(fields, &Point2::new(position.x, -position.y)),
         }))
     )
 );
@@ -128,8 +308,7 @@ named!(wire_instance(CompleteByteSlice) -> SchemaEntry,
         tag_s!("Wire") >> space >>
         wire: alt!(
             wire_segment |
-            bus_segment |
-            line_segment
+            bus_segment
         ) >>
         (SchemaEntry::Wire(wire))
     )
@@ -147,7 +326,6 @@ pub struct WireSegment {
 pub enum WireType {
     Wire,
     Bus,
-    Dotted
 }
 
 named!(wire_segment(CompleteByteSlice) -> WireSegment,
@@ -176,63 +354,257 @@ named!(bus_segment(CompleteByteSlice) -> WireSegment,
     )
 );
 
-named!(line_segment(CompleteByteSlice) -> WireSegment,
+/// A dashed graphic line, which only looks like a wire and connects nothing.
+#[derive(Debug, Clone)]
+pub struct NoteLine {
+    pub uuid: Uuid,
+    pub start: Point2,
+    pub end: Point2,
+}
+
+named!(note_line_entry(CompleteByteSlice) -> SchemaEntry,
     do_parse!(
-        tag_s!("Notes") >> space >> tag_s!("Line") >> line_ending >>
+        tag_s!("Wire") >> space >> tag_s!("Notes") >> space >> tag_s!("Line") >> opt!(space) >> line_ending >>
         opt!(space) >> start: point >> space >> end: point >> opt!(space) >> line_ending >>
-        (WireSegment {
+        (SchemaEntry::NoteLine(NoteLine {
             uuid: Uuid::nil(),
-            kind: WireType::Dotted,
             start: Point2::new(start.x, -start.y),
             end: Point2::new(end.x, -end.y),
-        })
+        }))
     )
 );
 
-#[derive(Debug)]
+/// The kind of a label decides how far the net it names reaches.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LabelKind {
+    /// Connects the nets of the same name on its sheet.
+    Local,
+    /// Connects the nets of the same name on all sheets.
+    Global,
+    /// Connects the net to the sheet pin of the same name on the parent sheet.
+    Hierarchical,
+}
+
+impl LabelKind {
+    /// Returns the keyword KiCad stores the kind with.
+    pub fn get_keyword(&self) -> &'static str {
+        match self {
+            LabelKind::Local => "Label",
+            LabelKind::Global => "GLabel",
+            LabelKind::Hierarchical => "HLabel",
+        }
+    }
+}
+
+/// The electrical direction of a global or hierarchical label, which decides the outline it is drawn with.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LabelShape {
+    Input,
+    Output,
+    BiDi,
+    TriState,
+    Passive,
+}
+
+impl LabelShape {
+    /// Returns the keyword KiCad stores the shape with.
+    pub fn get_keyword(&self) -> &'static str {
+        match self {
+            LabelShape::Input => "Input",
+            LabelShape::Output => "Output",
+            LabelShape::BiDi => "BiDi",
+            LabelShape::TriState => "3State",
+            LabelShape::Passive => "UnSpc",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct Label {
+    pub uuid: Uuid,
+    pub kind: LabelKind,
     pub text: String,
     pub position: Point2,
-    //todo: fill
+    /// The direction the text runs in, in counter clockwise quarter turns from running to the right.
+    pub orientation: usize,
+    /// The height of the text in mils.
+    pub size: f32,
+    /// Only global and hierarchical labels have a shape.
+    pub shape: LabelShape,
+}
+
+impl Label {
+    /// Creates a label with the text running to the right in KiCad's default size.
+    pub fn new(kind: LabelKind, text: String, position: Point2) -> Label {
+        Label {
+            uuid: Uuid::nil(),
+            kind: kind,
+            text: text,
+            position: position,
+            orientation: 0,
+            size: 50.0,
+            shape: LabelShape::Input,
+        }
+    }
+
+    /// Turns the label by a quarter turn counter clockwise.
+    pub fn rotate(&mut self) {
+        self.orientation = (self.orientation + 1) % 4;
+    }
+
+    /// Increments the number the text ends with and keeps its leading zeros, like `D0` to `D1` or `A09` to `A10`.
+    /// A text which does not end with a number is left as it is.
+    pub fn increment_text(&mut self) {
+        let digits = self.text.chars().rev().take_while(|c| c.is_ascii_digit()).count();
+        if digits == 0 {
+            return;
+        }
+        let split = self.text.len() - digits;
+        if let Ok(number) = self.text[split..].parse::<u64>() {
+            self.text = format!("{}{:0width$}", &self.text[..split], number + 1, width = digits);
+        }
+    }
 }
 
+named!(label_kind(CompleteByteSlice) -> LabelKind,
+    alt!(
+        value!(LabelKind::Global, tag_s!("GLabel")) |
+        value!(LabelKind::Hierarchical, tag_s!("HLabel")) |
+        value!(LabelKind::Local, tag_s!("Label"))
+    )
+);
+
+named!(label_shape(CompleteByteSlice) -> LabelShape,
+    alt!(
+        value!(LabelShape::Input, tag_s!("Input")) |
+        value!(LabelShape::Output, tag_s!("Output")) |
+        value!(LabelShape::BiDi, tag_s!("BiDi")) |
+        value!(LabelShape::TriState, tag_s!("3State")) |
+        value!(LabelShape::Passive, tag_s!("UnSpc"))
+    )
+);
+
 named!(label_entry(CompleteByteSlice) -> SchemaEntry,
     do_parse!(
-        tag_s!("Text") >> space >> tag_s!("Label") >> space >> position: point >> space >> _orientation: digit >> space >>
-        _dimension: utf8_str >> space >> tag_s!("~") >> space >> utf8_str >> line_ending >>
+        tag_s!("Text") >> space >> kind: label_kind >> space >> position: point >> space >> orientation: uint >> space >>
+        size: float >> space >> shape: opt!(terminated!(label_shape, space)) >>
+        take_until_either!("\r\n") >> line_ending >>
         text: whole_line_str >>
         (SchemaEntry::Label(Label {
+            uuid: Uuid::nil(),
+            kind: kind,
             text: text.to_owned(),
             position: Point2::new(position.x, -position.y),
+            orientation: orientation % 4,
+            size: size,
+            shape: shape.unwrap_or(LabelShape::Input),
         }))
     )
 );
 
-#[derive(Debug)]
+/// A free text on the schema, which connects nothing.
+#[derive(Debug, Clone)]
 pub struct Note {
+    pub uuid: Uuid,
+    /// The text, which may span several lines.
     pub text: String,
-    //todo: fill
+    /// The bottom left corner of the first line of the text.
+    pub position: Point2,
+    /// The direction the text runs in, in counter clockwise quarter turns from running to the right.
+    pub orientation: usize,
+    /// The height of the text in mils.
+    pub size: f32,
+    pub italic: bool,
+    /// The width of the strokes in mils, zero for the default width.
+    pub thickness: usize,
+}
+
+impl Note {
+    /// Creates an upright note in KiCad's default size.
+    pub fn new(text: String, position: Point2) -> Note {
+        Note {
+            uuid: Uuid::nil(),
+            text: text,
+            position: position,
+            orientation: 0,
+            size: 50.0,
+            italic: false,
+            thickness: 0,
+        }
+    }
+
+    /// Turns the note by a quarter turn counter clockwise.
+    pub fn rotate(&mut self) {
+        self.orientation = (self.orientation + 1) % 4;
+    }
+
+    /// Returns the orientation and the horizontal justification the lines of the note are drawn with, their bottom lies at their anchor.
+    /// Texts are never drawn upside down, so notes pointing left or down end at their position.
+    pub fn get_text_placement(&self) -> (TextOrientation, Justify) {
+        let orientation = if self.orientation % 2 == 0 { TextOrientation::Horizontal } else { TextOrientation::Vertical };
+        let hjustify = if self.orientation % 4 < 2 { Justify::Left } else { Justify::Right };
+        (orientation, hjustify)
+    }
+
+    /// Returns every line of the note together with its anchor.
+    /// The first line sits on the position, every further line is placed below the previous one, as seen when reading the text.
+    pub fn get_lines(&self) -> Vec<(Point2, &str)> {
+        let line_offset = if self.orientation % 2 == 0 {
+            Vector2::new(0.0, -self.size * NOTE_LINE_SPACING)
+        } else {
+            Vector2::new(self.size * NOTE_LINE_SPACING, 0.0)
+        };
+        self.text.lines().enumerate().map(|(i, line)| (self.position + line_offset * i as f32, line)).collect()
+    }
+
+    /// Returns the box the text of the note covers.
+    pub fn get_boundingbox(&self) -> AABB {
+        let (orientation, hjustify) = self.get_text_placement();
+        let aabb = text_aabb(&self.position, "", self.size, &orientation, &hjustify, &Justify::Bottom);
+        self.get_lines().iter().fold(aabb, |aabb, (position, line)| {
+            aabb.merged(&text_aabb(position, line, self.size, &orientation, &hjustify, &Justify::Bottom))
+        })
+    }
 }
 
+/// The distance of two lines of a note relative to the text size.
+pub const NOTE_LINE_SPACING: f32 = 1.5;
+
+named!(note_style(CompleteByteSlice) -> bool,
+    alt!(
+        value!(true, tag_s!("Italic")) |
+        value!(false, tag_s!("~"))
+    )
+);
+
 named!(note_entry(CompleteByteSlice) -> SchemaEntry,
     do_parse!(
-        tag_s!("Text") >> space >> tag_s!("Notes") >> take_until_either!("\r\n") >> line_ending >>
+        tag_s!("Text") >> space >> tag_s!("Notes") >> space >> position: point >> space >> orientation: uint >> space >>
+        size: float >> space >> italic: note_style >> space >> thickness: uint >>
         take_until_either!("\r\n") >> line_ending >>
+        text: map_res!(take_until_either!("\r\n"), bytes_to_utf8) >> line_ending >>
         (SchemaEntry::Note(Note {
-            text: "".to_owned(),
+            uuid: Uuid::nil(),
+            text: text.replace("\\n", "\n"),
+            position: Point2::new(position.x, -position.y),
+            orientation: orientation % 4,
+            size: size,
+            italic: italic,
+            thickness: thickness,
         }))
     )
 );
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Junction {
+    pub uuid: Uuid,
     pub position: Point2,
 }
 
 named!(junction_entry(CompleteByteSlice) -> SchemaEntry,
     do_parse!(
         tag_s!("Connection") >> space >> tag_s!("~") >> space >> position: point >> line_ending >>
-        (SchemaEntry::Junction(Junction { position: Point2::new(position.x, -position.y) }))
+        (SchemaEntry::Junction(Junction { uuid: Uuid::nil(), position: Point2::new(position.x, -position.y) }))
     )
 );
 
@@ -248,11 +620,60 @@ named!(no_conn_entry(CompleteByteSlice) -> SchemaEntry,
     )
 );
 
+#[derive(Debug, Clone)]
+pub struct BusEntry {
+    pub uuid: Uuid,
+    pub kind: BusEntryType,
+    pub start: Point2,
+    pub end: Point2,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BusEntryType {
+    WireToBus,
+    BusToBus
+}
+
+named!(bus_entry(CompleteByteSlice) -> SchemaEntry,
+    do_parse!(
+        tag_s!("Entry") >> space >>
+        kind: alt!(
+            map!(tag_s!("Wire Line"), |_| BusEntryType::WireToBus) |
+            map!(tag_s!("Bus Bus"), |_| BusEntryType::BusToBus)
+        ) >> opt!(space) >> line_ending >>
+        opt!(space) >> start: point >> space >> end: point >> opt!(space) >> line_ending >>
+        (SchemaEntry::BusEntry(BusEntry {
+            uuid: Uuid::nil(),
+            kind: kind,
+            start: Point2::new(start.x, -start.y),
+            end: Point2::new(end.x, -end.y),
+        }))
+    )
+);
+
+#[derive(Debug, Clone)]
+pub struct BusAlias {
+    pub name: String,
+    pub members: Vec<String>,
+}
+
+named!(bus_alias_entry(CompleteByteSlice) -> SchemaEntry,
+    do_parse!(
+        tag_s!("BusAlias") >> space >> name: utf8_str >>
+        members: many1!(do_parse!(space >> member: utf8_str >> (member))) >>
+        line_ending >>
+        (SchemaEntry::BusAlias(BusAlias {
+            name: name.to_owned(),
+            members: members.into_iter().map(|m| m.to_owned()).collect(),
+        }))
+    )
+);
+
 named!(field_entry(CompleteByteSlice) -> (Field),
     do_parse!(
         n: field_tag >>
         space >>
-        text: delimited_text >>
+        text: escaped_text >>
         space >>
         orientation: orientation >>
         space >>
@@ -260,28 +681,28 @@ named!(field_entry(CompleteByteSlice) -> (Field),
         space >>
         dimension: uint >>
         many1!(space) >>
-        // Flags for visibility of fields
-        many0!(number_str) >>
+        // Flags for visibility of fields, a trailing 1 hides the field.
+        flags: number_str >>
         space >>
         hjustify: justification >>
         space >>
         vjustify: justification >>
         italic: italic >>
         bold: bold >>
-        // name: opt!(ws!(utf8_str)) >>
+        name: opt!(preceded!(space, escaped_text)) >>
         take_until_either!("\r\n") >> line_ending >>
         (Field {
             n: n,
-            text: text.to_owned(),
-            position: position,
+            text: text,
+            position: Point2::new(position.x, -position.y),
             dimension: dimension,
             orientation: orientation,
-            visible: false,
+            visible: !flags.ends_with('1'),
             hjustify: hjustify,
             vjustify: vjustify,
             italic: italic,
             bold: bold,
-            name: None // name.map(|s| s.to_owned()),
+            name: name,
         })
 
     )
@@ -328,6 +749,7 @@ named!(component_rotation(CompleteByteSlice) -> Matrix4,
 #[cfg(test)]
 mod tests {
     use super::*;
+    use state::schema::component_instance::VALUE_FIELD;
 
     #[test]
     fn parse_schema_1() {
@@ -404,6 +826,10 @@ $EndSCHEMATC
 
     const SAMPLE_LABEL: &'static str = r#"Text Label 15250 1100 2    60   ~ 0
 LED1
+"#;
+
+    const SAMPLE_HIERARCHICAL_LABEL: &'static str = r#"Text HLabel 2000 1500 0    50   3State ~ 0
+MISO
 "#;
 
     fn parse_cmp() -> ComponentInstance {
@@ -436,6 +862,40 @@ LED1
         assert_eq!(cmp.position, Point2::new(4950.0, -2600.0));
     }
 
+    #[test]
+    fn parse_fields() {
+        let cmp = parse_cmp();
+
+        assert_eq!(cmp.unit, 1);
+        assert_eq!(cmp.fields.len(), 4);
+        assert_eq!(cmp.get_field_text(1), "GND");
+        // The fields are relative to the instance.
+        assert_eq!(cmp.fields[0].position, Point2::new(0.0, 250.0));
+        assert!(!cmp.fields[0].visible);
+        assert!(cmp.fields[1].visible);
+    }
+
+    #[test]
+    fn fields_turn_with_the_instance() {
+        // The value field lies 100 to the right of and 150 above the instance as long as it is not turned.
+        let placed = |matrix: &str| {
+            let sample = SAMPLE_COMPONENT
+                .replace("H 4950 2450", "H 5050 2450")
+                .replace("\t1    0    0    -1", matrix);
+            let (_, cmp) = component_instance(CompleteByteSlice(sample.as_bytes())).unwrap();
+            match cmp {
+                SchemaEntry::ComponentInstance(cmp) => cmp.get_field_placement(cmp.get_field(VALUE_FIELD).unwrap()).position,
+                _ => panic!("Unexpected return value returned from parser!"),
+            }
+        };
+
+        assert_eq!(placed("\t1    0    0    -1"), Point2::new(5050.0, -2450.0));
+        // Turned a quarter clockwise.
+        assert!((placed("\t0    1    1    0") - Point2::new(5100.0, -2700.0)).norm() < 1e-3);
+        // Mirrored at the vertical axis.
+        assert!((placed("\t-1    0    0    -1") - Point2::new(4850.0, -2450.0)).norm() < 1e-3);
+    }
+
     #[test]
     fn parse_wire() {
         let (_, wire) = wire_instance(CompleteByteSlice(SAMPLE_WIRE.as_bytes())).unwrap();
@@ -449,21 +909,190 @@ LED1
         }
     }
 
+    const SAMPLE_BUS_ENTRY: &'static str = r#"Entry Wire Line
+	5500 3000 5600 3100
+"#;
+
+    const SAMPLE_BUS_ALIAS: &'static str = "BusAlias SPI MOSI MISO SCK CS\n";
+
+    #[test]
+    fn parse_bus_entry() {
+        let (_, entry) = bus_entry(CompleteByteSlice(SAMPLE_BUS_ENTRY.as_bytes())).unwrap();
+
+        if let SchemaEntry::BusEntry(entry) = entry {
+            assert_eq!(entry.kind, BusEntryType::WireToBus);
+            assert_eq!(entry.start, Point2::new(5500.0, -3000.0));
+            assert_eq!(entry.end, Point2::new(5600.0, -3100.0));
+        } else {
+            panic!("Unexpected SchemaEntry type returned from parser!");
+        }
+    }
+
+    #[test]
+    fn parse_bus_alias() {
+        let (_, alias) = bus_alias_entry(CompleteByteSlice(SAMPLE_BUS_ALIAS.as_bytes())).unwrap();
+
+        if let SchemaEntry::BusAlias(alias) = alias {
+            assert_eq!(alias.name, "SPI");
+            assert_eq!(alias.members, vec!["MOSI", "MISO", "SCK", "CS"]);
+        } else {
+            panic!("Unexpected SchemaEntry type returned from parser!");
+        }
+    }
+
     #[test]
     fn parse_file() {
         let file = SchemaFile::parse(SAMPLE_SCHEMA_FILE.as_bytes()).unwrap();
 
         assert_eq!(file.components.len(), 0);
+        assert_eq!(file.notes.len(), 1);
+        assert_eq!(file.notes[0].text, "Mesh Node\nr3 autumn 2017\nby yatekii");
+        assert_eq!(file.notes[0].position, Point2::new(8050.0, -10900.0));
+        assert_eq!(file.notes[0].size, 276.0);
+        assert!(file.notes[0].italic);
+        assert_eq!(file.notes[0].thickness, 55);
+    }
+
+    #[test]
+    fn serialize_and_parse_again() {
+        // A rotated and mirrored orientation.
+        let rotation = Matrix4::from_axis_angle(&Vector3::z_axis(), ::std::f32::consts::PI / 2.0)
+            * Matrix4::new_nonuniform_scaling(&Vector3::new(-1.0, 1.0, 1.0));
+        let mut instance = ComponentInstance::new("R".into());
+        instance.uuid = Uuid::new_v4();
+        instance.reference = "R12".into();
+        instance.position = Point2::new(100.0, -200.0);
+        instance.rotation = rotation;
+        instance.unit = 2;
+        instance.set_field_text(VALUE_FIELD, "10k");
+        let tolerance = instance.add_user_field("Tolerance");
+        instance.set_field_text(tolerance, "1%");
+        instance.fields[0].position = Point2::new(0.0, 50.0);
+        let file = SchemaFile {
+            components: vec![instance],
+            wires: vec![WireSegment { uuid: Uuid::nil(), kind: WireType::Bus, start: Point2::new(0.0, 0.0), end: Point2::new(300.0, 0.0) }],
+            labels: vec![
+                Label::new(LabelKind::Local, "LED1".into(), Point2::new(-50.0, 25.0)),
+                Label { orientation: 2, shape: LabelShape::Output, ..Label::new(LabelKind::Global, "SDA".into(), Point2::new(0.0, 0.0)) },
+            ],
+            junctions: vec![Junction { uuid: Uuid::nil(), position: Point2::new(150.0, 0.0) }],
+            notes: vec![Note { orientation: 1, ..Note::new("Power supply\n5 V only".into(), Point2::new(1000.0, 500.0)) }],
+            note_lines: vec![NoteLine { uuid: Uuid::nil(), start: Point2::new(0.0, 1000.0), end: Point2::new(2000.0, 1000.0) }],
+            bus_entries: vec![],
+            bus_aliases: vec![BusAlias { name: "SPI".into(), members: vec!["MOSI".into(), "MISO".into()] }],
+        };
+
+        let parsed = SchemaFile::parse(file.serialize().as_bytes()).unwrap();
+
+        assert_eq!(parsed.components.len(), 1);
+        assert_eq!(parsed.components[0].name, "R");
+        assert_eq!(parsed.components[0].reference, "R12");
+        assert_eq!(parsed.components[0].position, Point2::new(100.0, -200.0));
+        assert!((parsed.components[0].rotation - rotation).iter().all(|d| d.abs() < 1e-6));
+        assert_eq!(parsed.components[0].unit, 2);
+        assert_eq!(parsed.components[0].get_field_text(REFERENCE_FIELD), "R12");
+        assert_eq!(parsed.components[0].get_field_text(VALUE_FIELD), "10k");
+        assert_eq!(parsed.components[0].fields[0].position, Point2::new(0.0, 50.0));
+        assert!(!parsed.components[0].fields[2].visible);
+        let tolerance = parsed.components[0].get_field(tolerance).unwrap();
+        assert_eq!(tolerance.text, "1%");
+        assert_eq!(tolerance.name, Some("Tolerance".to_owned()));
+        assert_eq!(parsed.wires[0].kind, WireType::Bus);
+        assert_eq!(parsed.wires[0].end, Point2::new(300.0, 0.0));
+        assert_eq!(parsed.labels[0].text, "LED1");
+        assert_eq!(parsed.labels[0].position, Point2::new(-50.0, 25.0));
+        assert_eq!(parsed.labels[1].kind, LabelKind::Global);
+        assert_eq!(parsed.labels[1].orientation, 2);
+        assert_eq!(parsed.labels[1].shape, LabelShape::Output);
+        assert_eq!(parsed.junctions[0].position, Point2::new(150.0, 0.0));
+        assert_eq!(parsed.wires.len(), 1);
+        assert_eq!(parsed.notes[0].text, "Power supply\n5 V only");
+        assert_eq!(parsed.notes[0].orientation, 1);
+        assert_eq!(parsed.notes[0].position, Point2::new(1000.0, 500.0));
+        assert_eq!(parsed.note_lines[0].end, Point2::new(2000.0, 1000.0));
+        assert_eq!(parsed.bus_aliases[0].members, vec!["MOSI", "MISO"]);
+    }
+
+    #[test]
+    fn field_quotes_and_backslashes_survive() {
+        let mut instance = ComponentInstance::new("R".into());
+        instance.set_field_text(VALUE_FIELD, "3\" \\ 2");
+        let remark = instance.add_user_field("Say \"hi\"");
+        instance.set_field_text(remark, "C:\\lib\\");
+        let file = SchemaFile {
+            components: vec![instance],
+            wires: vec![],
+            labels: vec![],
+            junctions: vec![],
+            notes: vec![],
+            note_lines: vec![],
+            bus_entries: vec![],
+            bus_aliases: vec![],
+        };
+
+        let serialized = file.serialize();
+        assert!(serialized.contains("\"3\\\" \\\\ 2\""));
+        let parsed = SchemaFile::parse(serialized.as_bytes()).unwrap();
+
+        assert_eq!(parsed.components[0].get_field_text(VALUE_FIELD), "3\" \\ 2");
+        let remark = parsed.components[0].get_field(remark).unwrap();
+        assert_eq!(remark.text, "C:\\lib\\");
+        assert_eq!(remark.name, Some("Say \"hi\"".to_owned()));
     }
 
     #[test]
     fn parse_label() {
         let (_, label) = label_entry(CompleteByteSlice(SAMPLE_LABEL.as_bytes())).unwrap();
 
-        if let SchemaEntry::Label(_label) = label {
-            // do nothing... (tbd!)
+        if let SchemaEntry::Label(label) = label {
+            assert_eq!(label.kind, LabelKind::Local);
+            assert_eq!(label.text, "LED1");
+            assert_eq!(label.position, Point2::new(15250.0, -1100.0));
+            assert_eq!(label.orientation, 2);
+            assert_eq!(label.size, 60.0);
+        } else {
+            panic!("Unexpected SchemaEntry type returned from parser!");
+        }
+
+        let (_, label) = label_entry(CompleteByteSlice(SAMPLE_HIERARCHICAL_LABEL.as_bytes())).unwrap();
+
+        if let SchemaEntry::Label(label) = label {
+            assert_eq!(label.kind, LabelKind::Hierarchical);
+            assert_eq!(label.text, "MISO");
+            assert_eq!(label.shape, LabelShape::TriState);
         } else {
             panic!("Unexpected SchemaEntry type returned from parser!");
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn increment_label_text() {
+        let mut label = Label::new(LabelKind::Local, "D9".to_owned(), Point2::origin());
+        label.increment_text();
+        assert_eq!(label.text, "D10");
+
+        label.text = "A09".to_owned();
+        label.increment_text();
+        assert_eq!(label.text, "A10");
+
+        label.text = "CLK".to_owned();
+        label.increment_text();
+        assert_eq!(label.text, "CLK");
+    }
+
+    #[test]
+    fn note_box_covers_every_line() {
+        use utils::text::text_width;
+
+        let mut note = Note::new("Power\nsupply unit".into(), Point2::new(100.0, 100.0));
+        let aabb = note.get_boundingbox();
+        assert_eq!(aabb.mins(), &Point2::new(100.0, 100.0 - 75.0));
+        assert_eq!(aabb.maxs(), &Point2::new(100.0 + text_width("supply unit", 50.0), 150.0));
+
+        // A note pointing left ends at its position.
+        note.orientation = 2;
+        let aabb = note.get_boundingbox();
+        assert_eq!(aabb.maxs().x, 100.0);
+        assert_eq!(aabb.mins().x, 100.0 - text_width("supply unit", 50.0));
+    }
+}
@@ -4,7 +4,10 @@ use std::collections::HashMap;
 
 use ::state::event::{EventBusHandle};
 
-use state::schema::component::Component;
+use state::schema::component::{
+    Component,
+    OptionFlag,
+};
 
 use state::component_libraries::component_library::ComponentLibrary;
 
@@ -39,6 +42,14 @@ impl ComponentLibraries {
         None
     }
 
+    /// Returns the power symbol with the given name or alias, like `GND` or `+3V3`, from any of the libraries.
+    pub fn get_power_component(&self, name: &str) -> Option<&Component> {
+        self.libraries.values()
+            .flat_map(|lib| lib.get_components())
+            .filter(|component| component.option_flag == OptionFlag::Power)
+            .find(|component| component.name == name || component.alias.iter().any(|alias| alias == name))
+    }
+
     pub fn get_component_by_name_and_lib(&self, component_name: &str, library_name: &str) -> Option<&Component> {
         if let Some(lib) = self.libraries.get(library_name) {
             let component = lib.get_component_by_name(component_name);
@@ -20,9 +20,26 @@ pub enum EventMessage {
     ResizeDrawArea(u16, u16),
     AddComponent(ComponentInstance),
     UpdateComponent(ComponentInstance),
+    RemoveComponent(ComponentInstance),
     AddWire(WireSegment),
     RemoveWire(WireSegment),
     UpdateWire(WireSegment),
+    AddLabel(Label),
+    RemoveLabel(Label),
+    UpdateLabel(Label),
+    AddBusEntry(BusEntry),
+    RemoveBusEntry(BusEntry),
+    UpdateBusEntry(BusEntry),
+    AddBusAlias(BusAlias),
+    RemoveBusAlias(BusAlias),
+    AddJunction(Junction),
+    RemoveJunction(Junction),
+    AddNote(Note),
+    RemoveNote(Note),
+    UpdateNote(Note),
+    AddNoteLine(NoteLine),
+    RemoveNoteLine(NoteLine),
+    UpdateNoteLine(NoteLine),
     ViewStateChanged,
     OpenComponent(Component),
     AddGeometricElement(GraphicElement),
@@ -0,0 +1,173 @@
+use uuid::Uuid;
+
+use geometry::*;
+use parsing::kicad::schema::SchemaFile;
+use super::Schema;
+use super::component_instance::REFERENCE_FIELD;
+
+/// Serializes the components, wires, labels, junctions, bus entries, notes and note lines among `items` into a schema fragment in the KiCad file format.
+/// The coordinates in the fragment are relative to `anchor`, such that it can be pasted anywhere.
+/// Returns `None` if none of the `items` belongs to the schema.
+pub fn copy(schema: &Schema, items: &[Uuid], anchor: &Point2) -> Option<String> {
+    let offset = Point2::origin() - *anchor;
+
+    let components: Vec<_> = items.iter()
+        .filter_map(|uuid| schema.find_component_instance(uuid))
+        .map(|instance| {
+            let mut instance = instance.clone();
+            instance.position += offset;
+            instance
+        })
+        .collect();
+    let wires: Vec<_> = schema.get_wires().iter()
+        .filter(|wire| items.contains(&wire.uuid))
+        .map(|wire| {
+            let mut wire = wire.clone();
+            wire.start += offset;
+            wire.end += offset;
+            wire
+        })
+        .collect();
+    let labels: Vec<_> = schema.get_labels().iter()
+        .filter(|label| items.contains(&label.uuid))
+        .map(|label| {
+            let mut label = label.clone();
+            label.position += offset;
+            label
+        })
+        .collect();
+    let junctions: Vec<_> = schema.get_junctions().iter()
+        .filter(|junction| items.contains(&junction.uuid))
+        .map(|junction| {
+            let mut junction = junction.clone();
+            junction.position += offset;
+            junction
+        })
+        .collect();
+    let bus_entries: Vec<_> = schema.get_bus_entries().iter()
+        .filter(|entry| items.contains(&entry.uuid))
+        .map(|entry| {
+            let mut entry = entry.clone();
+            entry.start += offset;
+            entry.end += offset;
+            entry
+        })
+        .collect();
+    let notes: Vec<_> = schema.get_notes().iter()
+        .filter(|note| items.contains(&note.uuid))
+        .map(|note| {
+            let mut note = note.clone();
+            note.position += offset;
+            note
+        })
+        .collect();
+    let note_lines: Vec<_> = schema.get_note_lines().iter()
+        .filter(|line| items.contains(&line.uuid))
+        .map(|line| {
+            let mut line = line.clone();
+            line.start += offset;
+            line.end += offset;
+            line
+        })
+        .collect();
+
+    if components.is_empty() && wires.is_empty() && labels.is_empty() && junctions.is_empty() && bus_entries.is_empty() && notes.is_empty() && note_lines.is_empty() {
+        return None;
+    }
+
+    Some(SchemaFile {
+        components: components,
+        wires: wires,
+        labels: labels,
+        junctions: junctions,
+        notes: notes,
+        note_lines: note_lines,
+        bus_entries: bus_entries,
+        bus_aliases: Vec::new(),
+    }.serialize())
+}
+
+/// Adds the items of a schema fragment created by `copy` to the schema, with their anchor at `position`.
+/// Every item gets a fresh `Uuid`. Unless `keep_references` is set, the components get unannotated references like `R?`.
+/// Junctions are not pasted, they are placed again when the wires are cleaned up.
+/// Returns the `Uuid`s of the added items or `None` if the fragment could not be parsed.
+pub fn paste(schema: &mut Schema, fragment: &str, position: &Point2, keep_references: bool) -> Option<Vec<Uuid>> {
+    let file = SchemaFile::parse(fragment.as_bytes())?;
+    let offset = *position - Point2::origin();
+
+    let mut uuids = Vec::new();
+    for mut instance in file.components {
+        instance.position += offset;
+        if !keep_references {
+            let reference = clear_reference(&instance.reference);
+            instance.set_field_text(REFERENCE_FIELD, &reference);
+        }
+        uuids.push(schema.add_component(instance));
+    }
+    for mut wire in file.wires {
+        wire.start += offset;
+        wire.end += offset;
+        uuids.push(schema.add_wire(wire));
+    }
+    for mut label in file.labels {
+        label.position += offset;
+        uuids.push(schema.add_label(label));
+    }
+    for mut entry in file.bus_entries {
+        entry.start += offset;
+        entry.end += offset;
+        uuids.push(schema.add_bus_entry(entry));
+    }
+    for mut note in file.notes {
+        note.position += offset;
+        uuids.push(schema.add_note(note));
+    }
+    for mut line in file.note_lines {
+        line.start += offset;
+        line.end += offset;
+        uuids.push(schema.add_note_line(line));
+    }
+    Some(uuids)
+}
+
+/// Strips the number from a reference, such that `R12` becomes `R?`.
+fn clear_reference(reference: &str) -> String {
+    format!("{}?", reference.trim_right_matches(|c: char| c.is_ascii_digit() || c == '?'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use state::schema::ComponentInstance;
+    use state::schema::test_utils::{Fixture, wire};
+
+    #[test]
+    fn references_are_cleared() {
+        assert_eq!(clear_reference("R12"), "R?");
+        assert_eq!(clear_reference("#PWR03"), "#PWR?");
+        assert_eq!(clear_reference("U?"), "U?");
+    }
+
+    #[test]
+    fn paste_moves_items_and_assigns_fresh_uuids() {
+        let Fixture { mut schema, .. } = Fixture::new();
+
+        let mut instance = ComponentInstance::new("R".into());
+        instance.reference = "R1".into();
+        instance.position = Point2::new(100.0, 100.0);
+        let component = schema.add_component(instance);
+        let wire = schema.add_wire(wire((100.0, 100.0), (300.0, 100.0)));
+
+        let fragment = copy(&schema, &[component, wire], &Point2::new(100.0, 100.0)).unwrap();
+        let pasted = paste(&mut schema, &fragment, &Point2::new(1000.0, 0.0), false).unwrap();
+
+        assert_eq!(pasted.len(), 2);
+        assert!(!pasted.contains(&component) && !pasted.contains(&wire));
+        let instance = schema.get_component_instance(&pasted[0]);
+        assert_eq!(instance.position, Point2::new(1000.0, 0.0));
+        assert_eq!(instance.reference, "R?");
+        let wire = schema.get_wire_instance(pasted[1]);
+        assert_eq!(wire.start, Point2::new(1000.0, 0.0));
+        assert_eq!(wire.end, Point2::new(1200.0, 0.0));
+    }
+}
@@ -4,9 +4,20 @@ use std::cell::Cell;
 use uuid::Uuid;
 
 use utils::traits::clone_cached_aabb;
+use utils::geometry::{
+    arc_aabb,
+    distance_point_arc,
+    distance_point_aabb,
+    distance_point_segment,
+    point_in_polygon,
+};
+use utils::text::text_aabb;
 
 use geometry::{Point2, AABB };
-use state::schema::component_instance::ComponentInstance;
+use state::schema::component_instance::{
+    ComponentInstance,
+    REFERENCE_FIELD,
+};
 use parsing::kicad::component_library::*;
 
 #[derive(Debug, PartialEq, Clone)]
@@ -36,8 +47,19 @@ pub struct Component {
 }
 
 impl Component {
+    /// Creates an instance of the component which carries a copy of the component's fields.
     pub fn instantiate(&self) -> ComponentInstance {
-        ComponentInstance::new(self.name.clone())
+        let mut instance = ComponentInstance::new(self.name.clone());
+        for field in &self.fields {
+            let mut field = field.clone();
+            if field.n == REFERENCE_FIELD {
+                field.text = instance.reference.clone();
+            }
+            instance.fields.retain(|f| f.n != field.n);
+            instance.fields.push(field);
+        }
+        instance.fields.sort_by_key(|f| f.n);
+        instance
     }
 
     pub fn get_graphic_elements(&self) -> &Vec<GraphicElement> {
@@ -57,6 +79,16 @@ impl Component {
     }
 
     pub fn update_boundingbox(&self) {
+        self.bounding_box.set(Some(self.compute_boundingbox(true)));
+    }
+
+    /// Returns the bounding box of the graphic elements and the pins, leaving out the fields.
+    /// Instances carry their own fields, so their bounding box is made up of this one and the boxes of their fields.
+    pub fn get_body_boundingbox(&self) -> AABB {
+        self.compute_boundingbox(false)
+    }
+
+    fn compute_boundingbox(&self, with_fields: bool) -> AABB {
         let mut max_x = f32::MIN;
         let mut min_x = f32::MAX;
         let mut max_y = f32::MIN;
@@ -89,17 +121,36 @@ impl Component {
                         max_y = max_y.max(p.y);
                         min_y = min_y.min(p.y);
                     }
-                }
-                _ => ()
+                },
+                &GraphicElement::CircleArc { ref center, radius, start_angle, end_angle, .. } => {
+                    // KiCad stores the angles in tenths of a degree.
+                    let bb = arc_aabb(
+                        center,
+                        radius,
+                        (start_angle as f32 / 10.0).to_radians(),
+                        (end_angle as f32 / 10.0).to_radians()
+                    );
+                    max_x = max_x.max(bb.maxs().x);
+                    min_x = min_x.min(bb.mins().x);
+                    max_y = max_y.max(bb.maxs().y);
+                    min_y = min_y.min(bb.mins().y);
+                },
+                &GraphicElement::TextField { ref content, ref orientation, ref position, dimension, .. } => {
+                    let bb = text_aabb(position, content, dimension as f32, orientation, &Justify::Center, &Justify::Center);
+                    max_x = max_x.max(bb.maxs().x);
+                    min_x = min_x.min(bb.mins().x);
+                    max_y = max_y.max(bb.maxs().y);
+                    min_y = min_y.min(bb.mins().y);
+                },
             }
         }
 
-        for field in &self.fields {
-            if field.visible {
-                max_x = max_x.max(field.position.x + field.dimension as f32 / 2.0);
-                min_x = min_x.min(field.position.x - field.dimension as f32 / 2.0);
-                max_y = max_y.max(field.position.y + field.dimension as f32 / 2.0);
-                min_y = min_y.min(field.position.y - field.dimension as f32 / 2.0);
+        if with_fields {
+            for (_, bb) in self.get_field_boundingboxes() {
+                max_x = max_x.max(bb.maxs().x);
+                min_x = min_x.min(bb.mins().x);
+                max_y = max_y.max(bb.maxs().y);
+                min_y = min_y.min(bb.mins().y);
             }
         }
 
@@ -107,18 +158,59 @@ impl Component {
         && max_y > f32::MIN
         && min_x < f32::MAX
         && min_y < f32::MAX {
-            self.bounding_box.set(Some(AABB::new(
+            AABB::new(
                 Point2::new(min_x, min_y),
                 Point2::new(max_x, max_y)
-            )))
+            )
         } else {
-            self.bounding_box.set(Some(AABB::new(
+            AABB::new(
                 Point2::new(0.0, 0.0),
                 Point2::new(0.0, 0.0)
-            )))
+            )
         }
     }
 
+    /// Returns the field number and the bounding box of every visible field in the coordinate system of the component.
+    pub fn get_field_boundingboxes(&self) -> Vec<(isize, AABB)> {
+        self.fields.iter().filter(|field| field.visible).map(|field| {
+            (field.n, text_aabb(&field.position, &field.text, field.dimension as f32, &field.orientation, &field.hjustify, &field.vjustify))
+        }).collect()
+    }
+
+    /// Returns the distance between `point` and the closest graphic element of the body, leaving out the pins.
+    /// Rectangles, circles and filled polygons are solid, so the distance is zero anywhere inside them.
+    /// `point` is in the coordinate system of the component. Returns `None` if the body has no graphic elements.
+    pub fn get_body_distance(&self, point: &Point2) -> Option<f32> {
+        self.graphic_elements.iter()
+            .filter_map(|element| get_element_distance(element, point))
+            .fold(None, |min: Option<f32>, d| Some(min.map_or(d, |m| m.min(d))))
+    }
+
+    /// Returns the index of the graphic element and the distance to `point` for every pin of the component.
+    /// `point` is in the coordinate system of the component.
+    pub fn get_pin_distances(&self, point: &Point2) -> Vec<(usize, f32)> {
+        self.graphic_elements.iter().enumerate().filter_map(|(i, element)| match element {
+            &GraphicElement::Pin { ref position, ref orientation, length, .. } => {
+                let end = position + orientation.unit_vec() * (length as f32);
+                Some((i, distance_point_segment(point, position, &end)))
+            },
+            _ => None,
+        }).collect()
+    }
+
+    /// Returns the number of body styles of the component, 2 if it has a De Morgan one and 1 otherwise.
+    pub fn get_body_style_count(&self) -> usize {
+        let has_de_morgan = self.graphic_elements.iter().any(|element| match element {
+            &GraphicElement::Polygon { convert, .. }
+            | &GraphicElement::Rectangle { convert, .. }
+            | &GraphicElement::Circle { convert, .. }
+            | &GraphicElement::CircleArc { convert, .. }
+            | &GraphicElement::TextField { convert, .. }
+            | &GraphicElement::Pin { convert, .. } => convert == 2,
+        });
+        if has_de_morgan { 2 } else { 1 }
+    }
+
     pub fn get_boundingbox(&self) -> AABB {
         use utils::traits::CellCopy;
         self.bounding_box.copy().take().unwrap_or_else(|| {
@@ -129,7 +221,51 @@ impl Component {
     }
 }
 
-#[derive(Debug, Clone)]
+/// Returns the distance between `point` and a graphic element of the body or `None` for pins.
+fn get_element_distance(element: &GraphicElement, point: &Point2) -> Option<f32> {
+    match element {
+        &GraphicElement::Rectangle { ref start, ref end, .. } => {
+            let aabb = AABB::new(
+                Point2::new(start.x.min(end.x), start.y.min(end.y)),
+                Point2::new(start.x.max(end.x), start.y.max(end.y))
+            );
+            Some(distance_point_aabb(point, &aabb))
+        },
+        &GraphicElement::Circle { ref center, radius, .. } => {
+            Some(((point - center).norm() - radius).max(0.0))
+        },
+        &GraphicElement::CircleArc { ref center, radius, start_angle, end_angle, .. } => {
+            Some(distance_point_arc(
+                point,
+                center,
+                radius,
+                (start_angle as f32 / 10.0).to_radians(),
+                (end_angle as f32 / 10.0).to_radians()
+            ))
+        },
+        &GraphicElement::Polygon { ref points, filled, .. } => {
+            if filled && point_in_polygon(point, points) {
+                return Some(0.0);
+            }
+            match points.len() {
+                0 => None,
+                1 => Some((point - points[0]).norm()),
+                _ => points.windows(2)
+                    .map(|s| distance_point_segment(point, &s[0], &s[1]))
+                    .fold(None, |min: Option<f32>, d| Some(min.map_or(d, |m| m.min(d)))),
+            }
+        },
+        &GraphicElement::TextField { ref content, ref orientation, ref position, dimension, .. } => {
+            let bb = text_aabb(position, content, dimension as f32, orientation, &Justify::Center, &Justify::Center);
+            Some(distance_point_aabb(point, &bb))
+        },
+        &GraphicElement::Pin { .. } => None,
+    }
+}
+
+/// A text field of a component like its reference or value.
+/// Fields with a number of four or higher are user defined and carry a name.
+#[derive(Debug, PartialEq, Clone)]
 pub struct Field {
     pub n: isize,
     pub text: String,
@@ -142,4 +278,23 @@ pub struct Field {
     pub italic: bool,
     pub bold: bool,
     pub name: Option<String>,
-}
\ No newline at end of file
+}
+
+impl Field {
+    /// Creates a horizontal, centered field with the default text size.
+    pub fn new(n: isize, text: String) -> Field {
+        Field {
+            n: n,
+            text: text,
+            position: Point2::origin(),
+            dimension: 50,
+            orientation: TextOrientation::Horizontal,
+            visible: true,
+            hjustify: Justify::Center,
+            vjustify: Justify::Center,
+            italic: false,
+            bold: false,
+            name: None,
+        }
+    }
+}
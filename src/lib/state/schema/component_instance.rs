@@ -1,6 +1,8 @@
 use uuid::Uuid;
 use std::cell::Cell;
 
+use ncollide2d::bounding_volume::BoundingVolume;
+
 use geometry::{
     Point2,
     Vector2,
@@ -8,9 +10,57 @@ use geometry::{
     Matrix4,
     AABB
 };
-use state::schema::component::Component;
+use state::schema::component::{
+    Component,
+    Field,
+};
+use parsing::kicad::component_library::{
+    GraphicElement,
+    Justify,
+    TextOrientation,
+};
 
 use utils::traits::clone_cached_aabb;
+use utils::text::text_aabb;
+use utils::geometry::{
+    transform_point_2d,
+    transform_aabb,
+};
+
+/// The numbers of the fields every component instance has.
+pub const REFERENCE_FIELD: isize = 0;
+pub const VALUE_FIELD: isize = 1;
+pub const FOOTPRINT_FIELD: isize = 2;
+pub const DATASHEET_FIELD: isize = 3;
+/// User defined fields are numbered from here on.
+pub const FIRST_USER_FIELD: isize = 4;
+
+/// Returns the direction a text with the given orientation reads in and the direction its top points to.
+fn get_text_axes(orientation: &TextOrientation) -> (Vector2, Vector2) {
+    match orientation {
+        TextOrientation::Horizontal => (Vector2::new(1.0, 0.0), Vector2::new(0.0, 1.0)),
+        TextOrientation::Vertical => (Vector2::new(0.0, 1.0), Vector2::new(-1.0, 0.0)),
+    }
+}
+
+/// A change of a single property of a field, such that it can be applied to many instances at once.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldEdit {
+    Text(String),
+    Name(String),
+    Visible(bool),
+    OffsetX(f32),
+    OffsetY(f32),
+}
+
+/// Where and how a text of a component instance is displayed in schema space, see `ComponentInstance::place_text`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextPlacement {
+    pub position: Point2,
+    pub orientation: TextOrientation,
+    pub hjustify: Justify,
+    pub vjustify: Justify,
+}
 
 #[derive(Derivative)]
 #[derivative(Debug, Clone)]
@@ -20,28 +70,224 @@ pub struct ComponentInstance {
     pub reference: String,
     pub position: Point2,
     pub rotation: Matrix4,
+    /// The unit of a multi unit component, starting at 1.
+    pub unit: usize,
+    /// The body style of the component, 1 for the normal one and 2 for the De Morgan one.
+    pub convert: usize,
+    /// The fields of the instance sorted by their number.
+    /// The positions of the fields are offsets from the position of the instance.
+    pub fields: Vec<Field>,
     #[derivative(Debug="ignore", Clone(clone_with="clone_cached_aabb"))]
     pub bounding_box: Cell<Option<AABB>>
 }
 
 impl ComponentInstance {
     pub fn new(name: String) -> ComponentInstance {
+        let reference = "?".to_owned();
+        let mut footprint = Field::new(FOOTPRINT_FIELD, String::new());
+        footprint.visible = false;
+        let mut datasheet = Field::new(DATASHEET_FIELD, String::new());
+        datasheet.visible = false;
         ComponentInstance {
             uuid: Uuid::nil(),
+            fields: vec![
+                Field::new(REFERENCE_FIELD, reference.clone()),
+                Field::new(VALUE_FIELD, name.clone()),
+                footprint,
+                datasheet,
+            ],
             name: name,
-            reference: "?".into(),
+            reference: reference,
             position: Point2::origin(),
             rotation: Matrix4::identity(),
+            unit: 1,
+            convert: 1,
             bounding_box: Cell::new(None)
         }
     }
 
+    /// Returns the field with the number `n` if the instance has one.
+    pub fn get_field(&self, n: isize) -> Option<&Field> {
+        self.fields.iter().find(|f| f.n == n)
+    }
+
+    /// Returns the text of the field with the number `n` or an empty string if the instance has no such field.
+    pub fn get_field_text(&self, n: isize) -> &str {
+        self.get_field(n).map_or("", |f| f.text.as_str())
+    }
+
+    /// Sets the text of the field with the number `n` and creates the field if it does not exist yet.
+    /// Setting the text of the reference field changes the reference of the instance too.
+    pub fn set_field_text(&mut self, n: isize, text: &str) {
+        if n == REFERENCE_FIELD {
+            self.reference = text.to_owned();
+        }
+        if let Some(field) = self.fields.iter_mut().find(|f| f.n == n) {
+            field.text = text.to_owned();
+            return;
+        }
+        self.fields.push(Field::new(n, text.to_owned()));
+        self.fields.sort_by_key(|f| f.n);
+    }
+
+    /// Replaces the field with the number of `field` or adds it if the instance has no such field.
+    pub fn set_field(&mut self, field: Field) {
+        if field.n == REFERENCE_FIELD {
+            self.reference = field.text.clone();
+        }
+        self.fields.retain(|f| f.n != field.n);
+        self.fields.push(field);
+        self.fields.sort_by_key(|f| f.n);
+    }
+
+    /// Adds a user defined field called `name` and returns its number.
+    pub fn add_user_field(&mut self, name: &str) -> isize {
+        let n = self.fields.iter().map(|f| f.n + 1).max().unwrap_or(0).max(FIRST_USER_FIELD);
+        let mut field = Field::new(n, String::new());
+        field.name = Some(name.to_owned());
+        self.fields.push(field);
+        n
+    }
+
+    /// Returns the field of the instance which corresponds to `field` of another instance.
+    /// The fields every instance has are matched by their number, user defined fields by their name.
+    pub fn find_matching_field(&self, field: &Field) -> Option<&Field> {
+        self.fields.iter().find(|f| fields_match(f, field))
+    }
+
+    /// Applies `edit` to the field corresponding to `field`, see `find_matching_field`.
+    /// A user defined field is not renamed to an empty name or to the name of another one, as user defined fields are matched by their names.
+    pub fn edit_field(&mut self, field: &Field, edit: &FieldEdit) {
+        let mut edited = match self.find_matching_field(field) {
+            Some(f) => f.clone(),
+            None => return,
+        };
+        match edit {
+            FieldEdit::Text(text) => edited.text = text.clone(),
+            FieldEdit::Name(name) => {
+                let taken = self.fields.iter().any(|f| f.n >= FIRST_USER_FIELD && f.n != edited.n && f.name.as_ref() == Some(name));
+                if name.is_empty() || taken {
+                    return;
+                }
+                edited.name = Some(name.clone());
+            },
+            FieldEdit::Visible(visible) => edited.visible = *visible,
+            FieldEdit::OffsetX(x) => edited.position.x = *x,
+            FieldEdit::OffsetY(y) => edited.position.y = *y,
+        }
+        self.set_field(edited);
+    }
+
+    /// Adds a user defined field called `name` unless the instance has one already.
+    pub fn ensure_user_field(&mut self, name: &str) {
+        if !self.fields.iter().any(|f| f.n >= FIRST_USER_FIELD && f.name.as_ref().map_or(false, |n| n == name)) {
+            self.add_user_field(name);
+        }
+    }
+
+    /// Removes the user defined field corresponding to `field`, see `find_matching_field`.
+    pub fn remove_matching_user_field(&mut self, field: &Field) {
+        if let Some(n) = self.find_matching_field(field).map(|f| f.n) {
+            self.remove_user_field(n);
+        }
+    }
+
+    /// Removes the user defined field with the number `n`. The fields every instance has can not be removed.
+    pub fn remove_user_field(&mut self, n: isize) {
+        if n >= FIRST_USER_FIELD {
+            self.fields.retain(|f| f.n != n);
+        }
+    }
+
+    /// Returns the rotation of the instance in degrees counterclockwise.
+    pub fn get_angle(&self) -> f32 {
+        self.rotation[(1, 0)].atan2(self.rotation[(0, 0)]).to_degrees()
+    }
+
+    /// Returns true if the instance is mirrored.
+    pub fn is_mirrored(&self) -> bool {
+        let r = &self.rotation;
+        r[(0, 0)] * r[(1, 1)] - r[(0, 1)] * r[(1, 0)] < 0.0
+    }
+
+    /// Rotates the instance by `angle` degrees counterclockwise after mirroring it at the x axis if `mirrored` is set.
+    pub fn set_orientation(&mut self, angle: f32, mirrored: bool) {
+        let mirror = if mirrored {
+            Matrix4::new_nonuniform_scaling(&Vector3::new(1.0, -1.0, 1.0))
+        } else {
+            Matrix4::identity()
+        };
+        self.rotation = Matrix4::from_axis_angle(&Vector3::z_axis(), angle.to_radians()) * mirror;
+    }
+
+    /// Returns the bounding box of the instance in schema space, taking its rotation and mirroring into account.
+    /// It covers the body of the component and the visible fields of the instance, wherever they were moved to.
     pub fn get_boundingbox(&self, component: &Component) -> AABB {
-        use utils::traits::Translatable;
-        component.get_boundingbox().translated(Vector2::new(
-            self.position.x,
-            self.position.y
-        ))
+        self.get_field_boundingboxes().iter().fold(
+            transform_aabb(&component.get_body_boundingbox(), &self.get_transform()),
+            |aabb, (_, field)| aabb.merged(field)
+        )
+    }
+
+    /// Returns the field number and the bounding box in schema space of every visible field of the instance.
+    pub fn get_field_boundingboxes(&self) -> Vec<(isize, AABB)> {
+        self.fields.iter().filter(|field| field.visible).map(|field| {
+            let placement = self.get_field_placement(field);
+            (field.n, text_aabb(&placement.position, &field.text, field.dimension as f32, &placement.orientation, &placement.hjustify, &placement.vjustify))
+        }).collect()
+    }
+
+    /// Returns how a field of the instance is displayed in schema space.
+    /// The offset of the field turns and mirrors with the instance, like in KiCad.
+    pub fn get_field_placement(&self, field: &Field) -> TextPlacement {
+        self.place_text(self.position + self.transform_vector(&field.position.coords), &field.orientation, &field.hjustify, &field.vjustify)
+    }
+
+    /// Places a text of the instance at `position` in schema space, such that it still reads from left to right or from bottom to top.
+    /// The text turns with the instance, but where the instance would mirror it or turn it upside down its justification flips instead.
+    /// That way the text keeps the side it is anchored at, a left justified pin name still starts at its pin on a mirrored instance.
+    pub fn place_text(&self, position: Point2, orientation: &TextOrientation, hjustify: &Justify, vjustify: &Justify) -> TextPlacement {
+        let (reading, up) = get_text_axes(orientation);
+        let reading = self.transform_vector(&reading);
+        let up = self.transform_vector(&up);
+        let orientation = if reading.x.abs() >= reading.y.abs() {
+            TextOrientation::Horizontal
+        } else {
+            TextOrientation::Vertical
+        };
+        let (readable, readable_up) = get_text_axes(&orientation);
+        TextPlacement {
+            position: position,
+            orientation: orientation,
+            hjustify: if reading.dot(&readable) < 0.0 { hjustify.flipped() } else { hjustify.clone() },
+            vjustify: if up.dot(&readable_up) < 0.0 { vjustify.flipped() } else { vjustify.clone() },
+        }
+    }
+
+    /// Returns the index of the graphic element, the pin number and the position in schema space for every pin of the instance.
+    pub fn get_pin_positions(&self, component: &Component) -> Vec<(usize, usize, Point2)> {
+        component.graphic_elements.iter().enumerate().filter_map(|(i, element)| match element {
+            GraphicElement::Pin { position, number, .. } => Some((i, *number, self.transform_point(position))),
+            _ => None,
+        }).collect()
+    }
+
+    /// Transforms a point from the coordinate system of the component into schema space.
+    pub fn transform_point(&self, point: &Point2) -> Point2 {
+        transform_point_2d(point, &self.get_transform())
+    }
+
+    /// Turns and mirrors a direction from the coordinate system of the component into schema space.
+    pub fn transform_vector(&self, vector: &Vector2) -> Vector2 {
+        let r = &self.rotation;
+        Vector2::new(r[(0, 0)] * vector.x + r[(0, 1)] * vector.y, r[(1, 0)] * vector.x + r[(1, 1)] * vector.y)
+    }
+
+    /// Transforms a point from schema space into the coordinate system of the component.
+    pub fn to_component_space(&self, point: &Point2) -> Point2 {
+        self.get_transform()
+            .try_inverse()
+            .map_or(*point, |inverse| transform_point_2d(point, &inverse))
     }
 
     pub fn get_transform(&self) -> Matrix4 {
@@ -53,4 +299,148 @@ impl ComponentInstance {
             )
         )
     }
-}
\ No newline at end of file
+}
+
+/// Returns true if `a` and `b` are the same field of two different instances.
+fn fields_match(a: &Field, b: &Field) -> bool {
+    if a.n < FIRST_USER_FIELD || b.n < FIRST_USER_FIELD {
+        a.n == b.n
+    } else {
+        a.name == b.name
+    }
+}
+
+/// Returns the fields all of the `instances` have, as they are found on the first instance.
+/// Each field comes with a flag which is set if the instances disagree on its text.
+pub fn get_shared_fields(instances: &[ComponentInstance]) -> Vec<(Field, bool)> {
+    let first = match instances.first() {
+        Some(first) => first,
+        None => return Vec::new(),
+    };
+    first.fields.iter().filter_map(|field| {
+        let mut mixed = false;
+        for instance in &instances[1..] {
+            match instance.find_matching_field(field) {
+                Some(other) => mixed |= other.text != field.text,
+                None => return None,
+            }
+        }
+        Some((field.clone(), mixed))
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn placement(angle: f32, mirrored: bool, orientation: TextOrientation) -> TextPlacement {
+        let mut instance = ComponentInstance::new("R".into());
+        instance.position = Point2::new(100.0, 100.0);
+        instance.set_orientation(angle, mirrored);
+        let mut field = Field::new(VALUE_FIELD, "10k".into());
+        field.position = Point2::new(50.0, 0.0);
+        field.orientation = orientation;
+        field.hjustify = Justify::Left;
+        field.vjustify = Justify::Bottom;
+        instance.get_field_placement(&field)
+    }
+
+    #[test]
+    fn fields_stay_readable() {
+        use self::Justify::*;
+        use self::TextOrientation::*;
+
+        // The offset of a field turns with the instance.
+        assert_eq!(placement(0.0, false, Horizontal), TextPlacement { position: Point2::new(150.0, 100.0), orientation: Horizontal, hjustify: Left, vjustify: Bottom });
+        assert!((placement(90.0, false, Horizontal).position - Point2::new(100.0, 150.0)).norm() < 1e-4);
+        assert!((placement(0.0, true, Horizontal).position - Point2::new(150.0, 100.0)).norm() < 1e-4);
+
+        let turned = |angle, mirrored, orientation| {
+            let p = placement(angle, mirrored, orientation);
+            (p.orientation, p.hjustify, p.vjustify)
+        };
+        assert_eq!(turned(90.0, false, Horizontal), (Vertical, Left, Bottom));
+        assert_eq!(turned(180.0, false, Horizontal), (Horizontal, Right, Top));
+        assert_eq!(turned(270.0, false, Horizontal), (Vertical, Right, Top));
+        assert_eq!(turned(270.0, false, Vertical), (Horizontal, Left, Bottom));
+        // Mirroring at the x axis turns the text upside down, a quarter turn more puts it on its end.
+        assert_eq!(turned(0.0, true, Horizontal), (Horizontal, Left, Top));
+        assert_eq!(turned(0.0, true, Vertical), (Vertical, Right, Bottom));
+        assert_eq!(turned(180.0, true, Horizontal), (Horizontal, Right, Bottom));
+        assert_eq!(turned(90.0, true, Horizontal), (Vertical, Left, Top));
+    }
+
+    #[test]
+    fn field_boxes_turn_with_the_instance() {
+        use utils::geometry::distance_point_aabb;
+
+        let mut instance = ComponentInstance::new("R".into());
+        instance.position = Point2::new(100.0, 100.0);
+        let mut field = Field::new(VALUE_FIELD, "10k".into());
+        field.position = Point2::new(200.0, 0.0);
+        instance.set_field(field);
+
+        let value_box = |instance: &ComponentInstance| {
+            instance.get_field_boundingboxes().into_iter().find(|(n, _)| *n == VALUE_FIELD).unwrap().1
+        };
+        assert_eq!(distance_point_aabb(&Point2::new(300.0, 100.0), &value_box(&instance)), 0.0);
+
+        // A quarter turn counterclockwise moves the field above the instance.
+        instance.set_orientation(90.0, false);
+        let turned = value_box(&instance);
+        assert_eq!(distance_point_aabb(&Point2::new(100.0, 300.0), &turned), 0.0);
+        assert!(distance_point_aabb(&Point2::new(300.0, 100.0), &turned) > 0.0);
+    }
+
+    #[test]
+    fn boundingbox_covers_moved_fields() {
+        use state::schema::component::OptionFlag;
+
+        // The body spans 100 to each side, the value field is moved far to the right of it.
+        let component = Component {
+            uuid: Uuid::nil(),
+            name: "R".into(),
+            reference: "R".into(),
+            text_offset: 0,
+            draw_pin_number: true,
+            draw_pin_name: true,
+            unit_count: 1,
+            units_locked: false,
+            option_flag: OptionFlag::Normal,
+            fields: Vec::new(),
+            alias: Vec::new(),
+            graphic_elements: vec![GraphicElement::Rectangle {
+                start: Point2::new(-50.0, -100.0),
+                end: Point2::new(50.0, 100.0),
+                unit: 1,
+                convert: 1,
+                filled: false,
+            }],
+            pins: Vec::new(),
+            bounding_box: Cell::new(None),
+        };
+        let mut instance = ComponentInstance::new("R".into());
+        instance.position = Point2::new(1000.0, 0.0);
+        instance.edit_field(&Field::new(VALUE_FIELD, String::new()), &FieldEdit::OffsetX(500.0));
+
+        let aabb = instance.get_boundingbox(&component);
+        assert_eq!(aabb.mins().y, -100.0);
+        assert_eq!(aabb.maxs().y, 100.0);
+        assert!(aabb.maxs().x > 1500.0);
+    }
+
+    #[test]
+    fn user_field_names_stay_unique() {
+        let mut instance = ComponentInstance::new("R".into());
+        let mpn = instance.add_user_field("MPN");
+        instance.add_user_field("Supplier");
+        let field = instance.get_field(mpn).unwrap().clone();
+
+        instance.edit_field(&field, &FieldEdit::Name(String::new()));
+        instance.edit_field(&field, &FieldEdit::Name("Supplier".into()));
+        assert_eq!(instance.get_field(mpn).unwrap().name, Some("MPN".to_owned()));
+
+        instance.edit_field(&field, &FieldEdit::Name("Part Number".into()));
+        assert_eq!(instance.get_field(mpn).unwrap().name, Some("Part Number".to_owned()));
+    }
+}
@@ -0,0 +1,210 @@
+use uuid::Uuid;
+
+use geometry::*;
+use state::component_libraries::ComponentLibraries;
+use parsing::kicad::schema::{
+    WireSegment,
+    Note,
+    NoteLine,
+    BusEntry,
+};
+use super::{
+    Schema,
+    CONNECTION_TOLERANCE,
+};
+
+/// A `Drag` moves a group of components, wires, labels, bus entries, notes and note lines along with the cursor.
+/// If it was started with attached wires, the ends of those wires follow the pins of the components like rubber bands.
+/// Wires which are horizontal or vertical get a dogleg such that they stay orthogonal.
+/// A single horizontal or vertical wire segment only moves perpendicular to itself, such that its neighbours just stretch.
+pub struct Drag {
+    origin: Point2,
+    /// The direction the drag is restricted to, if any.
+    constraint: Option<Vector2>,
+    components: Vec<(Uuid, Point2)>,
+    labels: Vec<(Uuid, Point2)>,
+    notes: Vec<(Uuid, Point2)>,
+    /// The bus entries as they were when the drag started.
+    bus_entries: Vec<BusEntry>,
+    /// The note lines as they were when the drag started.
+    note_lines: Vec<NoteLine>,
+    wires: Vec<AttachedWire>,
+}
+
+/// A wire which is dragged as a whole or which has at least one end on a pin of a dragged component.
+struct AttachedWire {
+    /// The wire as it was when the drag started.
+    wire: WireSegment,
+    moves_start: bool,
+    moves_end: bool,
+    /// The wire segment which connects the corner of the dogleg to the moving end.
+    dogleg: Option<Uuid>,
+}
+
+impl Drag {
+    /// Starts dragging the components, wires, labels, bus entries, notes and note lines among `items` from `origin` on. Junctions are placed again when the drag finishes.
+    /// `attached_wires` holds the `Uuid` of every wire which touches a pin of one of the components or an end of one of the wires
+    /// together with the position of that pin or end. If it is empty, the items are detached from their wires.
+    /// To drag the end of a wire alone, pass no `items` and the wires which end there as `attached_wires`.
+    pub fn new(schema: &mut Schema, origin: Point2, items: &[Uuid], attached_wires: &[(Uuid, Point2)]) -> Drag {
+        let components: Vec<_> = items.iter()
+            .filter_map(|uuid| schema.find_component_instance(uuid).map(|instance| (*uuid, instance.position)))
+            .collect();
+        let labels: Vec<_> = schema.get_labels().iter()
+            .filter(|label| items.contains(&label.uuid))
+            .map(|label| (label.uuid, label.position))
+            .collect();
+        let notes: Vec<_> = schema.get_notes().iter()
+            .filter(|note| items.contains(&note.uuid))
+            .map(|note| (note.uuid, note.position))
+            .collect();
+        let bus_entries: Vec<_> = schema.get_bus_entries().iter()
+            .filter(|entry| items.contains(&entry.uuid))
+            .cloned()
+            .collect();
+        let note_lines: Vec<_> = schema.get_note_lines().iter()
+            .filter(|line| items.contains(&line.uuid))
+            .cloned()
+            .collect();
+
+        // Wires which are dragged themselves move at both ends.
+        let mut wires: Vec<AttachedWire> = schema.get_wires().iter()
+            .filter(|wire| items.contains(&wire.uuid))
+            .map(|wire| AttachedWire { wire: wire.clone(), moves_start: true, moves_end: true, dogleg: None })
+            .collect();
+
+        let others = components.len() + labels.len() + bus_entries.len() + notes.len() + note_lines.len();
+        let constraint = match (others, &wires[..]) {
+            (0, [attached]) if (attached.wire.start.y - attached.wire.end.y).abs() <= CONNECTION_TOLERANCE => Some(Vector2::new(0.0, 1.0)),
+            (0, [attached]) if (attached.wire.start.x - attached.wire.end.x).abs() <= CONNECTION_TOLERANCE => Some(Vector2::new(1.0, 0.0)),
+            _ => None,
+        };
+
+        for (wire_uuid, pin) in attached_wires {
+            let index = match wires.iter().position(|w| w.wire.uuid == *wire_uuid) {
+                Some(index) => index,
+                None => {
+                    let wire = schema.get_wire_instance(*wire_uuid).clone();
+                    wires.push(AttachedWire { wire: wire, moves_start: false, moves_end: false, dogleg: None });
+                    wires.len() - 1
+                },
+            };
+            let attached = &mut wires[index];
+            attached.moves_start |= (attached.wire.start - pin).norm() <= CONNECTION_TOLERANCE;
+            attached.moves_end |= (attached.wire.end - pin).norm() <= CONNECTION_TOLERANCE;
+        }
+
+        // Orthogonal wires which only move at one end need a second segment to stay orthogonal.
+        for attached in &mut wires {
+            let (fixed, moving) = match (attached.moves_start, attached.moves_end) {
+                (true, false) => (attached.wire.end, attached.wire.start),
+                (false, true) => (attached.wire.start, attached.wire.end),
+                _ => continue,
+            };
+            if is_orthogonal(&fixed, &moving) {
+                attached.dogleg = Some(schema.add_wire(WireSegment {
+                    uuid: Uuid::nil(),
+                    kind: attached.wire.kind.clone(),
+                    start: moving,
+                    end: moving,
+                }));
+            }
+        }
+
+        Drag {
+            origin: origin,
+            constraint: constraint,
+            components: components,
+            labels: labels,
+            notes: notes,
+            bus_entries: bus_entries,
+            note_lines: note_lines,
+            wires: wires,
+        }
+    }
+
+    /// Moves the components and their attached wires such that they follow the cursor.
+    pub fn update(&self, schema: &mut Schema, cursor: Point2) {
+        let mut delta = cursor - self.origin;
+        if let Some(direction) = self.constraint {
+            delta = direction * delta.dot(&direction);
+        }
+
+        for (uuid, position) in &self.components {
+            schema.move_component(uuid, (position + delta) - Point2::origin());
+        }
+
+        for (uuid, position) in &self.labels {
+            schema.move_label(*uuid, position + delta);
+        }
+
+        for (uuid, position) in &self.notes {
+            if let Some(note) = schema.find_note(uuid).cloned() {
+                schema.update_note(Note { position: position + delta, ..note });
+            }
+        }
+
+        for entry in &self.bus_entries {
+            schema.update_bus_entry(BusEntry { start: entry.start + delta, end: entry.end + delta, ..entry.clone() });
+        }
+
+        for line in &self.note_lines {
+            schema.update_note_line(NoteLine { start: line.start + delta, end: line.end + delta, ..line.clone() });
+        }
+
+        for attached in &self.wires {
+            let mut wire = attached.wire.clone();
+            match (attached.moves_start, attached.moves_end) {
+                (true, true) => {
+                    wire.start += delta;
+                    wire.end += delta;
+                },
+                (true, false) => {
+                    let (fixed, moving) = (wire.end, wire.start);
+                    wire.start = self.update_dogleg(schema, attached, &fixed, &moving, moving + delta);
+                },
+                (false, true) => {
+                    let (fixed, moving) = (wire.start, wire.end);
+                    wire.end = self.update_dogleg(schema, attached, &fixed, &moving, moving + delta);
+                },
+                (false, false) => continue,
+            }
+            schema.update_wire(wire);
+        }
+    }
+
+    /// Finishes the drag and cleans up the doglegs which were not needed.
+    pub fn finish(self, schema: &mut Schema, libraries: &ComponentLibraries) {
+        schema.cleanup_wires(libraries);
+    }
+
+    /// Moves everything back to where it was when the drag started by reverting all the changes made since.
+    pub fn cancel(self, schema: &mut Schema) {
+        schema.rollback();
+    }
+
+    /// Routes the dogleg of an attached wire to the `moved` end and returns the point where the attached wire has to end now.
+    /// The segment at the pin keeps the direction the wire had, the segment at the fixed end takes up the offset.
+    fn update_dogleg(&self, schema: &mut Schema, attached: &AttachedWire, fixed: &Point2, moving: &Point2, moved: Point2) -> Point2 {
+        match attached.dogleg {
+            Some(dogleg) => {
+                let corner = if (fixed.y - moving.y).abs() <= CONNECTION_TOLERANCE {
+                    Point2::new(fixed.x, moved.y)
+                } else {
+                    Point2::new(moved.x, fixed.y)
+                };
+                let mut segment = schema.get_wire_instance(dogleg).clone();
+                segment.start = corner;
+                segment.end = moved;
+                schema.update_wire(segment);
+                corner
+            },
+            None => moved,
+        }
+    }
+}
+
+/// Returns true if the segment between the two points is horizontal or vertical.
+fn is_orthogonal(a: &Point2, b: &Point2) -> bool {
+    (a.x - b.x).abs() <= CONNECTION_TOLERANCE || (a.y - b.y).abs() <= CONNECTION_TOLERANCE
+}
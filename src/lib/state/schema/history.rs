@@ -0,0 +1,213 @@
+use std::collections::VecDeque;
+
+use uuid::Uuid;
+
+use parsing::kicad::schema::{
+    WireSegment,
+    Label,
+    Junction,
+    Note,
+    NoteLine,
+    BusEntry,
+    BusAlias,
+};
+use super::ComponentInstance;
+
+/// The number of steps which can be undone if nothing else is configured.
+pub const DEFAULT_HISTORY_DEPTH: usize = 100;
+
+/// A single invertible mutation of the schema.
+/// Each command carries the complete state of the item it touches, such that it can be applied and reverted without looking at the schema.
+#[derive(Clone, Debug)]
+pub enum Command {
+    AddComponent(ComponentInstance),
+    RemoveComponent(ComponentInstance),
+    UpdateComponent { before: ComponentInstance, after: ComponentInstance },
+    AddWire(WireSegment),
+    RemoveWire(WireSegment),
+    UpdateWire { before: WireSegment, after: WireSegment },
+    AddLabel(Label),
+    RemoveLabel(Label),
+    UpdateLabel { before: Label, after: Label },
+    AddJunction(Junction),
+    RemoveJunction(Junction),
+    AddNote(Note),
+    RemoveNote(Note),
+    UpdateNote { before: Note, after: Note },
+    AddNoteLine(NoteLine),
+    RemoveNoteLine(NoteLine),
+    UpdateNoteLine { before: NoteLine, after: NoteLine },
+    AddBusEntry(BusEntry),
+    RemoveBusEntry(BusEntry),
+    UpdateBusEntry { before: BusEntry, after: BusEntry },
+    AddBusAlias(BusAlias),
+    RemoveBusAlias(BusAlias),
+}
+
+impl Command {
+    /// Returns the command which reverts this one.
+    pub fn inverse(&self) -> Command {
+        match self {
+            Command::AddComponent(instance) => Command::RemoveComponent(instance.clone()),
+            Command::RemoveComponent(instance) => Command::AddComponent(instance.clone()),
+            Command::UpdateComponent { before, after } => Command::UpdateComponent { before: after.clone(), after: before.clone() },
+            Command::AddWire(wire) => Command::RemoveWire(wire.clone()),
+            Command::RemoveWire(wire) => Command::AddWire(wire.clone()),
+            Command::UpdateWire { before, after } => Command::UpdateWire { before: after.clone(), after: before.clone() },
+            Command::AddLabel(label) => Command::RemoveLabel(label.clone()),
+            Command::RemoveLabel(label) => Command::AddLabel(label.clone()),
+            Command::UpdateLabel { before, after } => Command::UpdateLabel { before: after.clone(), after: before.clone() },
+            Command::AddJunction(junction) => Command::RemoveJunction(junction.clone()),
+            Command::RemoveJunction(junction) => Command::AddJunction(junction.clone()),
+            Command::AddNote(note) => Command::RemoveNote(note.clone()),
+            Command::RemoveNote(note) => Command::AddNote(note.clone()),
+            Command::UpdateNote { before, after } => Command::UpdateNote { before: after.clone(), after: before.clone() },
+            Command::AddNoteLine(line) => Command::RemoveNoteLine(line.clone()),
+            Command::RemoveNoteLine(line) => Command::AddNoteLine(line.clone()),
+            Command::UpdateNoteLine { before, after } => Command::UpdateNoteLine { before: after.clone(), after: before.clone() },
+            Command::AddBusEntry(entry) => Command::RemoveBusEntry(entry.clone()),
+            Command::RemoveBusEntry(entry) => Command::AddBusEntry(entry.clone()),
+            Command::UpdateBusEntry { before, after } => Command::UpdateBusEntry { before: after.clone(), after: before.clone() },
+            Command::AddBusAlias(alias) => Command::RemoveBusAlias(alias.clone()),
+            Command::RemoveBusAlias(alias) => Command::AddBusAlias(alias.clone()),
+        }
+    }
+
+    /// Returns the `Uuid` of the item the command touches.
+    /// Bus aliases are identified by their name instead, they get the nil `Uuid`, as their commands are never merged anyway.
+    pub fn get_uuid(&self) -> Uuid {
+        match self {
+            Command::AddComponent(instance) | Command::RemoveComponent(instance) => instance.uuid,
+            Command::UpdateComponent { after, .. } => after.uuid,
+            Command::AddWire(wire) | Command::RemoveWire(wire) => wire.uuid,
+            Command::UpdateWire { after, .. } => after.uuid,
+            Command::AddLabel(label) | Command::RemoveLabel(label) => label.uuid,
+            Command::UpdateLabel { after, .. } => after.uuid,
+            Command::AddJunction(junction) | Command::RemoveJunction(junction) => junction.uuid,
+            Command::AddNote(note) | Command::RemoveNote(note) => note.uuid,
+            Command::UpdateNote { after, .. } => after.uuid,
+            Command::AddNoteLine(line) | Command::RemoveNoteLine(line) => line.uuid,
+            Command::UpdateNoteLine { after, .. } => after.uuid,
+            Command::AddBusEntry(entry) | Command::RemoveBusEntry(entry) => entry.uuid,
+            Command::UpdateBusEntry { after, .. } => after.uuid,
+            Command::AddBusAlias(_) | Command::RemoveBusAlias(_) => Uuid::nil(),
+        }
+    }
+
+    /// Combines this command with a `next` one which touches the same item into a single command.
+    /// Consecutive updates collapse into one update and an update of a freshly added item collapses into the addition.
+    /// Returns `None` if the two commands cannot be merged.
+    pub fn merge(&self, next: &Command) -> Option<Command> {
+        match (self, next) {
+            (Command::UpdateComponent { before, .. }, Command::UpdateComponent { after, .. }) => {
+                Some(Command::UpdateComponent { before: before.clone(), after: after.clone() })
+            },
+            (Command::AddComponent(_), Command::UpdateComponent { after, .. }) => Some(Command::AddComponent(after.clone())),
+            (Command::UpdateWire { before, .. }, Command::UpdateWire { after, .. }) => {
+                Some(Command::UpdateWire { before: before.clone(), after: after.clone() })
+            },
+            (Command::AddWire(_), Command::UpdateWire { after, .. }) => Some(Command::AddWire(after.clone())),
+            (Command::UpdateLabel { before, .. }, Command::UpdateLabel { after, .. }) => {
+                Some(Command::UpdateLabel { before: before.clone(), after: after.clone() })
+            },
+            (Command::AddLabel(_), Command::UpdateLabel { after, .. }) => Some(Command::AddLabel(after.clone())),
+            (Command::UpdateNote { before, .. }, Command::UpdateNote { after, .. }) => {
+                Some(Command::UpdateNote { before: before.clone(), after: after.clone() })
+            },
+            (Command::AddNote(_), Command::UpdateNote { after, .. }) => Some(Command::AddNote(after.clone())),
+            (Command::UpdateNoteLine { before, .. }, Command::UpdateNoteLine { after, .. }) => {
+                Some(Command::UpdateNoteLine { before: before.clone(), after: after.clone() })
+            },
+            (Command::AddNoteLine(_), Command::UpdateNoteLine { after, .. }) => Some(Command::AddNoteLine(after.clone())),
+            (Command::UpdateBusEntry { before, .. }, Command::UpdateBusEntry { after, .. }) => {
+                Some(Command::UpdateBusEntry { before: before.clone(), after: after.clone() })
+            },
+            (Command::AddBusEntry(_), Command::UpdateBusEntry { after, .. }) => Some(Command::AddBusEntry(after.clone())),
+            _ => None,
+        }
+    }
+}
+
+/// The undo and redo stacks of a schema.
+/// Commands are recorded into a pending transaction which becomes one undoable step once it is committed.
+/// This way a whole user action like a drag is undone at once, no matter how many mutations it took.
+pub struct History {
+    undo_stack: VecDeque<Vec<Command>>,
+    redo_stack: Vec<Vec<Command>>,
+    pending: Vec<Command>,
+    depth: usize,
+}
+
+impl History {
+    /// Creates an empty history which remembers at most `depth` steps.
+    pub fn new(depth: usize) -> History {
+        History {
+            undo_stack: VecDeque::new(),
+            redo_stack: Vec::new(),
+            pending: Vec::new(),
+            depth: depth,
+        }
+    }
+
+    /// Changes the number of steps which can be undone. The oldest steps are forgotten if there are too many.
+    pub fn set_depth(&mut self, depth: usize) {
+        self.depth = depth;
+        while self.undo_stack.len() > self.depth {
+            self.undo_stack.pop_front();
+        }
+    }
+
+    /// Adds a command to the pending transaction.
+    /// It is merged with the last pending command which touches the same item if possible.
+    /// As a new action was taken, the redo stack is cleared.
+    pub fn record(&mut self, command: Command) {
+        self.redo_stack.clear();
+        let uuid = command.get_uuid();
+        if let Some(index) = self.pending.iter().rposition(|c| c.get_uuid() == uuid) {
+            if let Some(merged) = self.pending[index].merge(&command) {
+                self.pending[index] = merged;
+                return;
+            }
+        }
+        self.pending.push(command);
+    }
+
+    /// Closes the pending transaction and makes it an undoable step.
+    pub fn commit(&mut self) {
+        if !self.pending.is_empty() {
+            let transaction = self.pending.drain(..).collect();
+            self.push_undo(transaction);
+        }
+    }
+
+    /// Removes the pending transaction without committing it and returns it.
+    pub fn take_pending(&mut self) -> Vec<Command> {
+        self.pending.drain(..).collect()
+    }
+
+    pub fn pop_undo(&mut self) -> Option<Vec<Command>> {
+        self.undo_stack.pop_back()
+    }
+
+    pub fn pop_redo(&mut self) -> Option<Vec<Command>> {
+        self.redo_stack.pop()
+    }
+
+    pub fn push_undo(&mut self, transaction: Vec<Command>) {
+        self.undo_stack.push_back(transaction);
+        while self.undo_stack.len() > self.depth {
+            self.undo_stack.pop_front();
+        }
+    }
+
+    pub fn push_redo(&mut self, transaction: Vec<Command>) {
+        self.redo_stack.push(transaction);
+    }
+
+    /// Forgets everything, including the pending transaction.
+    pub fn clear(&mut self) {
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+        self.pending.clear();
+    }
+}
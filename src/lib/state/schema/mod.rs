@@ -1,8 +1,19 @@
 pub mod component;
+pub mod clipboard;
 pub mod component_instance;
+pub mod drag;
+pub mod history;
+pub mod search;
+#[cfg(test)]
+pub mod test_utils;
 
 pub use self::component::Component;
 pub use self::component_instance::ComponentInstance;
+pub use self::drag::Drag;
+pub use self::history::{
+    Command,
+    History,
+};
 pub use viewing::view_state::ViewState;
 
 use std::f32::consts::PI;
@@ -13,11 +24,27 @@ use state::event::{EventMessage, EventBusHandle};
 use geometry::*;
 use state::component_libraries::ComponentLibraries;
 use parsing::kicad::schema::*;
+use utils::geometry::{
+    distance_point_segment,
+    segment_aabb,
+};
+use self::history::DEFAULT_HISTORY_DEPTH;
 
-/// Represents a schema containing all its components and necessary resource references
+/// Two conductors closer than this are considered to be connected.
+pub const CONNECTION_TOLERANCE: f32 = 0.5;
+
+/// Represents a schema containing all its components and necessary resource references.
+/// Every mutation of the components, wires, labels, junctions, bus entries, bus aliases, notes and note lines is recorded as a `Command` such that it can be undone.
 pub struct Schema {
     components: Vec<ComponentInstance>,
     wires: Vec<WireSegment>,
+    labels: Vec<Label>,
+    junctions: Vec<Junction>,
+    notes: Vec<Note>,
+    note_lines: Vec<NoteLine>,
+    bus_entries: Vec<BusEntry>,
+    bus_aliases: Vec<BusAlias>,
+    history: History,
     event_bus: EventBusHandle,
 }
 
@@ -27,76 +54,960 @@ impl Schema {
         Schema {
             wires: Vec::new(),
             components: Vec::new(),
+            labels: Vec::new(),
+            junctions: Vec::new(),
+            notes: Vec::new(),
+            note_lines: Vec::new(),
+            bus_entries: Vec::new(),
+            bus_aliases: Vec::new(),
+            history: History::new(DEFAULT_HISTORY_DEPTH),
             event_bus: event_bus,
         }
     }
 
-    /// This function infers the bounding box containing all boundingboxes of the objects contained in the schema
+    /// This function infers the bounding box containing all boundingboxes of the components, wires, labels, notes and note lines contained in the schema.
+    /// An empty schema has an empty bounding box at the origin.
     pub fn get_bounding_box(&self, libraries: &ComponentLibraries) -> AABB {
-        let mut aabb = AABB::new(
-            Point2::new(0.0, 0.0),
-            Point2::new(0.0, 0.0)
-        );
-        self.components.iter().for_each(|instance| {
-            libraries.get_component_by_name(&instance.name).map(|component| {
-                let bb = &instance.get_boundingbox(component);
-                use ncollide2d::bounding_volume::BoundingVolume;
-                aabb.merge(bb);
-            });
-        });
-        aabb
+        use ncollide2d::bounding_volume::BoundingVolume;
+        let mut boxes = self.components.iter()
+            .filter_map(|instance| libraries.get_component_by_name(&instance.name).map(|component| instance.get_boundingbox(component)))
+            .chain(self.wires.iter().map(|wire| segment_aabb(&wire.start, &wire.end, 0.0)))
+            .chain(self.labels.iter().map(|label| segment_aabb(&label.position, &label.position, 0.0)))
+            .chain(self.notes.iter().map(|note| note.get_boundingbox()))
+            .chain(self.note_lines.iter().map(|line| segment_aabb(&line.start, &line.end, 0.0)));
+        let first = boxes.next().unwrap_or(AABB::new(Point2::origin(), Point2::origin()));
+        boxes.fold(first, |aabb, bb| aabb.merged(&bb))
     }
 
     pub fn get_component_instance(&self, component_uuid: &Uuid) -> &ComponentInstance {
         self.components.iter().find(|c| c.uuid == *component_uuid).unwrap()
     }
 
-    pub fn get_component_instance_mut(&mut self, component_uuid: &Uuid) -> &mut ComponentInstance {
-        self.components.iter_mut().find(|c| c.uuid == *component_uuid).unwrap()
+    /// Returns the component instance with the given reference, ignoring the case.
+    pub fn find_component_by_reference(&self, reference: &str) -> Option<&ComponentInstance> {
+        self.components.iter().find(|c| c.reference.eq_ignore_ascii_case(reference))
+    }
+
+    /// Returns the component instance with the given `Uuid` or `None` if the `Uuid` does not belong to a component.
+    pub fn find_component_instance(&self, component_uuid: &Uuid) -> Option<&ComponentInstance> {
+        self.components.iter().find(|c| c.uuid == *component_uuid)
     }
 
     pub fn get_wire_instance(&self, wire_uuid: Uuid) -> &WireSegment {
         self.wires.iter().find(|c| c.uuid == wire_uuid).unwrap()
     }
 
-    pub fn get_wire_instance_mut(&mut self, wire_uuid: Uuid) -> &mut WireSegment {
-        self.wires.iter_mut().find(|c| c.uuid == wire_uuid).unwrap()
+    pub fn get_label(&self, label_uuid: Uuid) -> &Label {
+        self.labels.iter().find(|l| l.uuid == label_uuid).unwrap()
+    }
+
+    pub fn get_wires(&self) -> &Vec<WireSegment> {
+        &self.wires
+    }
+
+    pub fn get_component_instances(&self) -> &Vec<ComponentInstance> {
+        &self.components
+    }
+
+    pub fn get_labels(&self) -> &Vec<Label> {
+        &self.labels
+    }
+
+    pub fn get_junctions(&self) -> &Vec<Junction> {
+        &self.junctions
+    }
+
+    pub fn get_notes(&self) -> &Vec<Note> {
+        &self.notes
+    }
+
+    pub fn get_note_lines(&self) -> &Vec<NoteLine> {
+        &self.note_lines
+    }
+
+    pub fn get_bus_entries(&self) -> &Vec<BusEntry> {
+        &self.bus_entries
+    }
+
+    /// Returns the bus entry with the given `Uuid` or `None` if the `Uuid` does not belong to a bus entry.
+    pub fn find_bus_entry(&self, entry_uuid: &Uuid) -> Option<&BusEntry> {
+        self.bus_entries.iter().find(|e| e.uuid == *entry_uuid)
+    }
+
+    /// Returns the note with the given `Uuid` or `None` if the `Uuid` does not belong to a note.
+    pub fn find_note(&self, note_uuid: &Uuid) -> Option<&Note> {
+        self.notes.iter().find(|n| n.uuid == *note_uuid)
+    }
+
+    /// Returns the positions of all the pins of all the components in the schema.
+    pub fn get_pin_positions(&self, libraries: &ComponentLibraries) -> Vec<Point2> {
+        self.components.iter().flat_map(|instance| {
+            libraries.get_component_by_name(&instance.name)
+                .map(|component| instance.get_pin_positions(component).into_iter().map(|(_, _, p)| p).collect())
+                .unwrap_or_else(Vec::new)
+        }).collect()
     }
 
     pub fn rotate_component(&mut self, component_uuid: &Uuid) {
-        let instance = self.get_component_instance_mut(component_uuid);
-        instance.rotation *= Matrix4::from_axis_angle(
+        self.transform_component(component_uuid, &Matrix4::from_axis_angle(
             &Vector3::z_axis(),
             PI / 2.0
-        );
-        let instance = instance.clone();
-        self.event_bus.send(&EventMessage::UpdateComponent(instance));
+        ));
+    }
+
+    /// Mirrors a component at the horizontal axis through its position, which turns it upside down.
+    pub fn mirror_component_x(&mut self, component_uuid: &Uuid) {
+        self.transform_component(component_uuid, &Matrix4::new_nonuniform_scaling(&Vector3::new(1.0, -1.0, 1.0)));
+    }
+
+    /// Mirrors a component at the vertical axis through its position, which swaps its left and right side.
+    pub fn mirror_component_y(&mut self, component_uuid: &Uuid) {
+        self.transform_component(component_uuid, &Matrix4::new_nonuniform_scaling(&Vector3::new(-1.0, 1.0, 1.0)));
+    }
+
+    /// Applies a rotation or mirroring in schema space to the orientation of a component.
+    /// `Uuid`s which do not belong to a component are ignored.
+    fn transform_component(&mut self, component_uuid: &Uuid, transform: &Matrix4) {
+        let before = match self.find_component_instance(component_uuid) {
+            Some(instance) => instance.clone(),
+            None => return,
+        };
+        let mut after = before.clone();
+        after.rotation = transform * before.rotation;
+        self.execute(Command::UpdateComponent { before: before, after: after });
     }
 
     pub fn add_component(&mut self, mut instance: ComponentInstance) -> Uuid {
         instance.uuid = Uuid::new_v4();
-        self.components.push(instance.clone());
-        self.event_bus.send(&EventMessage::AddComponent(instance.clone()));
-        return instance.uuid.clone()
+        let uuid = instance.uuid;
+        self.execute(Command::AddComponent(instance));
+        uuid
     }
 
-    pub fn add_wire(&mut self, mut instance: WireSegment) {
+    pub fn remove_component(&mut self, component_uuid: &Uuid) {
+        if let Some(instance) = self.find_component_instance(component_uuid).cloned() {
+            self.execute(Command::RemoveComponent(instance));
+        }
+    }
+
+    pub fn add_wire(&mut self, mut instance: WireSegment) -> Uuid {
         instance.uuid = Uuid::new_v4();
-        self.wires.push(instance.clone());
-        self.event_bus.send(&EventMessage::AddWire(instance));
+        let uuid = instance.uuid;
+        self.execute(Command::AddWire(instance));
+        uuid
+    }
+
+    pub fn add_label(&mut self, mut label: Label) -> Uuid {
+        label.uuid = Uuid::new_v4();
+        let uuid = label.uuid;
+        self.execute(Command::AddLabel(label));
+        uuid
+    }
+
+    pub fn remove_label(&mut self, label_uuid: Uuid) {
+        if let Some(label) = self.labels.iter().find(|l| l.uuid == label_uuid).cloned() {
+            self.execute(Command::RemoveLabel(label));
+        }
+    }
+
+    pub fn remove_wire(&mut self, wire_uuid: Uuid) {
+        if let Some(wire) = self.wires.iter().find(|w| w.uuid == wire_uuid).cloned() {
+            self.execute(Command::RemoveWire(wire));
+        }
+    }
+
+    pub fn add_junction(&mut self, mut junction: Junction) {
+        junction.uuid = Uuid::new_v4();
+        self.execute(Command::AddJunction(junction));
+    }
+
+    pub fn remove_junction(&mut self, junction_uuid: Uuid) {
+        if let Some(junction) = self.junctions.iter().find(|j| j.uuid == junction_uuid).cloned() {
+            self.execute(Command::RemoveJunction(junction));
+        }
+    }
+
+    /// Returns true if the junction joins three or more wire ends and pins, or wires passing through it.
+    /// Such a junction is put back by `update_junctions` right after it was removed, so it can't be deleted on its own.
+    pub fn is_junction_required(&self, junction_uuid: &Uuid, libraries: &ComponentLibraries) -> bool {
+        let position = match self.junctions.iter().find(|j| j.uuid == *junction_uuid) {
+            Some(junction) => junction.position,
+            None => return false,
+        };
+        let pins = self.get_pin_positions(libraries);
+        [WireType::Wire, WireType::Bus].iter().any(|kind| {
+            // A wire passing through the junction is split there and counts twice.
+            let wires = self.wires.iter()
+                .filter(|w| w.kind == *kind && distance_point_segment(&position, &w.start, &w.end) <= CONNECTION_TOLERANCE)
+                .map(|w| if (w.start - position).norm() > CONNECTION_TOLERANCE && (w.end - position).norm() > CONNECTION_TOLERANCE { 2 } else { 1 })
+                .sum::<usize>();
+            let pins = if *kind == WireType::Wire {
+                pins.iter().filter(|p| (*p - position).norm() <= CONNECTION_TOLERANCE).count()
+            } else {
+                0
+            };
+            wires + pins >= 3
+        })
+    }
+
+    pub fn add_note(&mut self, mut note: Note) -> Uuid {
+        note.uuid = Uuid::new_v4();
+        let uuid = note.uuid;
+        self.execute(Command::AddNote(note));
+        uuid
+    }
+
+    pub fn remove_note(&mut self, note_uuid: Uuid) {
+        if let Some(note) = self.find_note(&note_uuid).cloned() {
+            self.execute(Command::RemoveNote(note));
+        }
+    }
+
+    /// Replaces the note with the same `Uuid` as `note`, for example after its text was edited.
+    pub fn update_note(&mut self, note: Note) {
+        if let Some(before) = self.find_note(&note.uuid).cloned() {
+            self.execute(Command::UpdateNote { before: before, after: note });
+        }
+    }
+
+    pub fn add_note_line(&mut self, mut line: NoteLine) -> Uuid {
+        line.uuid = Uuid::new_v4();
+        let uuid = line.uuid;
+        self.execute(Command::AddNoteLine(line));
+        uuid
+    }
+
+    pub fn remove_note_line(&mut self, line_uuid: Uuid) {
+        if let Some(line) = self.note_lines.iter().find(|l| l.uuid == line_uuid).cloned() {
+            self.execute(Command::RemoveNoteLine(line));
+        }
+    }
+
+    pub fn update_note_line(&mut self, line: NoteLine) {
+        if let Some(before) = self.note_lines.iter().find(|l| l.uuid == line.uuid).cloned() {
+            self.execute(Command::UpdateNoteLine { before: before, after: line });
+        }
+    }
+
+    /// Removes the items with the given `Uuid`s, no matter if they are components, wires, labels, junctions, bus entries, notes or note lines.
+    /// `Uuid`s which belong to no item are ignored.
+    pub fn remove_items(&mut self, uuids: &[Uuid]) {
+        for uuid in uuids {
+            self.remove_component(uuid);
+            self.remove_wire(*uuid);
+            self.remove_label(*uuid);
+            self.remove_junction(*uuid);
+            self.remove_bus_entry(*uuid);
+            self.remove_note(*uuid);
+            self.remove_note_line(*uuid);
+        }
+    }
+
+    /// Closes the current transaction, such that all the mutations since the last commit are undone in one step.
+    /// This should be called once a user action is complete.
+    pub fn commit(&mut self) {
+        self.history.commit();
+    }
+
+    /// Reverts all the mutations since the last commit.
+    pub fn rollback(&mut self) {
+        let pending = self.history.take_pending();
+        for command in pending.iter().rev() {
+            self.apply(&command.inverse());
+        }
+    }
+
+    /// Reverts the last committed step.
+    /// Returns false if there was nothing to undo.
+    pub fn undo(&mut self) -> bool {
+        self.history.commit();
+        match self.history.pop_undo() {
+            Some(transaction) => {
+                for command in transaction.iter().rev() {
+                    self.apply(&command.inverse());
+                }
+                self.history.push_redo(transaction);
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Applies the last undone step again.
+    /// Returns false if there was nothing to redo.
+    pub fn redo(&mut self) -> bool {
+        self.history.commit();
+        match self.history.pop_redo() {
+            Some(transaction) => {
+                for command in &transaction {
+                    self.apply(command);
+                }
+                self.history.push_undo(transaction);
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Sets the number of steps which can be undone.
+    pub fn set_history_depth(&mut self, depth: usize) {
+        self.history.set_depth(depth);
+    }
+
+    /// Forgets all the recorded steps. Used after loading a file, which should not be undoable.
+    pub fn clear_history(&mut self) {
+        self.history.clear();
+    }
+
+    /// Applies a command and records it in the history.
+    fn execute(&mut self, command: Command) {
+        self.apply(&command);
+        self.history.record(command);
+    }
+
+    /// Applies a command to the schema and notifies the listeners, without recording it.
+    fn apply(&mut self, command: &Command) {
+        match command {
+            Command::AddComponent(instance) => {
+                self.components.push(instance.clone());
+                self.event_bus.send(&EventMessage::AddComponent(instance.clone()));
+            },
+            Command::RemoveComponent(instance) => {
+                self.components.retain(|c| c.uuid != instance.uuid);
+                self.event_bus.send(&EventMessage::RemoveComponent(instance.clone()));
+            },
+            Command::UpdateComponent { after, .. } => {
+                if let Some(instance) = self.components.iter_mut().find(|c| c.uuid == after.uuid) {
+                    *instance = after.clone();
+                }
+                self.event_bus.send(&EventMessage::UpdateComponent(after.clone()));
+            },
+            Command::AddWire(wire) => {
+                self.wires.push(wire.clone());
+                self.event_bus.send(&EventMessage::AddWire(wire.clone()));
+            },
+            Command::RemoveWire(wire) => {
+                self.wires.retain(|w| w.uuid != wire.uuid);
+                self.event_bus.send(&EventMessage::RemoveWire(wire.clone()));
+            },
+            Command::UpdateWire { after, .. } => {
+                if let Some(wire) = self.wires.iter_mut().find(|w| w.uuid == after.uuid) {
+                    *wire = after.clone();
+                }
+                self.event_bus.send(&EventMessage::UpdateWire(after.clone()));
+            },
+            Command::AddLabel(label) => {
+                self.labels.push(label.clone());
+                self.event_bus.send(&EventMessage::AddLabel(label.clone()));
+            },
+            Command::RemoveLabel(label) => {
+                self.labels.retain(|l| l.uuid != label.uuid);
+                self.event_bus.send(&EventMessage::RemoveLabel(label.clone()));
+            },
+            Command::UpdateLabel { after, .. } => {
+                if let Some(label) = self.labels.iter_mut().find(|l| l.uuid == after.uuid) {
+                    *label = after.clone();
+                }
+                self.event_bus.send(&EventMessage::UpdateLabel(after.clone()));
+            },
+            Command::AddJunction(junction) => {
+                self.junctions.push(junction.clone());
+                self.event_bus.send(&EventMessage::AddJunction(junction.clone()));
+            },
+            Command::RemoveJunction(junction) => {
+                self.junctions.retain(|j| j.uuid != junction.uuid);
+                self.event_bus.send(&EventMessage::RemoveJunction(junction.clone()));
+            },
+            Command::AddNote(note) => {
+                self.notes.push(note.clone());
+                self.event_bus.send(&EventMessage::AddNote(note.clone()));
+            },
+            Command::RemoveNote(note) => {
+                self.notes.retain(|n| n.uuid != note.uuid);
+                self.event_bus.send(&EventMessage::RemoveNote(note.clone()));
+            },
+            Command::UpdateNote { after, .. } => {
+                if let Some(note) = self.notes.iter_mut().find(|n| n.uuid == after.uuid) {
+                    *note = after.clone();
+                }
+                self.event_bus.send(&EventMessage::UpdateNote(after.clone()));
+            },
+            Command::AddNoteLine(line) => {
+                self.note_lines.push(line.clone());
+                self.event_bus.send(&EventMessage::AddNoteLine(line.clone()));
+            },
+            Command::RemoveNoteLine(line) => {
+                self.note_lines.retain(|l| l.uuid != line.uuid);
+                self.event_bus.send(&EventMessage::RemoveNoteLine(line.clone()));
+            },
+            Command::UpdateNoteLine { after, .. } => {
+                if let Some(line) = self.note_lines.iter_mut().find(|l| l.uuid == after.uuid) {
+                    *line = after.clone();
+                }
+                self.event_bus.send(&EventMessage::UpdateNoteLine(after.clone()));
+            },
+            Command::AddBusEntry(entry) => {
+                self.bus_entries.push(entry.clone());
+                self.event_bus.send(&EventMessage::AddBusEntry(entry.clone()));
+            },
+            Command::RemoveBusEntry(entry) => {
+                self.bus_entries.retain(|e| e.uuid != entry.uuid);
+                self.event_bus.send(&EventMessage::RemoveBusEntry(entry.clone()));
+            },
+            Command::UpdateBusEntry { after, .. } => {
+                if let Some(entry) = self.bus_entries.iter_mut().find(|e| e.uuid == after.uuid) {
+                    *entry = after.clone();
+                }
+                self.event_bus.send(&EventMessage::UpdateBusEntry(after.clone()));
+            },
+            Command::AddBusAlias(alias) => {
+                self.bus_aliases.push(alias.clone());
+                self.event_bus.send(&EventMessage::AddBusAlias(alias.clone()));
+            },
+            Command::RemoveBusAlias(alias) => {
+                self.bus_aliases.retain(|a| a.name != alias.name);
+                self.event_bus.send(&EventMessage::RemoveBusAlias(alias.clone()));
+            },
+        }
+    }
+
+    /// Makes the junctions of the schema match its wires.
+    /// This has to be run after every edit of the wires or components.
+    ///
+    /// Wires are split wherever a wire end, a pin or a junction lies in their middle.
+    /// Then a junction is placed wherever three or more wire ends and pins meet
+    /// and junctions which no longer join three or more of them are removed.
+    pub fn update_junctions(&mut self, libraries: &ComponentLibraries) {
+        let pins = self.get_pin_positions(libraries);
+
+        let mut required = Vec::new();
+        for kind in &[WireType::Wire, WireType::Bus] {
+            let mut split_points = self.get_wire_ends(kind);
+            split_points.extend(self.junctions.iter().map(|j| j.position));
+            if *kind == WireType::Wire {
+                split_points.extend(pins.iter().cloned());
+            }
+            for point in split_points {
+                self.split_wires_at(kind, &point);
+            }
+
+            let mut ends = self.get_wire_ends(kind);
+            if *kind == WireType::Wire {
+                ends.extend(pins.iter().cloned());
+            }
+            for end in &ends {
+                let count = ends.iter().filter(|p| (*p - end).norm() <= CONNECTION_TOLERANCE).count();
+                if count >= 3 && !required.iter().any(|p: &Point2| (p - end).norm() <= CONNECTION_TOLERANCE) {
+                    required.push(*end);
+                }
+            }
+        }
+
+        let stale = self.junctions.iter()
+            .filter(|j| !required.iter().any(|p| (p - j.position).norm() <= CONNECTION_TOLERANCE))
+            .map(|j| j.uuid)
+            .collect::<Vec<_>>();
+        for uuid in stale {
+            self.remove_junction(uuid);
+        }
+
+        for position in required {
+            if !self.junctions.iter().any(|j| (j.position - position).norm() <= CONNECTION_TOLERANCE) {
+                self.add_junction(Junction { uuid: Uuid::nil(), position: position });
+            }
+        }
+    }
+
+    /// Normalizes the geometry of the wires without changing the connectivity of the schema.
+    /// This has to be run after every edit of the wires or components and updates the junctions too.
+    ///
+    /// Zero-length wires are removed and collinear wires of the same kind which overlap or continue each other are merged.
+    /// Wires which only touch are kept apart if another wire end or a pin sits at the point they share.
+    pub fn cleanup_wires(&mut self, libraries: &ComponentLibraries) {
+        let zero_length = self.wires.iter()
+            .filter(|w| (w.end - w.start).norm() <= CONNECTION_TOLERANCE)
+            .map(|w| w.uuid)
+            .collect::<Vec<_>>();
+        for uuid in zero_length {
+            self.remove_wire(uuid);
+        }
+
+        let pins = self.get_pin_positions(libraries);
+        while let Some((merged, obsolete)) = self.find_mergeable_wires(&pins) {
+            self.remove_wire(obsolete);
+            self.update_wire(merged);
+        }
+
+        self.update_junctions(libraries);
+    }
+
+    /// Finds two wires which can be merged.
+    /// Returns the first wire stretched over both of them and the `Uuid` of the second one.
+    fn find_mergeable_wires(&self, pins: &[Point2]) -> Option<(WireSegment, Uuid)> {
+        for (i, a) in self.wires.iter().enumerate() {
+            for b in self.wires.iter().skip(i + 1).filter(|b| b.kind == a.kind) {
+                let (start, end, overlap) = match collinear_union(a, b) {
+                    Some(union) => union,
+                    None => continue,
+                };
+                if overlap <= CONNECTION_TOLERANCE {
+                    // The wires only touch, so make sure nothing else is connected where they do.
+                    let shared = [a.start, a.end].iter()
+                        .cloned()
+                        .find(|p| (b.start - p).norm() <= CONNECTION_TOLERANCE || (b.end - p).norm() <= CONNECTION_TOLERANCE);
+                    if let Some(shared) = shared {
+                        let other_ends = self.wires.iter()
+                            .filter(|w| w.uuid != a.uuid && w.uuid != b.uuid && w.kind == a.kind)
+                            .any(|w| (w.start - shared).norm() <= CONNECTION_TOLERANCE || (w.end - shared).norm() <= CONNECTION_TOLERANCE);
+                        let pin = a.kind == WireType::Wire && pins.iter().any(|p| (p - shared).norm() <= CONNECTION_TOLERANCE);
+                        if other_ends || pin {
+                            continue;
+                        }
+                    }
+                }
+                let mut merged = a.clone();
+                merged.start = start;
+                merged.end = end;
+                return Some((merged, b.uuid));
+            }
+        }
+        None
+    }
+
+    /// Returns the start and end points of all the wires of the given kind.
+    fn get_wire_ends(&self, kind: &WireType) -> Vec<Point2> {
+        self.wires.iter()
+            .filter(|w| w.kind == *kind)
+            .flat_map(|w| vec![w.start, w.end])
+            .collect()
+    }
+
+    /// Splits every wire of the given kind which passes through `point` without ending there in two.
+    fn split_wires_at(&mut self, kind: &WireType, point: &Point2) {
+        let passes_through = |w: &WireSegment| {
+            w.kind == *kind
+            && distance_point_segment(point, &w.start, &w.end) <= CONNECTION_TOLERANCE
+            && (w.start - point).norm() > CONNECTION_TOLERANCE
+            && (w.end - point).norm() > CONNECTION_TOLERANCE
+        };
+        while let Some(wire) = self.wires.iter().find(|w| passes_through(w)).cloned() {
+            let mut first = wire.clone();
+            first.end = *point;
+            let mut second = wire;
+            second.start = *point;
+            self.update_wire(first);
+            self.add_wire(second);
+        }
+    }
+
+    pub fn add_bus_entry(&mut self, mut entry: BusEntry) -> Uuid {
+        entry.uuid = Uuid::new_v4();
+        let uuid = entry.uuid;
+        self.execute(Command::AddBusEntry(entry));
+        uuid
+    }
+
+    pub fn remove_bus_entry(&mut self, entry_uuid: Uuid) {
+        if let Some(entry) = self.find_bus_entry(&entry_uuid).cloned() {
+            self.execute(Command::RemoveBusEntry(entry));
+        }
+    }
+
+    /// Replaces the bus entry with the same `Uuid` as `entry`, for example while it is dragged.
+    pub fn update_bus_entry(&mut self, entry: BusEntry) {
+        if let Some(before) = self.find_bus_entry(&entry.uuid).cloned() {
+            self.execute(Command::UpdateBusEntry { before: before, after: entry });
+        }
+    }
+
+    /// Adds a bus alias. An alias with the same name is replaced.
+    pub fn add_bus_alias(&mut self, alias: BusAlias) {
+        if let Some(before) = self.bus_aliases.iter().find(|a| a.name == alias.name).cloned() {
+            self.execute(Command::RemoveBusAlias(before));
+        }
+        self.execute(Command::AddBusAlias(alias));
     }
 
     pub fn update_wire(&mut self, ws: WireSegment) {
-        self.wires.swap_remove(self.wires.iter().enumerate().find(|&e| e.1.uuid == ws.uuid).unwrap().0);
-        self.wires.push(ws.clone());
-        self.event_bus.send(&EventMessage::UpdateWire(ws));
+        let before = self.get_wire_instance(ws.uuid).clone();
+        self.execute(Command::UpdateWire { before: before, after: ws });
     }
 
     pub fn move_component(&mut self, component_uuid: &Uuid, translation: Vector2) {
-        let instance = self.get_component_instance_mut(component_uuid);
+        let before = self.get_component_instance(component_uuid).clone();
+        let mut after = before.clone();
         // TODO change this to a translation instead of setting the position maybe?
-        instance.position = Point2::origin() + translation.clone();
-        let instance = instance.clone();
-        self.event_bus.send(&EventMessage::UpdateComponent(instance));
+        after.position = Point2::origin() + translation.clone();
+        self.execute(Command::UpdateComponent { before: before, after: after });
+    }
+
+    /// Replaces the component instance with the same `Uuid` as `instance`, for example after its fields were edited.
+    pub fn update_component(&mut self, instance: ComponentInstance) {
+        if let Some(before) = self.find_component_instance(&instance.uuid).cloned() {
+            self.execute(Command::UpdateComponent { before: before, after: instance });
+        }
+    }
+
+    /// Replaces the label with the same `Uuid` as `label`, for example after its text was edited.
+    pub fn update_label(&mut self, label: Label) {
+        let before = self.get_label(label.uuid).clone();
+        self.execute(Command::UpdateLabel { before: before, after: label });
+    }
+
+    pub fn move_label(&mut self, label_uuid: Uuid, position: Point2) {
+        let before = self.get_label(label_uuid).clone();
+        let mut after = before.clone();
+        after.position = position;
+        self.execute(Command::UpdateLabel { before: before, after: after });
+    }
+}
+
+/// Returns the span covered by two collinear wires which overlap or touch and the length of their overlap.
+/// The overlap is zero if the wires only touch.
+/// Returns `None` if the wires are not collinear or if there is a gap between them.
+fn collinear_union(a: &WireSegment, b: &WireSegment) -> Option<(Point2, Point2, f32)> {
+    let length = (a.end - a.start).norm();
+    let direction = (a.end - a.start) / length;
+    let distance_to_line = |p: &Point2| {
+        let v = p - a.start;
+        (v - direction * v.dot(&direction)).norm()
+    };
+    if distance_to_line(&b.start) > CONNECTION_TOLERANCE || distance_to_line(&b.end) > CONNECTION_TOLERANCE {
+        return None;
+    }
+
+    let t_start = (b.start - a.start).dot(&direction);
+    let t_end = (b.end - a.start).dot(&direction);
+    let overlap = length.min(t_start.max(t_end)) - t_start.min(t_end).max(0.0);
+    if overlap < -CONNECTION_TOLERANCE {
+        return None;
+    }
+
+    // Take the outermost of the existing end points instead of calculating new ones to keep them on the grid.
+    let mut ends = vec![(0.0, a.start), (length, a.end), (t_start, b.start), (t_end, b.end)];
+    ends.sort_by(|x, y| x.0.partial_cmp(&y.0).unwrap());
+    Some((ends[0].1, ends[3].1, overlap.max(0.0)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use self::test_utils::{Fixture, wire};
+
+    #[test]
+    fn t_connection_splits_wire_and_adds_junction() {
+        let Fixture { libraries, mut schema, .. } = Fixture::new();
+
+        schema.add_wire(wire((0.0, 0.0), (200.0, 0.0)));
+        schema.add_wire(wire((100.0, 0.0), (100.0, 100.0)));
+        schema.update_junctions(&libraries);
+
+        assert_eq!(schema.get_wires().len(), 3);
+        assert_eq!(schema.get_junctions().len(), 1);
+        assert_eq!(schema.get_junctions()[0].position, Point2::new(100.0, 0.0));
+    }
+
+    #[test]
+    fn crossing_wires_get_no_junction() {
+        let Fixture { libraries, mut schema, .. } = Fixture::new();
+
+        schema.add_wire(wire((0.0, 0.0), (200.0, 0.0)));
+        schema.add_wire(wire((100.0, -100.0), (100.0, 100.0)));
+        schema.update_junctions(&libraries);
+
+        assert_eq!(schema.get_wires().len(), 2);
+        assert!(schema.get_junctions().is_empty());
+    }
+
+    #[test]
+    fn collinear_wires_are_merged() {
+        let Fixture { libraries, mut schema, .. } = Fixture::new();
+
+        schema.add_wire(wire((0.0, 0.0), (100.0, 0.0)));
+        schema.add_wire(wire((200.0, 0.0), (100.0, 0.0)));
+        schema.add_wire(wire((50.0, 0.0), (150.0, 0.0)));
+        schema.add_wire(wire((300.0, 0.0), (300.0, 0.0)));
+        schema.cleanup_wires(&libraries);
+
+        assert_eq!(schema.get_wires().len(), 1);
+        let wire = &schema.get_wires()[0];
+        assert_eq!(wire.start.x.min(wire.end.x), 0.0);
+        assert_eq!(wire.start.x.max(wire.end.x), 200.0);
+    }
+
+    #[test]
+    fn wires_meeting_at_a_t_connection_are_not_merged() {
+        let Fixture { libraries, mut schema, .. } = Fixture::new();
+
+        schema.add_wire(wire((0.0, 0.0), (100.0, 0.0)));
+        schema.add_wire(wire((100.0, 0.0), (200.0, 0.0)));
+        schema.add_wire(wire((100.0, 0.0), (100.0, 100.0)));
+        schema.cleanup_wires(&libraries);
+
+        assert_eq!(schema.get_wires().len(), 3);
+        assert_eq!(schema.get_junctions().len(), 1);
+    }
+
+    #[test]
+    fn undo_and_redo_a_step() {
+        let Fixture { libraries, mut schema, .. } = Fixture::new();
+
+        schema.add_wire(wire((0.0, 0.0), (200.0, 0.0)));
+        schema.commit();
+        schema.add_wire(wire((100.0, 0.0), (100.0, 100.0)));
+        schema.cleanup_wires(&libraries);
+        schema.commit();
+
+        assert!(schema.undo());
+        assert_eq!(schema.get_wires().len(), 1);
+        assert!(schema.get_junctions().is_empty());
+        let w = &schema.get_wires()[0];
+        assert_eq!((w.start, w.end), (Point2::new(0.0, 0.0), Point2::new(200.0, 0.0)));
+
+        assert!(schema.redo());
+        assert_eq!(schema.get_wires().len(), 3);
+        assert_eq!(schema.get_junctions().len(), 1);
+
+        assert!(schema.undo());
+        assert!(schema.undo());
+        assert!(schema.get_wires().is_empty());
+        assert!(!schema.undo());
+    }
+
+    #[test]
+    fn updates_merge_into_one_step() {
+        let Fixture { mut schema, .. } = Fixture::new();
+
+        let uuid = schema.add_wire(wire((0.0, 0.0), (100.0, 0.0)));
+        schema.commit();
+        for x in 1..10 {
+            let mut w = schema.get_wire_instance(uuid).clone();
+            w.end = Point2::new(100.0 + x as f32 * 10.0, 0.0);
+            schema.update_wire(w);
+        }
+        schema.commit();
+
+        assert!(schema.undo());
+        assert_eq!(schema.get_wire_instance(uuid).end, Point2::new(100.0, 0.0));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn remove_items_and_undo() {
+        let Fixture { libraries, mut schema, .. } = Fixture::new();
+
+        schema.add_wire(wire((0.0, 0.0), (200.0, 0.0)));
+        let stub = schema.add_wire(wire((100.0, 0.0), (100.0, 100.0)));
+        schema.cleanup_wires(&libraries);
+        schema.commit();
+
+        schema.remove_items(&[stub]);
+        schema.cleanup_wires(&libraries);
+        schema.commit();
+
+        // The junction is gone and the split wire is merged again.
+        assert_eq!(schema.get_wires().len(), 1);
+        assert!(schema.get_junctions().is_empty());
+
+        assert!(schema.undo());
+        assert_eq!(schema.get_wires().len(), 3);
+        assert_eq!(schema.get_junctions().len(), 1);
+    }
+
+    #[test]
+    fn junctions_joining_three_ends_are_required() {
+        let Fixture { libraries, mut schema, .. } = Fixture::new();
+
+        schema.add_wire(wire((0.0, 0.0), (200.0, 0.0)));
+        schema.add_wire(wire((100.0, 0.0), (100.0, 100.0)));
+        schema.add_wire(wire((0.0, 300.0), (200.0, 300.0)));
+        schema.add_wire(wire((100.0, 200.0), (100.0, 400.0)));
+        schema.add_junction(Junction { uuid: Uuid::nil(), position: Point2::new(100.0, 300.0) });
+        schema.cleanup_wires(&libraries);
+        assert_eq!(schema.get_junctions().len(), 2);
+
+        // Both the T connection and the joined crossing need their junction, which would come back after removing it.
+        for junction in schema.get_junctions() {
+            assert!(schema.is_junction_required(&junction.uuid, &libraries));
+        }
+        let t = schema.get_junctions().iter().find(|j| j.position == Point2::new(100.0, 0.0)).unwrap().uuid;
+        schema.remove_items(&[t]);
+        schema.cleanup_wires(&libraries);
+        assert_eq!(schema.get_junctions().len(), 2);
+
+        schema.add_wire(wire((0.0, 500.0), (100.0, 500.0)));
+        schema.add_junction(Junction { uuid: Uuid::nil(), position: Point2::new(50.0, 500.0) });
+        let loose = schema.get_junctions().last().unwrap().uuid;
+        assert!(!schema.is_junction_required(&loose, &libraries));
+        assert!(!schema.is_junction_required(&Uuid::new_v4(), &libraries));
+    }
+
+    #[test]
+    fn notes_and_note_lines_connect_nothing() {
+        let Fixture { libraries, mut schema, .. } = Fixture::new();
+
+        // A note line ending on the middle of a wire neither splits it nor gets a junction.
+        schema.add_wire(wire((0.0, 0.0), (200.0, 0.0)));
+        let line = schema.add_note_line(NoteLine { uuid: Uuid::nil(), start: Point2::new(100.0, 0.0), end: Point2::new(100.0, 100.0) });
+        let note = schema.add_note(Note::new("Block A".into(), Point2::new(0.0, 200.0)));
+        schema.cleanup_wires(&libraries);
+        schema.commit();
+
+        assert_eq!(schema.get_wires().len(), 1);
+        assert!(schema.get_junctions().is_empty());
+
+        schema.remove_items(&[line, note]);
+        schema.commit();
+        assert!(schema.get_note_lines().is_empty());
+        assert!(schema.get_notes().is_empty());
+
+        assert!(schema.undo());
+        assert_eq!(schema.get_note_lines().len(), 1);
+        assert_eq!(schema.find_note(&note).map(|n| n.text.as_str()), Some("Block A"));
+    }
+
+    #[test]
+    fn mirror_and_rotate_component() {
+        let Fixture { mut schema, .. } = Fixture::new();
+
+        let mut instance = ComponentInstance::new("R".into());
+        instance.position = Point2::new(100.0, 100.0);
+        let uuid = schema.add_component(instance);
+
+        // Mirroring twice at the same axis restores the orientation.
+        schema.mirror_component_x(&uuid);
+        assert_eq!(schema.get_component_instance(&uuid).transform_point(&Point2::new(10.0, 20.0)), Point2::new(110.0, 80.0));
+        schema.mirror_component_x(&uuid);
+        assert_eq!(schema.get_component_instance(&uuid).transform_point(&Point2::new(10.0, 20.0)), Point2::new(110.0, 120.0));
+
+        // The rotation happens in schema space, after the mirroring.
+        schema.mirror_component_y(&uuid);
+        schema.rotate_component(&uuid);
+        let p = schema.get_component_instance(&uuid).transform_point(&Point2::new(10.0, 0.0));
+        assert!((p - Point2::new(100.0, 90.0)).norm() < 1e-4);
+    }
+
+    #[test]
+    fn history_depth_is_limited() {
+        let Fixture { mut schema, .. } = Fixture::new();
+        schema.set_history_depth(2);
+
+        for x in 0..5 {
+            schema.add_wire(wire((x as f32 * 100.0, 0.0), (x as f32 * 100.0, 100.0)));
+            schema.commit();
+        }
+
+        assert!(schema.undo());
+        assert!(schema.undo());
+        assert!(!schema.undo());
+        assert_eq!(schema.get_wires().len(), 3);
+    }
+
+    #[test]
+    fn stale_junction_is_removed() {
+        let Fixture { libraries, mut schema, .. } = Fixture::new();
+
+        schema.add_wire(wire((0.0, 0.0), (100.0, 0.0)));
+        schema.add_wire(wire((100.0, 0.0), (200.0, 0.0)));
+        schema.add_junction(Junction { uuid: Uuid::nil(), position: Point2::new(100.0, 0.0) });
+        schema.update_junctions(&libraries);
+
+        assert!(schema.get_junctions().is_empty());
+    }
+
+    #[test]
+    fn update_component_and_undo() {
+        let Fixture { mut schema, .. } = Fixture::new();
+
+        let uuid = schema.add_component(ComponentInstance::new("R".into()));
+        schema.commit();
+
+        let mut instance = schema.get_component_instance(&uuid).clone();
+        instance.set_field_text(component_instance::VALUE_FIELD, "10k");
+        instance.set_orientation(90.0, true);
+        schema.update_component(instance);
+        schema.commit();
+
+        let instance = schema.get_component_instance(&uuid);
+        assert_eq!(instance.get_field_text(component_instance::VALUE_FIELD), "10k");
+        assert!((instance.get_angle() - 90.0).abs() < 1e-4);
+        assert!(instance.is_mirrored());
+
+        assert!(schema.undo());
+        let instance = schema.get_component_instance(&uuid);
+        assert_eq!(instance.get_field_text(component_instance::VALUE_FIELD), "R");
+        assert!(!instance.is_mirrored());
+    }
+
+    #[test]
+    fn bulk_edit_is_one_undo_step() {
+        use self::component_instance::{get_shared_fields, FieldEdit, FOOTPRINT_FIELD, VALUE_FIELD};
+
+        let Fixture { mut schema, .. } = Fixture::new();
+
+        let mut small = ComponentInstance::new("C".into());
+        small.set_field_text(VALUE_FIELD, "100n");
+        let mut large = ComponentInstance::new("C".into());
+        large.set_field_text(VALUE_FIELD, "10u");
+        let uuids = vec![schema.add_component(small), schema.add_component(large)];
+        schema.commit();
+
+        let mut instances: Vec<_> = uuids.iter().map(|uuid| schema.get_component_instance(uuid).clone()).collect();
+        let shared = get_shared_fields(&instances);
+        let (_, mixed) = shared.iter().find(|(f, _)| f.n == VALUE_FIELD).unwrap();
+        assert!(mixed);
+        let (footprint, mixed) = shared.iter().find(|(f, _)| f.n == FOOTPRINT_FIELD).unwrap().clone();
+        assert!(!mixed);
+
+        for instance in &mut instances {
+            instance.edit_field(&footprint, &FieldEdit::Text("C_0603".into()));
+            instance.ensure_user_field("DNP");
+        }
+        for instance in instances {
+            schema.update_component(instance);
+        }
+        schema.commit();
+
+        for uuid in &uuids {
+            let instance = schema.get_component_instance(uuid);
+            assert_eq!(instance.get_field_text(FOOTPRINT_FIELD), "C_0603");
+            assert!(instance.fields.iter().any(|f| f.name == Some("DNP".to_owned())));
+        }
+
+        assert!(schema.undo());
+        for uuid in &uuids {
+            assert_eq!(schema.get_component_instance(uuid).get_field_text(FOOTPRINT_FIELD), "");
+        }
+    }
+
+    #[test]
+    fn bus_entries_and_aliases_are_undoable() {
+        let Fixture { mut schema, .. } = Fixture::new();
+
+        let uuid = schema.add_bus_entry(BusEntry {
+            uuid: Uuid::nil(),
+            kind: BusEntryType::WireToBus,
+            start: Point2::new(0.0, 100.0),
+            end: Point2::new(100.0, 0.0),
+        });
+        schema.add_bus_alias(BusAlias { name: "SPI".into(), members: vec!["MOSI".into()] });
+        schema.commit();
+
+        let entry = schema.find_bus_entry(&uuid).unwrap().clone();
+        schema.update_bus_entry(BusEntry { start: Point2::new(0.0, 200.0), end: Point2::new(100.0, 100.0), ..entry });
+        schema.add_bus_alias(BusAlias { name: "SPI".into(), members: vec!["MOSI".into(), "MISO".into()] });
+        schema.commit();
+        assert_eq!(schema.find_bus_entry(&uuid).unwrap().start, Point2::new(0.0, 200.0));
+
+        assert!(schema.undo());
+        assert_eq!(schema.find_bus_entry(&uuid).unwrap().start, Point2::new(0.0, 100.0));
+
+        schema.remove_items(&[uuid]);
+        schema.commit();
+        assert!(schema.get_bus_entries().is_empty());
+
+        assert!(schema.undo());
+        assert_eq!(schema.get_bus_entries().len(), 1);
+        assert!(schema.undo());
+        assert!(schema.get_bus_entries().is_empty());
+    }
+}
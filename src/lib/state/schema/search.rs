@@ -0,0 +1,236 @@
+use regex::{
+    self,
+    Regex,
+    RegexBuilder,
+};
+use uuid::Uuid;
+
+use super::Schema;
+use super::component_instance::{
+    REFERENCE_FIELD,
+    VALUE_FIELD,
+};
+
+/// Describes what to search for in a schema and where.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchQuery {
+    pub pattern: String,
+    /// Interpret the pattern as a regular expression instead of plain text.
+    pub regex: bool,
+    /// Only match whole words.
+    pub whole_word: bool,
+    pub case_sensitive: bool,
+    pub in_references: bool,
+    pub in_values: bool,
+    /// Search the fields other than the reference and the value.
+    pub in_fields: bool,
+    pub in_labels: bool,
+    pub in_notes: bool,
+}
+
+impl SearchQuery {
+    /// Creates a case insensitive plain text query which searches everywhere.
+    pub fn new(pattern: String) -> SearchQuery {
+        SearchQuery {
+            pattern: pattern,
+            regex: false,
+            whole_word: false,
+            case_sensitive: false,
+            in_references: true,
+            in_values: true,
+            in_fields: true,
+            in_labels: true,
+            in_notes: true,
+        }
+    }
+
+    /// Builds the regular expression which matches the query.
+    pub fn compile(&self) -> Result<Regex, regex::Error> {
+        let pattern = if self.regex { self.pattern.clone() } else { regex::escape(&self.pattern) };
+        let pattern = if self.whole_word { format!(r"\b(?:{})\b", pattern) } else { pattern };
+        RegexBuilder::new(&pattern)
+            .case_insensitive(!self.case_sensitive)
+            .build()
+    }
+
+    /// Returns true if the field with the number `n` is to be searched.
+    fn searches_field(&self, n: isize) -> bool {
+        match n {
+            REFERENCE_FIELD => self.in_references,
+            VALUE_FIELD => self.in_values,
+            _ => self.in_fields,
+        }
+    }
+}
+
+/// The place a match was found at.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SearchMatch {
+    /// A field of the component instance with the `Uuid`, identified by its number.
+    Field(Uuid, isize),
+    Label(Uuid),
+    Note(Uuid),
+}
+
+impl SearchMatch {
+    /// Returns the `Uuid` of the component instance, the label or the note which matched.
+    pub fn get_uuid(&self) -> Uuid {
+        match self {
+            SearchMatch::Field(uuid, _) | SearchMatch::Label(uuid) | SearchMatch::Note(uuid) => *uuid,
+        }
+    }
+}
+
+/// Returns all the places in the schema which match the `query`, the component fields in field order before the labels and the notes.
+/// Only the sheet held by the schema is searched. Child sheets are not loaded into it, so they can not be searched yet.
+pub fn find(schema: &Schema, query: &SearchQuery) -> Result<Vec<SearchMatch>, regex::Error> {
+    let regex = query.compile()?;
+    let mut matches = Vec::new();
+    for instance in schema.get_component_instances() {
+        for field in instance.fields.iter().filter(|f| query.searches_field(f.n)) {
+            // The reference of an instance is the authoritative version of its reference field.
+            let text = if field.n == REFERENCE_FIELD { &instance.reference } else { &field.text };
+            if regex.is_match(text) {
+                matches.push(SearchMatch::Field(instance.uuid, field.n));
+            }
+        }
+    }
+    if query.in_labels {
+        for label in schema.get_labels() {
+            if regex.is_match(&label.text) {
+                matches.push(SearchMatch::Label(label.uuid));
+            }
+        }
+    }
+    if query.in_notes {
+        for note in schema.get_notes() {
+            if regex.is_match(&note.text) {
+                matches.push(SearchMatch::Note(note.uuid));
+            }
+        }
+    }
+    Ok(matches)
+}
+
+/// Replaces every match of the `query` with `replacement`, which can refer to the groups of a regular expression like `$1`.
+/// The replacements are recorded in the pending transaction of the schema, such that they can be undone at once after a commit.
+/// Returns the number of texts which were changed.
+pub fn replace_all(schema: &mut Schema, query: &SearchQuery, replacement: &str) -> Result<usize, regex::Error> {
+    let regex = query.compile()?;
+    let mut count = 0;
+    for found in find(schema, query)? {
+        match found {
+            SearchMatch::Field(uuid, n) => {
+                let mut instance = schema.get_component_instance(&uuid).clone();
+                let text = if n == REFERENCE_FIELD { instance.reference.clone() } else { instance.get_field_text(n).to_owned() };
+                let replaced = regex.replace_all(&text, replacement).into_owned();
+                if replaced != text {
+                    instance.set_field_text(n, &replaced);
+                    schema.update_component(instance);
+                    count += 1;
+                }
+            },
+            SearchMatch::Label(uuid) => {
+                let mut label = schema.get_label(uuid).clone();
+                let replaced = regex.replace_all(&label.text, replacement).into_owned();
+                if replaced != label.text {
+                    label.text = replaced;
+                    schema.update_label(label);
+                    count += 1;
+                }
+            },
+            SearchMatch::Note(uuid) => {
+                let mut note = match schema.find_note(&uuid) {
+                    Some(note) => note.clone(),
+                    None => continue,
+                };
+                let replaced = regex.replace_all(&note.text, replacement).into_owned();
+                if replaced != note.text {
+                    note.text = replaced;
+                    schema.update_note(note);
+                    count += 1;
+                }
+            },
+        }
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geometry::*;
+    use parsing::kicad::schema::{Label, LabelKind, Note};
+    use state::schema::ComponentInstance;
+    use state::schema::test_utils::Fixture;
+
+    fn schema_with_parts() -> Schema {
+        let Fixture { mut schema, .. } = Fixture::new();
+        for (reference, value) in &[("U1", "STM32"), ("U12", "LM358"), ("R3", "10k")] {
+            let mut instance = ComponentInstance::new("X".into());
+            instance.set_field_text(REFERENCE_FIELD, reference);
+            instance.set_field_text(VALUE_FIELD, value);
+            schema.add_component(instance);
+        }
+        schema.add_label(Label::new(LabelKind::Local, "U12_OUT".into(), Point2::origin()));
+        schema.commit();
+        schema
+    }
+
+    #[test]
+    fn whole_word_and_regex_queries() {
+        let schema = schema_with_parts();
+
+        let mut query = SearchQuery::new("u12".into());
+        assert_eq!(find(&schema, &query).unwrap().len(), 2);
+
+        query.whole_word = true;
+        let matches = find(&schema, &query).unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(schema.get_component_instance(&matches[0].get_uuid()).reference, "U12");
+
+        let mut query = SearchQuery::new(r"^U\d+$".into());
+        query.regex = true;
+        query.case_sensitive = true;
+        assert_eq!(find(&schema, &query).unwrap().len(), 2);
+
+        query.pattern = "(".into();
+        assert!(find(&schema, &query).is_err());
+    }
+
+    #[test]
+    fn replace_all_is_one_undo_step() {
+        let mut schema = schema_with_parts();
+
+        let mut query = SearchQuery::new(r"U(\d+)".into());
+        query.regex = true;
+        assert_eq!(replace_all(&mut schema, &query, "IC$1").unwrap(), 3);
+        schema.commit();
+        assert_eq!(schema.get_labels()[0].text, "IC12_OUT");
+        assert!(schema.get_component_instances().iter().any(|ci| ci.reference == "IC12"));
+
+        assert!(schema.undo());
+        assert_eq!(schema.get_labels()[0].text, "U12_OUT");
+        assert!(schema.get_component_instances().iter().all(|ci| !ci.reference.starts_with("IC")));
+    }
+
+    #[test]
+    fn notes_are_searched_and_replaced() {
+        let mut schema = schema_with_parts();
+        let note = schema.add_note(Note::new("Keep U12 cool\nSee U1".into(), Point2::new(0.0, 500.0)));
+        schema.commit();
+
+        let mut query = SearchQuery::new("U1".into());
+        query.in_references = false;
+        query.in_labels = false;
+        assert_eq!(find(&schema, &query).unwrap(), vec![SearchMatch::Note(note)]);
+
+        query.whole_word = true;
+        assert_eq!(replace_all(&mut schema, &query, "IC1").unwrap(), 1);
+        schema.commit();
+        assert_eq!(schema.find_note(&note).unwrap().text, "Keep U12 cool\nSee IC1");
+
+        query.in_notes = false;
+        assert!(find(&schema, &query).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,51 @@
+//! Shared setup for the schema and viewer tests.
+
+use uuid::Uuid;
+
+use geometry::*;
+use parsing::kicad::schema::{Label, LabelKind, WireSegment, WireType};
+use state::component_libraries::ComponentLibraries;
+use state::event::EventBus;
+use state::schema::Schema;
+
+/// An empty schema with its event bus and an empty set of libraries.
+pub struct Fixture {
+    pub event_bus: EventBus,
+    pub libraries: ComponentLibraries,
+    pub schema: Schema,
+}
+
+impl Fixture {
+    pub fn new() -> Fixture {
+        let event_bus = EventBus::new();
+        let libraries = ComponentLibraries::new(event_bus.get_handle());
+        let schema = Schema::new(event_bus.get_handle());
+        Fixture { event_bus: event_bus, libraries: libraries, schema: schema }
+    }
+}
+
+/// A plain wire from `start` to `end` with a fresh uuid.
+pub fn wire(start: (f32, f32), end: (f32, f32)) -> WireSegment {
+    segment(WireType::Wire, start, end)
+}
+
+/// A bus from `start` to `end` with a fresh uuid.
+pub fn bus(start: (f32, f32), end: (f32, f32)) -> WireSegment {
+    segment(WireType::Bus, start, end)
+}
+
+/// A local label with a fresh uuid.
+pub fn label(text: &str, position: (f32, f32)) -> Label {
+    let mut label = Label::new(LabelKind::Local, text.into(), Point2::new(position.0, position.1));
+    label.uuid = Uuid::new_v4();
+    label
+}
+
+fn segment(kind: WireType, start: (f32, f32), end: (f32, f32)) -> WireSegment {
+    WireSegment {
+        uuid: Uuid::new_v4(),
+        kind: kind,
+        start: Point2::new(start.0, start.1),
+        end: Point2::new(end.0, end.1),
+    }
+}
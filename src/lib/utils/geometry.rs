@@ -5,7 +5,8 @@ use geometry::{
     Vector2,
     Point4,
     Vector4,
-    Matrix4
+    Matrix4,
+    AABB,
 };
 
 pub fn vector_to_point_2d(v: &nbase::Vector2<f32>) -> ngeometry::Point2<f32> {
@@ -68,4 +69,184 @@ pub fn transform_vector_2d(point: &Vector2, perspective: &Matrix4) -> Vector2 {
 pub fn transform_vector_4d(point: &Vector4, perspective: &Matrix4) -> Vector4 {
         perspective
         * point
-}
\ No newline at end of file
+}
+
+/// Returns the shortest distance between `point` and the line segment spanned by `start` and `end`.
+pub fn distance_point_segment(point: &Point2, start: &Point2, end: &Point2) -> f32 {
+    (point - closest_point_segment(point, start, end)).norm()
+}
+
+/// Returns the point on the line segment from `start` to `end` which is closest to `point`.
+pub fn closest_point_segment(point: &Point2, start: &Point2, end: &Point2) -> Point2 {
+    let segment = end - start;
+    let length_squared = segment.norm_squared();
+    if length_squared == 0.0 {
+        return *start;
+    }
+    let t = ((point - start).dot(&segment) / length_squared).max(0.0).min(1.0);
+    start + segment * t
+}
+
+/// Returns the `AABB` of the line segment spanned by `start` and `end`, grown by `margin` in each direction.
+/// The order of `start` and `end` does not matter.
+pub fn segment_aabb(start: &Point2, end: &Point2, margin: f32) -> AABB {
+    let margin = Vector2::new(margin, margin);
+    AABB::new(
+        Point2::new(start.x.min(end.x), start.y.min(end.y)) - margin,
+        Point2::new(start.x.max(end.x), start.y.max(end.y)) + margin
+    )
+}
+
+/// Returns the `AABB` which contains the `aabb` after it was transformed by `transform`.
+/// For rotations by multiples of 90 degrees and for mirroring the result is as tight as the original box.
+pub fn transform_aabb(aabb: &AABB, transform: &Matrix4) -> AABB {
+    let corners = [
+        transform_point_2d(aabb.mins(), transform),
+        transform_point_2d(&Point2::new(aabb.mins().x, aabb.maxs().y), transform),
+        transform_point_2d(&Point2::new(aabb.maxs().x, aabb.mins().y), transform),
+        transform_point_2d(aabb.maxs(), transform),
+    ];
+    let mut mins = corners[0];
+    let mut maxs = corners[0];
+    for corner in &corners[1..] {
+        mins = Point2::new(mins.x.min(corner.x), mins.y.min(corner.y));
+        maxs = Point2::new(maxs.x.max(corner.x), maxs.y.max(corner.y));
+    }
+    AABB::new(mins, maxs)
+}
+
+/// Returns the `AABB` of the arc around `center` which runs counter-clockwise from `start_angle` to `end_angle` in radians.
+/// Like in KiCad, the arc takes the shorter way around the circle.
+/// The box covers the whole sweep, including the points where the arc is furthest left, right, up or down.
+pub fn arc_aabb(center: &Point2, radius: f32, start_angle: f32, end_angle: f32) -> AABB {
+    use std::f32::consts::PI;
+    let (start, sweep) = arc_span(start_angle, end_angle);
+
+    let point_at = |angle: f32| center + Vector2::new(angle.cos(), angle.sin()) * radius;
+    let mut points = vec![point_at(start), point_at(start + sweep)];
+    // The sweep ends before 3π, so checking the quadrant angles up to 3.5π covers every case.
+    for i in 0..8 {
+        let extreme = i as f32 * PI / 2.0;
+        if extreme > start && extreme < start + sweep {
+            points.push(point_at(extreme));
+        }
+    }
+
+    let mut mins = points[0];
+    let mut maxs = points[0];
+    for p in &points[1..] {
+        mins = Point2::new(mins.x.min(p.x), mins.y.min(p.y));
+        maxs = Point2::new(maxs.x.max(p.x), maxs.y.max(p.y));
+    }
+    AABB::new(mins, maxs)
+}
+
+/// Returns the shortest distance between `point` and the arc around `center` from `start_angle` to `end_angle` in radians.
+/// The arc takes the shorter way around the circle, just like in `arc_aabb`.
+pub fn distance_point_arc(point: &Point2, center: &Point2, radius: f32, start_angle: f32, end_angle: f32) -> f32 {
+    use std::f32::consts::PI;
+    let (start, sweep) = arc_span(start_angle, end_angle);
+    let v = point - center;
+    let angle = ((v.y.atan2(v.x) - start) % (2.0 * PI) + 2.0 * PI) % (2.0 * PI);
+    if angle <= sweep {
+        (v.norm() - radius).abs()
+    } else {
+        let point_at = |angle: f32| center + Vector2::new(angle.cos(), angle.sin()) * radius;
+        (point - point_at(start)).norm().min((point - point_at(start + sweep)).norm())
+    }
+}
+
+/// Returns the start angle in `[0, 2π)` and the non-negative sweep of the shorter arc between the two angles.
+fn arc_span(start_angle: f32, end_angle: f32) -> (f32, f32) {
+    use std::f32::consts::PI;
+    let normalize = |angle: f32| ((angle % (2.0 * PI)) + 2.0 * PI) % (2.0 * PI);
+    let sweep = normalize(end_angle - start_angle);
+    if sweep > PI {
+        (normalize(end_angle), 2.0 * PI - sweep)
+    } else {
+        (normalize(start_angle), sweep)
+    }
+}
+
+/// Returns the distance between `point` and the `aabb`, which is zero if the point lies inside.
+pub fn distance_point_aabb(point: &Point2, aabb: &AABB) -> f32 {
+    let dx = (aabb.mins().x - point.x).max(0.0).max(point.x - aabb.maxs().x);
+    let dy = (aabb.mins().y - point.y).max(0.0).max(point.y - aabb.maxs().y);
+    Vector2::new(dx, dy).norm()
+}
+
+/// Returns true if `point` lies inside the closed polygon with the given corners.
+pub fn point_in_polygon(point: &Point2, corners: &[Point2]) -> bool {
+    let mut inside = false;
+    let mut j = corners.len().wrapping_sub(1);
+    for i in 0..corners.len() {
+        let (a, b) = (&corners[i], &corners[j]);
+        if (a.y > point.y) != (b.y > point.y) && point.x < (b.x - a.x) * (point.y - a.y) / (b.y - a.y) + a.x {
+            inside = !inside;
+        }
+        j = i;
+    }
+    inside
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geometry::Vector3;
+
+    #[test]
+    fn transformed_aabb_follows_rotation() {
+        let aabb = AABB::new(Point2::new(0.0, 0.0), Point2::new(20.0, 10.0));
+        let transform = Matrix4::from_axis_angle(&Vector3::z_axis(), ::std::f32::consts::PI / 2.0)
+            .append_translation(&Vector3::new(100.0, 0.0, 0.0));
+
+        let transformed = transform_aabb(&aabb, &transform);
+
+        assert!((transformed.mins() - Point2::new(90.0, 0.0)).norm() < 1e-4);
+        assert!((transformed.maxs() - Point2::new(100.0, 20.0)).norm() < 1e-4);
+    }
+
+    #[test]
+    fn arc_aabb_covers_the_sweep() {
+        use std::f32::consts::PI;
+        let center = Point2::origin();
+        let close = |a: &Point2, b: Point2| (a - b).norm() < 1e-4;
+
+        // A quarter circle only spans its end points.
+        let quarter = arc_aabb(&center, 10.0, 0.0, PI / 2.0);
+        assert!(close(quarter.mins(), Point2::new(0.0, 0.0)));
+        assert!(close(quarter.maxs(), Point2::new(10.0, 10.0)));
+
+        // The top of the circle lies between the end points.
+        let top = arc_aabb(&center, 10.0, PI / 4.0, 3.0 * PI / 4.0);
+        assert!((top.maxs().y - 10.0).abs() < 1e-4);
+        assert!((top.mins().y - 10.0 * (PI / 4.0).sin()).abs() < 1e-4);
+
+        // The arc takes the short way across zero, no matter the order of the angles.
+        let across_zero = arc_aabb(&center, 10.0, PI / 8.0, -PI / 8.0);
+        assert!((across_zero.maxs().x - 10.0).abs() < 1e-4);
+        assert!(across_zero.mins().x > 9.0);
+    }
+
+    #[test]
+    fn distances_to_shapes() {
+        use std::f32::consts::PI;
+        let center = Point2::origin();
+
+        // On the circle within the sweep, and past the end of the sweep.
+        assert!((distance_point_arc(&Point2::new(0.0, 12.0), &center, 10.0, 0.0, PI / 2.0) - 2.0).abs() < 1e-4);
+        assert!((distance_point_arc(&Point2::new(-10.0, 0.0), &center, 10.0, 0.0, PI / 2.0) - 200.0f32.sqrt()).abs() < 1e-4);
+
+        let aabb = AABB::new(Point2::new(0.0, 0.0), Point2::new(10.0, 10.0));
+        assert_eq!(distance_point_aabb(&Point2::new(5.0, 5.0), &aabb), 0.0);
+        assert_eq!(distance_point_aabb(&Point2::new(13.0, 14.0), &aabb), 5.0);
+
+        let (start, end) = (Point2::new(0.0, 0.0), Point2::new(10.0, 0.0));
+        assert_eq!(closest_point_segment(&Point2::new(4.0, 3.0), &start, &end), Point2::new(4.0, 0.0));
+        assert_eq!(distance_point_segment(&Point2::new(13.0, 4.0), &start, &end), 5.0);
+
+        let triangle = [Point2::new(0.0, 0.0), Point2::new(10.0, 0.0), Point2::new(0.0, 10.0)];
+        assert!(point_in_polygon(&Point2::new(2.0, 2.0), &triangle));
+        assert!(!point_in_polygon(&Point2::new(8.0, 8.0), &triangle));
+    }
+}
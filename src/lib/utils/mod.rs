@@ -1,2 +1,3 @@
 pub mod geometry;
-pub mod traits;
\ No newline at end of file
+pub mod traits;
+pub mod text;
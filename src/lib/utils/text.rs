@@ -0,0 +1,149 @@
+use gfx_glyph::{
+    Font,
+    Scale,
+    Point as GlyphPoint,
+};
+use gfx_glyph::rusttype::Segment;
+use lyon::path::default::Path;
+use lyon::path::builder::{
+    PathBuilder,
+    FlatPathBuilder,
+};
+use lyon::math::point;
+
+use geometry::{
+    Point2,
+    Vector2,
+    AABB,
+};
+use parsing::kicad::component_library::{
+    TextOrientation,
+    Justify,
+};
+
+/// The font all the texts of a schema are set in.
+pub const FONT_DATA: &[u8] = include_bytes!("../../../test_data/Inconsolata-Regular.ttf");
+
+thread_local! {
+    static FONT: Font<'static> = Font::from_bytes(FONT_DATA).expect("The bundled font could not be loaded.");
+}
+
+/// Returns the width of `text` set with a height of `size`, kerning included.
+pub fn text_width(text: &str, size: f32) -> f32 {
+    layout_text(text, size).1
+}
+
+/// Sets `text` with a height of `size` on a line starting at zero.
+/// Returns every character with the position of its origin on the line, kerning included, and the width of the whole text.
+pub fn layout_text(text: &str, size: f32) -> (Vec<(char, f32)>, f32) {
+    FONT.with(|font| {
+        let scale = Scale::uniform(size);
+        let mut width = 0.0;
+        let mut previous = None;
+        let characters = text.chars().map(|character| {
+            let glyph = font.glyph(character).scaled(scale);
+            if let Some(previous) = previous {
+                width += font.pair_kerning(scale, previous, glyph.id());
+            }
+            let origin = width;
+            width += glyph.h_metrics().advance_width;
+            previous = Some(glyph.id());
+            (character, origin)
+        }).collect();
+        (characters, width)
+    })
+}
+
+/// Returns how far the font reaches below the baseline at a height of `size`. The value is negative.
+pub fn get_descent(size: f32) -> f32 {
+    FONT.with(|font| font.v_metrics(Scale::uniform(size)).descent)
+}
+
+/// Returns the outline of the glyph of `character` at a height of `size` with its origin on the baseline and y pointing up.
+/// Returns `None` for characters without an outline like spaces.
+pub fn glyph_path(character: char, size: f32) -> Option<Path> {
+    FONT.with(|font| {
+        let contours = font.glyph(character).scaled(Scale::uniform(size)).shape()?;
+        // The font has y pointing down, like the screen.
+        let flip = |p: &GlyphPoint<f32>| point(p.x, -p.y);
+        let mut builder = Path::builder();
+        for contour in contours {
+            let start = match contour.segments.first() {
+                Some(Segment::Line(line)) => line.p[0],
+                Some(Segment::Curve(curve)) => curve.p[0],
+                None => continue,
+            };
+            builder.move_to(flip(&start));
+            for segment in &contour.segments {
+                match segment {
+                    Segment::Line(line) => builder.line_to(flip(&line.p[1])),
+                    Segment::Curve(curve) => builder.quadratic_bezier_to(flip(&curve.p[1]), flip(&curve.p[2])),
+                }
+            }
+            builder.close();
+        }
+        Some(builder.build())
+    })
+}
+
+/// Returns the bounding box of `text` with a height of `size` which is anchored at `position`.
+/// The justification tells which side of the text lies at the anchor, vertical text reads from bottom to top.
+pub fn text_aabb(position: &Point2, text: &str, size: f32, orientation: &TextOrientation, hjustify: &Justify, vjustify: &Justify) -> AABB {
+    let width = text_width(text, size);
+    let (x0, x1) = match hjustify {
+        Justify::Left => (0.0, width),
+        Justify::Right => (-width, 0.0),
+        _ => (-width / 2.0, width / 2.0),
+    };
+    let (y0, y1) = match vjustify {
+        Justify::Top => (-size, 0.0),
+        Justify::Bottom => (0.0, size),
+        _ => (-size / 2.0, size / 2.0),
+    };
+    let (mins, maxs) = match orientation {
+        TextOrientation::Horizontal => (Vector2::new(x0, y0), Vector2::new(x1, y1)),
+        TextOrientation::Vertical => (Vector2::new(-y1, x0), Vector2::new(-y0, x1)),
+    };
+    AABB::new(position + mins, position + maxs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn width_grows_with_the_text() {
+        let one = text_width("R", 50.0);
+        assert!(one > 0.0);
+        // The font is monospaced.
+        assert!((text_width("R12", 50.0) - 3.0 * one).abs() < 1e-3);
+        assert!((text_width("R", 100.0) - 2.0 * one).abs() < 1e-3);
+    }
+
+    #[test]
+    fn layout_matches_the_width() {
+        let (characters, width) = layout_text("R12", 50.0);
+        assert_eq!(characters.iter().map(|(c, _)| *c).collect::<String>(), "R12");
+        assert_eq!(characters[0].1, 0.0);
+        assert!((characters[2].1 - 2.0 * text_width("R", 50.0)).abs() < 1e-3);
+        assert!((width - text_width("R12", 50.0)).abs() < 1e-3);
+        assert!(get_descent(50.0) < 0.0);
+
+        assert!(glyph_path('R', 50.0).is_some());
+        assert!(glyph_path(' ', 50.0).is_none());
+    }
+
+    #[test]
+    fn justification_moves_the_box() {
+        let width = text_width("GND", 50.0);
+        let origin = Point2::origin();
+
+        let left = text_aabb(&origin, "GND", 50.0, &TextOrientation::Horizontal, &Justify::Left, &Justify::Bottom);
+        assert_eq!(*left.mins(), Point2::new(0.0, 0.0));
+        assert_eq!(*left.maxs(), Point2::new(width, 50.0));
+
+        let vertical = text_aabb(&origin, "GND", 50.0, &TextOrientation::Vertical, &Justify::Center, &Justify::Center);
+        assert_eq!(*vertical.mins(), Point2::new(-25.0, -width / 2.0));
+        assert_eq!(*vertical.maxs(), Point2::new(25.0, width / 2.0));
+    }
+}
@@ -0,0 +1,325 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::u32;
+use std::usize;
+
+use geometry::*;
+use utils::geometry::distance_point_segment;
+use state::schema::CONNECTION_TOLERANCE;
+
+/// How many grid cells the search may leave the box spanned by start and end.
+const SEARCH_MARGIN: i32 = 10;
+/// Routing is refused if the search area would get bigger than this to keep the live preview responsive.
+const MAX_NODES: usize = 250_000;
+
+const STEP_COST: u32 = 1;
+const BEND_COST: u32 = 5;
+const CROSSING_COST: u32 = 3;
+/// Passing through a component or running along an existing wire is not forbidden, but avoided at nearly any cost.
+/// This way a path is found even if the pins themselves lie inside a bounding box.
+const BLOCKED_COST: u32 = 100;
+
+const DIRECTIONS: [(i32, i32); 4] = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+/// The direction of the start node, which has not been entered from anywhere.
+const NO_DIRECTION: usize = 4;
+const STATES_PER_NODE: usize = 5;
+
+const HORIZONTAL_WIRE: u8 = 1;
+const VERTICAL_WIRE: u8 = 2;
+
+/// Computes an orthogonal path on the grid from `start` to `end`.
+/// The grid is anchored at `start` and has a cell size of `step`.
+///
+/// The path avoids the interior of the `obstacles` and minimizes its length, its bends and the number of `wires` it crosses.
+/// It never runs along one of the `wires` or bends on one of them unless there is no other way, as that would connect it to the wire.
+/// Returns the corners of the path including `start` and `end`, or `None` if no path could be found.
+pub fn route(start: &Point2, end: &Point2, step: &Vector2, obstacles: &[AABB], wires: &[(Point2, Point2)]) -> Option<Vec<Point2>> {
+    if step.x <= 0.0 || step.y <= 0.0 {
+        return None;
+    }
+    let target = (
+        ((end.x - start.x) / step.x).round() as i32,
+        ((end.y - start.y) / step.y).round() as i32,
+    );
+    let grid = Grid::new(start, step, target, obstacles, wires)?;
+    let start_node = grid.index(0, 0)?;
+    let target_node = grid.index(target.0, target.1)?;
+
+    let heuristic = |node: usize| {
+        let (i, j) = grid.coordinates(node);
+        ((i - target.0).abs() + (j - target.1).abs()) as u32 * STEP_COST
+    };
+
+    let mut costs = vec![u32::MAX; grid.len() * STATES_PER_NODE];
+    let mut previous = vec![usize::MAX; grid.len() * STATES_PER_NODE];
+    let mut queue = BinaryHeap::new();
+
+    let start_state = start_node * STATES_PER_NODE + NO_DIRECTION;
+    costs[start_state] = 0;
+    queue.push(QueueEntry { priority: heuristic(start_node), state: start_state });
+
+    while let Some(QueueEntry { priority, state }) = queue.pop() {
+        let node = state / STATES_PER_NODE;
+        let direction = state % STATES_PER_NODE;
+        let cost = costs[state];
+
+        if node == target_node {
+            let nodes = reconstruct_path(&previous, state);
+            return Some(grid.to_corners(&nodes, end));
+        }
+        // Skip entries which were superseded by a cheaper path to the same state.
+        if priority > cost + heuristic(node) {
+            continue;
+        }
+
+        let (i, j) = grid.coordinates(node);
+        for (d, &(di, dj)) in DIRECTIONS.iter().enumerate() {
+            // Turning back never makes sense.
+            if direction != NO_DIRECTION && d == (direction + 2) % 4 {
+                continue;
+            }
+            let next = match grid.index(i + di, j + dj) {
+                Some(next) => next,
+                None => continue,
+            };
+
+            let mut step_cost = STEP_COST;
+            if direction != NO_DIRECTION && d != direction {
+                step_cost += BEND_COST;
+                // A corner on a wire connects to it.
+                if grid.wires[node] != 0 {
+                    step_cost += BLOCKED_COST;
+                }
+            }
+            if next != target_node {
+                if grid.blocked[next] {
+                    step_cost += BLOCKED_COST;
+                }
+                let along = if d % 2 == 0 { HORIZONTAL_WIRE } else { VERTICAL_WIRE };
+                if grid.wires[next] & along != 0 {
+                    step_cost += BLOCKED_COST;
+                } else if grid.wires[next] != 0 {
+                    step_cost += CROSSING_COST;
+                }
+            }
+
+            let next_state = next * STATES_PER_NODE + d;
+            let next_cost = cost + step_cost;
+            if next_cost < costs[next_state] {
+                costs[next_state] = next_cost;
+                previous[next_state] = state;
+                queue.push(QueueEntry { priority: next_cost + heuristic(next), state: next_state });
+            }
+        }
+    }
+
+    None
+}
+
+/// Walks back from the final state to the start and returns the visited nodes in order.
+fn reconstruct_path(previous: &[usize], last: usize) -> Vec<usize> {
+    let mut nodes = Vec::new();
+    let mut state = last;
+    while state != usize::MAX {
+        nodes.push(state / STATES_PER_NODE);
+        state = previous[state];
+    }
+    nodes.reverse();
+    nodes
+}
+
+/// An entry of the priority queue of the search.
+/// The ordering is reversed such that the `BinaryHeap` pops the cheapest entry first.
+#[derive(PartialEq, Eq)]
+struct QueueEntry {
+    priority: u32,
+    state: usize,
+}
+
+impl Ord for QueueEntry {
+    fn cmp(&self, other: &QueueEntry) -> Ordering {
+        other.priority.cmp(&self.priority).then_with(|| self.state.cmp(&other.state))
+    }
+}
+
+impl PartialOrd for QueueEntry {
+    fn partial_cmp(&self, other: &QueueEntry) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// The search area of the router.
+/// Every node knows whether it lies inside an obstacle and which kind of wires pass through it.
+struct Grid {
+    origin: Point2,
+    step: Vector2,
+    min: (i32, i32),
+    width: i32,
+    height: i32,
+    blocked: Vec<bool>,
+    wires: Vec<u8>,
+}
+
+impl Grid {
+    fn new(origin: &Point2, step: &Vector2, target: (i32, i32), obstacles: &[AABB], wires: &[(Point2, Point2)]) -> Option<Grid> {
+        let min = (target.0.min(0) - SEARCH_MARGIN, target.1.min(0) - SEARCH_MARGIN);
+        let width = target.0.abs() + 2 * SEARCH_MARGIN + 1;
+        let height = target.1.abs() + 2 * SEARCH_MARGIN + 1;
+        if width as usize * height as usize > MAX_NODES {
+            return None;
+        }
+
+        let mut grid = Grid {
+            origin: origin.clone(),
+            step: step.clone(),
+            min: min,
+            width: width,
+            height: height,
+            blocked: vec![false; width as usize * height as usize],
+            wires: vec![0; width as usize * height as usize],
+        };
+
+        for obstacle in obstacles {
+            for node in grid.nodes_in(obstacle.mins(), obstacle.maxs()) {
+                let p = grid.position(node);
+                if p.x > obstacle.mins().x + CONNECTION_TOLERANCE && p.x < obstacle.maxs().x - CONNECTION_TOLERANCE
+                && p.y > obstacle.mins().y + CONNECTION_TOLERANCE && p.y < obstacle.maxs().y - CONNECTION_TOLERANCE {
+                    grid.blocked[node] = true;
+                }
+            }
+        }
+
+        for (start, end) in wires {
+            let flags = if (start.y - end.y).abs() <= CONNECTION_TOLERANCE {
+                HORIZONTAL_WIRE
+            } else if (start.x - end.x).abs() <= CONNECTION_TOLERANCE {
+                VERTICAL_WIRE
+            } else {
+                HORIZONTAL_WIRE | VERTICAL_WIRE
+            };
+            let mins = Point2::new(start.x.min(end.x), start.y.min(end.y));
+            let maxs = Point2::new(start.x.max(end.x), start.y.max(end.y));
+            for node in grid.nodes_in(&mins, &maxs) {
+                if distance_point_segment(&grid.position(node), start, end) <= CONNECTION_TOLERANCE {
+                    grid.wires[node] |= flags;
+                }
+            }
+        }
+
+        Some(grid)
+    }
+
+    fn len(&self) -> usize {
+        self.width as usize * self.height as usize
+    }
+
+    /// Returns the node at the given grid coordinates or `None` if they lie outside of the search area.
+    fn index(&self, i: i32, j: i32) -> Option<usize> {
+        let (x, y) = (i - self.min.0, j - self.min.1);
+        if x < 0 || y < 0 || x >= self.width || y >= self.height {
+            None
+        } else {
+            Some((y * self.width + x) as usize)
+        }
+    }
+
+    fn coordinates(&self, node: usize) -> (i32, i32) {
+        let node = node as i32;
+        (node % self.width + self.min.0, node / self.width + self.min.1)
+    }
+
+    fn position(&self, node: usize) -> Point2 {
+        let (i, j) = self.coordinates(node);
+        Point2::new(self.origin.x + i as f32 * self.step.x, self.origin.y + j as f32 * self.step.y)
+    }
+
+    /// Returns all nodes of the search area which lie within the given box.
+    fn nodes_in(&self, mins: &Point2, maxs: &Point2) -> Vec<usize> {
+        let i_min = ((mins.x - self.origin.x) / self.step.x).floor() as i32;
+        let i_max = ((maxs.x - self.origin.x) / self.step.x).ceil() as i32;
+        let j_min = ((mins.y - self.origin.y) / self.step.y).floor() as i32;
+        let j_max = ((maxs.y - self.origin.y) / self.step.y).ceil() as i32;
+        let mut nodes = Vec::new();
+        for j in j_min.max(self.min.1)..(j_max + 1).min(self.min.1 + self.height) {
+            for i in i_min.max(self.min.0)..(i_max + 1).min(self.min.0 + self.width) {
+                nodes.extend(self.index(i, j));
+            }
+        }
+        nodes
+    }
+
+    /// Turns a path of nodes into its corners and makes sure it ends exactly at `end`, even if `end` is not on the grid.
+    fn to_corners(&self, nodes: &[usize], end: &Point2) -> Vec<Point2> {
+        let points = nodes.iter().map(|node| self.position(*node)).collect::<Vec<_>>();
+        let mut corners = vec![points[0]];
+        for window in points.windows(3) {
+            let (a, b, c) = (window[0], window[1], window[2]);
+            let straight = ((a.x - b.x).abs() <= CONNECTION_TOLERANCE && (b.x - c.x).abs() <= CONNECTION_TOLERANCE)
+                || ((a.y - b.y).abs() <= CONNECTION_TOLERANCE && (b.y - c.y).abs() <= CONNECTION_TOLERANCE);
+            if !straight {
+                corners.push(b);
+            }
+        }
+        let last = points[points.len() - 1];
+        if points.len() > 1 {
+            corners.push(last);
+        }
+        if (last.x - end.x).abs() > CONNECTION_TOLERANCE && (last.y - end.y).abs() > CONNECTION_TOLERANCE {
+            corners.push(Point2::new(end.x, last.y));
+        }
+        if (last - end).norm() > CONNECTION_TOLERANCE {
+            corners.push(end.clone());
+        }
+        corners
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns true if every segment of the path is horizontal or vertical.
+    fn is_orthogonal(path: &[Point2]) -> bool {
+        path.windows(2).all(|s| s[0].x == s[1].x || s[0].y == s[1].y)
+    }
+
+    #[test]
+    fn route_straight() {
+        let path = route(&Point2::new(0.0, 0.0), &Point2::new(500.0, 0.0), &Vector2::new(50.0, 50.0), &[], &[]).unwrap();
+
+        assert_eq!(path, vec![Point2::new(0.0, 0.0), Point2::new(500.0, 0.0)]);
+    }
+
+    #[test]
+    fn route_with_one_bend() {
+        let path = route(&Point2::new(0.0, 0.0), &Point2::new(300.0, 200.0), &Vector2::new(50.0, 50.0), &[], &[]).unwrap();
+
+        assert_eq!(path.len(), 3);
+        assert!(is_orthogonal(&path));
+    }
+
+    #[test]
+    fn route_around_obstacle() {
+        let obstacle = AABB::new(Point2::new(100.0, -200.0), Point2::new(400.0, 200.0));
+        let path = route(&Point2::new(0.0, 0.0), &Point2::new(500.0, 0.0), &Vector2::new(50.0, 50.0), &[obstacle], &[]).unwrap();
+
+        assert!(is_orthogonal(&path));
+        assert_eq!(path[0], Point2::new(0.0, 0.0));
+        assert_eq!(path[path.len() - 1], Point2::new(500.0, 0.0));
+        // No segment may pass through the obstacle.
+        for s in path.windows(2) {
+            let inside_x = s[0].x.max(s[1].x) > 100.0 && s[0].x.min(s[1].x) < 400.0;
+            let inside_y = s[0].y.max(s[1].y) > -200.0 && s[0].y.min(s[1].y) < 200.0;
+            assert!(!(inside_x && inside_y));
+        }
+    }
+
+    #[test]
+    fn route_avoids_running_along_wires() {
+        let wire = (Point2::new(-100.0, 0.0), Point2::new(600.0, 0.0));
+        let path = route(&Point2::new(0.0, 0.0), &Point2::new(500.0, 0.0), &Vector2::new(50.0, 50.0), &[], &[wire]).unwrap();
+
+        assert!(is_orthogonal(&path));
+        assert!(path.len() > 2);
+    }
+}
@@ -0,0 +1,112 @@
+use std::collections::HashMap;
+
+/// The most members a vector bus may have. Larger ranges are not taken as buses, as they would take forever to expand.
+pub const MAX_BUS_MEMBERS: i64 = 4096;
+
+/// Expands a bus label into the names of its member nets.
+/// Returns `None` if the label does not describe a bus.
+///
+/// Two kinds of bus labels are understood:
+/// - Vector buses like `D[0..7]` expand to `D0` up to `D7`.
+/// - Group buses like `USB{DP DM}` expand to `USB.DP` and `USB.DM`, unnamed groups like `{SCL SDA}` expand to their members as they are.
+///   A member of a group can be a vector bus itself or the name of a bus alias, so `{SPI}` expands to the members of the alias `SPI`.
+pub fn expand_bus_label(text: &str, aliases: &HashMap<String, Vec<String>>) -> Option<Vec<String>> {
+    expand_group(text, aliases).or_else(|| expand_vector(text))
+}
+
+/// Expands a vector bus like `D[0..7]`.
+/// Returns `None` if the range holds more than `MAX_BUS_MEMBERS` indices.
+fn expand_vector(text: &str) -> Option<Vec<String>> {
+    let open = text.find('[')?;
+    if !text.ends_with(']') {
+        return None;
+    }
+    let prefix = &text[..open];
+    let mut bounds = text[open + 1..text.len() - 1].splitn(2, "..");
+    let start: i64 = bounds.next()?.trim().parse().ok()?;
+    let end: i64 = bounds.next()?.trim().parse().ok()?;
+    // The difference of the bounds can overflow for absurd ranges.
+    match end.checked_sub(start).and_then(|d| d.checked_abs()) {
+        Some(span) if span < MAX_BUS_MEMBERS => (),
+        _ => return None,
+    }
+    let indices = if start <= end {
+        (start..=end).collect::<Vec<_>>()
+    } else {
+        (end..=start).rev().collect::<Vec<_>>()
+    };
+    Some(indices.into_iter().map(|i| format!("{}{}", prefix, i)).collect())
+}
+
+/// Expands a group bus like `USB{DP DM}` or `{SPI}`.
+fn expand_group(text: &str, aliases: &HashMap<String, Vec<String>>) -> Option<Vec<String>> {
+    let open = text.find('{')?;
+    if !text.ends_with('}') {
+        return None;
+    }
+    let name = &text[..open];
+    let mut members = Vec::new();
+    for member in text[open + 1..text.len() - 1].split(|c: char| c.is_whitespace() || c == ',').filter(|m| !m.is_empty()) {
+        if let Some(alias_members) = aliases.get(member) {
+            for alias_member in alias_members {
+                members.extend(expand_vector(alias_member).unwrap_or_else(|| vec![alias_member.clone()]));
+            }
+        } else {
+            members.extend(expand_vector(member).unwrap_or_else(|| vec![member.to_owned()]));
+        }
+    }
+    if name.is_empty() {
+        Some(members)
+    } else {
+        Some(members.into_iter().map(|m| format!("{}.{}", name, m)).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expand_vector_bus() {
+        let members = expand_bus_label("D[0..3]", &HashMap::new()).unwrap();
+
+        assert_eq!(members, vec!["D0", "D1", "D2", "D3"]);
+    }
+
+    #[test]
+    fn expand_descending_vector_bus() {
+        let members = expand_bus_label("A[2..0]", &HashMap::new()).unwrap();
+
+        assert_eq!(members, vec!["A2", "A1", "A0"]);
+    }
+
+    #[test]
+    fn expand_named_group_bus() {
+        let members = expand_bus_label("USB{DP DM}", &HashMap::new()).unwrap();
+
+        assert_eq!(members, vec!["USB.DP", "USB.DM"]);
+    }
+
+    #[test]
+    fn expand_aliased_group_bus() {
+        let mut aliases = HashMap::new();
+        aliases.insert("SPI".to_owned(), vec!["MOSI".to_owned(), "MISO".to_owned(), "SCK".to_owned()]);
+
+        let members = expand_bus_label("{SPI CS[0..1]}", &aliases).unwrap();
+
+        assert_eq!(members, vec!["MOSI", "MISO", "SCK", "CS0", "CS1"]);
+    }
+
+    #[test]
+    fn huge_vector_bus_is_no_bus() {
+        assert_eq!(expand_bus_label("D[1..4096]", &HashMap::new()).map(|m| m.len()), Some(4096));
+        assert!(expand_bus_label("D[0..4096]", &HashMap::new()).is_none());
+        assert!(expand_bus_label("D[0..2000000000]", &HashMap::new()).is_none());
+        assert!(expand_bus_label("D[-9223372036854775808..9223372036854775807]", &HashMap::new()).is_none());
+    }
+
+    #[test]
+    fn plain_label_is_no_bus() {
+        assert!(expand_bus_label("GND", &HashMap::new()).is_none());
+    }
+}
@@ -0,0 +1,199 @@
+use geometry::*;
+
+/// Schema coordinates are measured in mils, thousandths of an inch.
+const MILS_PER_INCH: f32 = 1000.0;
+const MILS_PER_MILLIMETER: f32 = MILS_PER_INCH / 25.4;
+/// The smallest distance in pixels between two drawn grid lines. A denser grid is thinned out when drawn.
+pub const MIN_GRID_LINE_SPACING: f32 = 8.0;
+
+/// A unit grid sizes and coordinates can be displayed in.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GridUnit {
+    Mil,
+    Inch,
+    Millimeter,
+}
+
+/// All the units in the order they are offered in.
+pub const GRID_UNITS: [GridUnit; 3] = [GridUnit::Mil, GridUnit::Inch, GridUnit::Millimeter];
+
+impl GridUnit {
+    /// Converts a `value` in this unit into schema units.
+    pub fn to_schema(&self, value: f32) -> f32 {
+        match self {
+            GridUnit::Mil => value,
+            GridUnit::Inch => value * MILS_PER_INCH,
+            GridUnit::Millimeter => value * MILS_PER_MILLIMETER,
+        }
+    }
+
+    /// Converts a `value` in schema units into this unit.
+    pub fn from_schema(&self, value: f32) -> f32 {
+        match self {
+            GridUnit::Mil => value,
+            GridUnit::Inch => value / MILS_PER_INCH,
+            GridUnit::Millimeter => value / MILS_PER_MILLIMETER,
+        }
+    }
+
+    pub fn get_symbol(&self) -> &'static str {
+        match self {
+            GridUnit::Mil => "mil",
+            GridUnit::Inch => "in",
+            GridUnit::Millimeter => "mm",
+        }
+    }
+
+    /// Returns the unit with the given symbol.
+    pub fn from_symbol(symbol: &str) -> Option<GridUnit> {
+        GRID_UNITS.iter().find(|unit| unit.get_symbol() == symbol).cloned()
+    }
+
+    /// Formats a `value` in schema units in this unit, with as many decimals as the unit needs.
+    pub fn format(&self, value: f32) -> String {
+        let value = self.from_schema(value);
+        match self {
+            GridUnit::Mil => format!("{:.0}", value),
+            GridUnit::Inch => format!("{:.3}", value),
+            GridUnit::Millimeter => format!("{:.2}", value),
+        }
+    }
+}
+
+/// A commonly used grid size.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GridPreset {
+    pub size: f32,
+    pub unit: GridUnit,
+}
+
+pub const GRID_PRESETS: [GridPreset; 5] = [
+    GridPreset { size: 50.0, unit: GridUnit::Mil },
+    GridPreset { size: 25.0, unit: GridUnit::Mil },
+    GridPreset { size: 10.0, unit: GridUnit::Mil },
+    GridPreset { size: 1.27, unit: GridUnit::Millimeter },
+    GridPreset { size: 2.54, unit: GridUnit::Millimeter },
+];
+
+impl GridPreset {
+    /// Returns the size of the preset in schema units.
+    pub fn get_size(&self) -> f32 {
+        self.unit.to_schema(self.size)
+    }
+
+    /// Returns the name the preset is offered under, like `50 mil`.
+    pub fn get_name(&self) -> String {
+        format!("{} {}", self.size, self.unit.get_symbol())
+    }
+
+    /// Returns the preset with the given name.
+    pub fn from_name(name: &str) -> Option<GridPreset> {
+        GRID_PRESETS.iter().find(|preset| preset.get_name() == name).cloned()
+    }
+}
+
+/// Describes the grid the cursor snaps to and how it is displayed.
+/// All the sizes are in schema units, the `unit` only changes how they are displayed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GridSettings {
+    pub unit: GridUnit,
+    pub size: f32,
+    /// The size of the alternative grid, which can be switched to quickly for fine placement.
+    pub alt_size: f32,
+    pub use_alt: bool,
+    pub visible: bool,
+    pub snap_to_grid: bool,
+    /// Snap the cursor onto nearby pin and wire ends, even if they are off the grid.
+    pub snap_to_pins: bool,
+}
+
+impl GridSettings {
+    /// Creates the settings of the usual 50 mil KiCad grid with a 25 mil alternative grid.
+    pub fn new() -> GridSettings {
+        GridSettings {
+            unit: GridUnit::Mil,
+            size: GRID_PRESETS[0].get_size(),
+            alt_size: GRID_PRESETS[1].get_size(),
+            use_alt: false,
+            visible: true,
+            snap_to_grid: true,
+            snap_to_pins: true,
+        }
+    }
+
+    /// Returns the size of the grid which is currently in use.
+    pub fn get_active_size(&self) -> f32 {
+        if self.use_alt { self.alt_size } else { self.size }
+    }
+
+    /// Sets the size of the grid which is currently in use. Sizes which are not positive or not finite are ignored.
+    pub fn set_active_size(&mut self, size: f32) {
+        if size.is_finite() && size > 0.0 {
+            if self.use_alt { self.alt_size = size } else { self.size = size }
+        }
+    }
+
+    /// Switches between the grid and the alternative grid.
+    pub fn toggle_alt(&mut self) {
+        self.use_alt = !self.use_alt;
+    }
+
+    /// Returns the grid point nearest to `point` or `point` itself if snapping to the grid is off.
+    pub fn snap(&self, point: &Point2) -> Point2 {
+        if !self.snap_to_grid {
+            return *point;
+        }
+        let size = self.get_active_size();
+        Point2::new((point.x / size).round() * size, (point.y / size).round() * size)
+    }
+
+    /// Returns the spacing of the grid lines to draw at a zoom of `pixels_per_unit`.
+    /// The spacing is a power of two multiple of the active size, such that the lines are never denser than `MIN_GRID_LINE_SPACING`.
+    pub fn get_line_spacing(&self, pixels_per_unit: f32) -> f32 {
+        let mut spacing = self.get_active_size();
+        // A degenerate zoom would never get dense enough.
+        if pixels_per_unit > 0.0 {
+            while spacing * pixels_per_unit < MIN_GRID_LINE_SPACING {
+                spacing *= 2.0;
+            }
+        }
+        spacing
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn units_and_presets() {
+        assert!((GridUnit::Inch.to_schema(0.05) - 50.0).abs() < 1e-3);
+        assert!((GridUnit::Millimeter.to_schema(2.54) - 100.0).abs() < 1e-3);
+        assert_eq!(GridUnit::Millimeter.format(50.0), "1.27");
+        assert_eq!(GridUnit::from_symbol("in"), Some(GridUnit::Inch));
+        assert_eq!(GridPreset::from_name("1.27 mm"), Some(GRID_PRESETS[3]));
+    }
+
+    #[test]
+    fn alt_grid_and_line_spacing() {
+        let mut grid = GridSettings::new();
+        assert_eq!(grid.snap(&Point2::new(74.0, -26.0)), Point2::new(50.0, -50.0));
+        grid.toggle_alt();
+        assert_eq!(grid.snap(&Point2::new(74.0, -26.0)), Point2::new(75.0, -25.0));
+
+        // 25 mil at 0.1 pixels per mil are 2.5 pixels, so every fourth line is drawn.
+        assert_eq!(grid.get_line_spacing(0.1), 100.0);
+        assert_eq!(grid.get_line_spacing(1.0), 25.0);
+    }
+
+    #[test]
+    fn invalid_sizes_are_ignored() {
+        let mut grid = GridSettings::new();
+        for size in &[0.0, -50.0, ::std::f32::NAN, ::std::f32::INFINITY] {
+            grid.set_active_size(*size);
+            assert_eq!(grid.get_active_size(), 50.0);
+        }
+        grid.set_active_size(100.0);
+        assert_eq!(grid.get_active_size(), 100.0);
+    }
+}
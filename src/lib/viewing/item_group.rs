@@ -8,6 +8,8 @@ use std::iter::{
 use state::component_libraries::ComponentLibraries;
 use state::schema::Schema;
 use geometry::*;
+use utils::geometry::segment_aabb;
+use drawing::drawables::loaders::pin::PIN_RADIUS;
 
 /// An `ItemGroup` holds a set of items that are selected on the schema. This can be Components or Wires.
 /// Each item is represented through an `Uuid` and can only be contained once in each group. It can be contained by multiple groups tho.
@@ -47,6 +49,13 @@ impl ItemGroup {
         self.items.remove(item);
     }
 
+    /// Removes an item from the group if it is contained, adds it otherwise.
+    pub fn toggle(&mut self, item: Uuid) {
+        if !self.items.remove(&item) {
+            self.items.insert(item);
+        }
+    }
+
     /// Removes an item from the group. If the item is not contained, nothing happens.
     pub fn clear(&mut self) {
         self.items.clear();
@@ -62,24 +71,36 @@ impl ItemGroup {
         self.items = items;
     }
 
-    /// Takes a list of item `Uuid`s and returns their outer `AABB`.
-    pub fn get_grouped_component_aabb(&self, libraries: &ComponentLibraries, schema: &Schema) -> Option<AABB> {
-        let mut aabb = None;
-        for uuid in &self.items {
-            let instance = schema.get_component_instance(&uuid);
-            let component = libraries.get_component_by_name(&instance.name);
-            if let Some(c) = component {
-                let bb = instance.get_boundingbox(c).clone();
-                use ncollide2d::bounding_volume::BoundingVolume;
-                if aabb.is_none() {
-                    aabb = Some(bb);
-                } else {
-                    // unwrap() here is safe as we checked for is_none()
-                    aabb.as_mut().unwrap().merge(&bb);
-                }
+    /// Takes a list of item `Uuid`s and returns the outer `AABB` of the components, wires, labels and junctions among them.
+    pub fn get_grouped_aabb(&self, libraries: &ComponentLibraries, schema: &Schema) -> Option<AABB> {
+        let mut boxes = Vec::new();
+        for instance in self.items.iter().filter_map(|uuid| schema.find_component_instance(uuid)) {
+            if let Some(c) = libraries.get_component_by_name(&instance.name) {
+                boxes.push(instance.get_boundingbox(c));
             }
         }
-        aabb
+        let margin = PIN_RADIUS / 2.0;
+        boxes.extend(schema.get_wires().iter()
+            .filter(|w| self.items.contains(&w.uuid))
+            .map(|w| segment_aabb(&w.start, &w.end, margin)));
+        boxes.extend(schema.get_labels().iter()
+            .filter(|l| self.items.contains(&l.uuid))
+            .map(|l| segment_aabb(&l.position, &l.position, margin)));
+        boxes.extend(schema.get_junctions().iter()
+            .filter(|j| self.items.contains(&j.uuid))
+            .map(|j| segment_aabb(&j.position, &j.position, margin)));
+        boxes.extend(schema.get_notes().iter()
+            .filter(|n| self.items.contains(&n.uuid))
+            .map(|n| n.get_boundingbox()));
+        boxes.extend(schema.get_note_lines().iter()
+            .filter(|l| self.items.contains(&l.uuid))
+            .map(|l| segment_aabb(&l.start, &l.end, margin)));
+
+        use ncollide2d::bounding_volume::BoundingVolume;
+        boxes.into_iter().fold(None, |aabb: Option<AABB>, bb| Some(match aabb {
+            Some(aabb) => aabb.merged(&bb),
+            None => bb,
+        }))
     }
 
     pub fn iter(&self) -> Iter {
@@ -1,4 +1,9 @@
 pub mod component_viewer;
 pub mod schema_viewer;
 pub mod view_state;
-pub mod item_group;
\ No newline at end of file
+pub mod grid;
+pub mod item_group;
+pub mod net;
+pub mod bus;
+
+pub mod autorouter;
@@ -0,0 +1,41 @@
+use geometry::Point2;
+use super::schema_viewer::ElectricalConductor;
+
+/// A `Net` holds all the electrical conductors that are connected to each other.
+/// Next to the conductors themselves it carries their geometry in schema space,
+/// such that a net can be highlighted without having to look up every single conductor again.
+#[derive(Clone, Debug)]
+pub struct Net {
+    /// The name of the net. It is taken from a label if there is one, otherwise it is derived from one of its pins.
+    pub name: Option<String>,
+    pub conductors: Vec<ElectricalConductor>,
+    /// All the wire segments of the net.
+    pub segments: Vec<(Point2, Point2)>,
+    /// All the pin ends and label anchors of the net.
+    pub points: Vec<Point2>,
+    /// If the net is a bus, this holds the names of the member nets its labels expand to.
+    pub members: Vec<String>,
+}
+
+impl Net {
+    /// Returns true if the conductor is part of the net.
+    pub fn contains(&self, conductor: &ElectricalConductor) -> bool {
+        self.conductors.contains(conductor)
+    }
+
+    /// Returns true if the net is a bus.
+    pub fn is_bus(&self) -> bool {
+        !self.members.is_empty()
+    }
+
+    /// Returns the name of the net as it should be shown to the user.
+    /// Buses also list their members.
+    pub fn get_display_name(&self) -> String {
+        let name = self.name.clone().unwrap_or_else(|| "<unnamed>".to_owned());
+        if self.is_bus() {
+            format!("{} ({})", name, self.members.join(", "))
+        } else {
+            name
+        }
+    }
+}
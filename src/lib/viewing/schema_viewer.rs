@@ -2,7 +2,11 @@ use std::sync::{
     Arc,
     RwLock,
 };
-use std::collections::HashMap;
+use std::collections::{
+    HashMap,
+    HashSet,
+    VecDeque,
+};
 
 use ncollide2d::partitioning::{
     DBVT,
@@ -10,7 +14,10 @@ use ncollide2d::partitioning::{
     DBVTLeafId,
 };
 use ncollide2d::query::PointInterferencesCollector;
-use ncollide2d::bounding_volume::BoundingVolumeInterferencesCollector;
+use ncollide2d::bounding_volume::{
+    BoundingVolume,
+    BoundingVolumeInterferencesCollector,
+};
 
 use uuid::Uuid;
 
@@ -18,14 +25,135 @@ use state::schema::*;
 use state::component_libraries::*;
 use state::event::{Listener, EventMessage};
 use geometry::*;
-use utils::geometry::point_to_vector_2d;
-use parsing::kicad::component_library::GraphicElement;
+use utils::geometry::{
+    distance_point_segment,
+    closest_point_segment,
+    distance_point_aabb,
+    segment_aabb,
+};
+use parsing::kicad::schema::{
+    WireSegment,
+    WireType,
+    Label,
+    Junction,
+    BusEntry,
+    BusEntryType,
+    Note,
+    NoteLine,
+};
 use drawing::drawables::loaders::pin::PIN_RADIUS;
+use super::net::Net;
+use super::bus::expand_bus_label;
+use super::autorouter::route;
+
+/// The maximum distance in pixels between the cursor and an item for the item to be picked.
+pub const PICK_TOLERANCE: f32 = 4.0;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum ElectricalConductor {
     Wire(Uuid),
+    /// A pin is identified by the `Uuid` of its component instance and the index of its graphic element.
     Pin(Uuid, usize),
+    Label(Uuid),
+    Junction(Uuid),
+    BusEntry(Uuid),
+}
+
+/// A point the cursor snaps onto while drawing wires.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SnapPoint {
+    /// The end of a pin or a wire.
+    End(Point2),
+    /// A point on a wire away from its ends, where the new wire forms a junction.
+    Segment(Point2),
+}
+
+impl SnapPoint {
+    pub fn get_position(&self) -> Point2 {
+        match self {
+            SnapPoint::End(position) | SnapPoint::Segment(position) => *position,
+        }
+    }
+}
+
+/// An item which can be picked with the cursor.
+/// If several items are near the cursor, the one which comes first in this list wins.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PickedItem {
+    /// A pin is identified by the `Uuid` of its component instance and the index of its graphic element.
+    Pin(Uuid, usize),
+    Junction(Uuid),
+    Wire(Uuid),
+    Label(Uuid),
+    BusEntry(Uuid),
+    /// A field is identified by the `Uuid` of its component instance and the number of the field.
+    Field(Uuid, isize),
+    Note(Uuid),
+    NoteLine(Uuid),
+    Body(Uuid),
+}
+
+impl PickedItem {
+    /// Returns the `Uuid` of the picked item or of the component instance the picked pin or field belongs to.
+    pub fn get_uuid(&self) -> Uuid {
+        match self {
+            PickedItem::Pin(uuid, _) | PickedItem::Field(uuid, _) => *uuid,
+            PickedItem::Junction(uuid) | PickedItem::Wire(uuid) | PickedItem::Label(uuid) | PickedItem::Body(uuid) => *uuid,
+            PickedItem::BusEntry(uuid) => *uuid,
+            PickedItem::Note(uuid) | PickedItem::NoteLine(uuid) => *uuid,
+        }
+    }
+
+    /// Returns true if the picked item is part of a component instance.
+    pub fn is_component_part(&self) -> bool {
+        match self {
+            PickedItem::Pin(..) | PickedItem::Field(..) | PickedItem::Body(_) => true,
+            _ => false,
+        }
+    }
+
+    fn priority(&self) -> usize {
+        match self {
+            PickedItem::Pin(..) => 0,
+            PickedItem::Junction(_) | PickedItem::Wire(_) | PickedItem::Label(_) | PickedItem::BusEntry(_) => 1,
+            PickedItem::Field(..) | PickedItem::Note(_) | PickedItem::NoteLine(_) => 2,
+            PickedItem::Body(_) => 3,
+        }
+    }
+}
+
+/// Decides which items a selection rectangle selects.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SelectionMode {
+    /// Only the items which lie entirely inside the rectangle are selected.
+    Window,
+    /// All the items which touch the rectangle are selected.
+    Crossing,
+}
+
+impl SelectionMode {
+    /// Dragging the rectangle from left to right selects a window, dragging it from right to left selects crossed items.
+    pub fn from_drag(start: &Point2, end: &Point2) -> SelectionMode {
+        if end.x >= start.x {
+            SelectionMode::Window
+        } else {
+            SelectionMode::Crossing
+        }
+    }
+}
+
+/// The places where a pin or a wire ends without connecting to anything, see `get_unconnected_ends`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct UnconnectedEnds {
+    pub pins: Vec<Point2>,
+    pub wire_ends: Vec<Point2>,
+}
+
+/// The end of a pin in schema space.
+/// It is cached such that nets can be traced without acquiring the libraries.
+struct PinEnd {
+    position: Point2,
+    number: usize,
 }
 
 pub struct SchemaViewer {
@@ -35,10 +163,33 @@ pub struct SchemaViewer {
 
     collision_world: RwLock<DBVT<f32, Uuid, AABB>>,
     wire_net: RwLock<DBVT<f32, ElectricalConductor, AABB>>,
+    /// Holds the notes and note lines, which can be picked but connect nothing.
+    graphic_world: RwLock<DBVT<f32, Uuid, AABB>>,
     leaf_map: HashMap<Uuid, DBVTLeafId>,
     wire_leaf_map: HashMap<Uuid, DBVTLeafId>,
     pin_leaf_map: HashMap<Uuid, HashMap<usize, DBVTLeafId>>,
+    label_leaf_map: HashMap<Uuid, DBVTLeafId>,
+    junction_leaf_map: HashMap<Uuid, DBVTLeafId>,
+    bus_entry_leaf_map: HashMap<Uuid, DBVTLeafId>,
+    graphic_leaf_map: HashMap<Uuid, DBVTLeafId>,
     selected_component: Option<Uuid>,
+
+    // Geometry of the conductors in the wire net.
+    wires: HashMap<Uuid, WireSegment>,
+    labels: HashMap<Uuid, Label>,
+    junctions: HashMap<Uuid, Junction>,
+    bus_entries: HashMap<Uuid, BusEntry>,
+    notes: HashMap<Uuid, Note>,
+    note_lines: HashMap<Uuid, NoteLine>,
+    instances: HashMap<Uuid, ComponentInstance>,
+    pin_ends: HashMap<Uuid, HashMap<usize, PinEnd>>,
+    references: HashMap<Uuid, String>,
+    bus_aliases: HashMap<String, Vec<String>>,
+    /// Set whenever a conductor was added, moved or removed, such that the unconnected ends are only searched again when needed.
+    connectivity_changed: bool,
+    /// The conductor underneath the cursor and the display name of its net, such that the net is only traced again once another conductor is hovered.
+    /// It is dropped whenever the conductors or the bus aliases change.
+    hovered_net: Option<(ElectricalConductor, String)>,
 }
 
 impl SchemaViewer {
@@ -49,20 +200,117 @@ impl SchemaViewer {
             libraries: libraries,
             collision_world: RwLock::new(DBVT::new()),
             wire_net: RwLock::new(DBVT::new()),
+            graphic_world: RwLock::new(DBVT::new()),
             leaf_map: HashMap::new(),
             wire_leaf_map: HashMap::new(),
             pin_leaf_map: HashMap::new(),
+            label_leaf_map: HashMap::new(),
+            junction_leaf_map: HashMap::new(),
+            bus_entry_leaf_map: HashMap::new(),
+            graphic_leaf_map: HashMap::new(),
             selected_component: None,
+            wires: HashMap::new(),
+            labels: HashMap::new(),
+            junctions: HashMap::new(),
+            bus_entries: HashMap::new(),
+            notes: HashMap::new(),
+            note_lines: HashMap::new(),
+            instances: HashMap::new(),
+            pin_ends: HashMap::new(),
+            references: HashMap::new(),
+            bus_aliases: HashMap::new(),
+            connectivity_changed: true,
+            hovered_net: None,
         }
     }
 
-    pub fn get_currently_hovered_component_uuid(&self, cursor: Point2) -> Option<Uuid> {
-        let mut result = Vec::new();
+    /// Returns the component instance whose pins, fields or body are closest to the cursor.
+    /// Returns `None` if a wire, label or junction is picked instead or if nothing lies within `tolerance`.
+    pub fn get_currently_hovered_component_uuid(&self, cursor: Point2, tolerance: f32) -> Option<Uuid> {
+        self.pick(&cursor, tolerance)
+            .filter(|item| item.is_component_part())
+            .map(|item| item.get_uuid())
+    }
+
+    /// Returns the item closest to `cursor` within `tolerance`, measured against the actual shapes instead of their bounding boxes.
+    /// Pins win over conductors, which win over fields, notes and note lines, which win over component bodies.
+    /// Within the same rank the closest item wins.
+    pub fn pick(&self, cursor: &Point2, tolerance: f32) -> Option<PickedItem> {
+        let area = segment_aabb(cursor, cursor, tolerance);
+        let mut candidates: Vec<(PickedItem, f32)> = Vec::new();
+
+        let mut conductors = Vec::new();
+        {
+            let mut visitor = BoundingVolumeInterferencesCollector::new(&area, &mut conductors);
+            self.wire_net.read().unwrap().visit(&mut visitor);
+        }
+        for conductor in conductors {
+            let distance = match &conductor {
+                ElectricalConductor::Wire(uuid) => self.wires.get(uuid).map(|w| distance_point_segment(cursor, &w.start, &w.end)),
+                ElectricalConductor::BusEntry(uuid) => self.bus_entries.get(uuid).map(|e| distance_point_segment(cursor, &e.start, &e.end)),
+                // Pins are measured against their whole line below.
+                ElectricalConductor::Pin(..) => None,
+                _ => self.get_connection_points(&conductor).first().map(|p| (p - cursor).norm()),
+            };
+            let item = match conductor {
+                ElectricalConductor::Wire(uuid) => PickedItem::Wire(uuid),
+                ElectricalConductor::Label(uuid) => PickedItem::Label(uuid),
+                ElectricalConductor::Junction(uuid) => PickedItem::Junction(uuid),
+                ElectricalConductor::BusEntry(uuid) => PickedItem::BusEntry(uuid),
+                ElectricalConductor::Pin(..) => continue,
+            };
+            if let Some(distance) = distance {
+                candidates.push((item, distance));
+            }
+        }
+
+        let mut graphics = Vec::new();
+        {
+            let mut visitor = BoundingVolumeInterferencesCollector::new(&area, &mut graphics);
+            self.graphic_world.read().unwrap().visit(&mut visitor);
+        }
+        for uuid in graphics {
+            if let Some(line) = self.note_lines.get(&uuid) {
+                candidates.push((PickedItem::NoteLine(uuid), distance_point_segment(cursor, &line.start, &line.end)));
+            } else if let Some(note) = self.notes.get(&uuid) {
+                candidates.push((PickedItem::Note(uuid), distance_point_aabb(cursor, &note.get_boundingbox())));
+            }
+        }
+
+        let mut components = Vec::new();
         {
-            let mut visitor = PointInterferencesCollector::new(&cursor, &mut result);
+            let mut visitor = BoundingVolumeInterferencesCollector::new(&area, &mut components);
             self.collision_world.read().unwrap().visit(&mut visitor);
         }
-        result.first().map(|i| *i)
+        let libraries = self.libraries.read().unwrap();
+        for uuid in components {
+            let instance = match self.instances.get(&uuid) {
+                Some(instance) => instance,
+                None => continue,
+            };
+            let component = match libraries.get_component_by_name(&instance.name) {
+                Some(component) => component,
+                None => continue,
+            };
+            // Rotations and mirroring keep distances, so they can be measured in the coordinate system of the component.
+            let local = instance.to_component_space(cursor);
+            for (i, distance) in component.get_pin_distances(&local) {
+                candidates.push((PickedItem::Pin(uuid, i), distance));
+            }
+            for (n, bb) in instance.get_field_boundingboxes() {
+                candidates.push((PickedItem::Field(uuid, n), distance_point_aabb(cursor, &bb)));
+            }
+            if let Some(distance) = component.get_body_distance(&local) {
+                candidates.push((PickedItem::Body(uuid), distance));
+            }
+        }
+
+        candidates.into_iter()
+            .filter(|(_, distance)| *distance <= tolerance)
+            .min_by(|(a, da), (b, db)| {
+                a.priority().cmp(&b.priority()).then(da.partial_cmp(db).unwrap_or(::std::cmp::Ordering::Equal))
+            })
+            .map(|(item, _)| item)
     }
 
     pub fn get_component_uuids_in_rect(&self, aabb: &AABB) -> Vec<Uuid> {
@@ -74,128 +322,712 @@ impl SchemaViewer {
         result
     }
 
+    /// Returns the `Uuid`s of all the wires, labels, junctions and bus entries whose bounding box intersects the `aabb`.
+    pub fn get_conductor_uuids_in_rect(&self, aabb: &AABB) -> Vec<Uuid> {
+        let mut result = Vec::new();
+        {
+            let mut visitor = BoundingVolumeInterferencesCollector::new(aabb, &mut result);
+            self.wire_net.read().unwrap().visit(&mut visitor);
+        }
+        result.into_iter().filter_map(|conductor| match conductor {
+            ElectricalConductor::Wire(uuid) | ElectricalConductor::Label(uuid) | ElectricalConductor::Junction(uuid) => Some(uuid),
+            ElectricalConductor::BusEntry(uuid) => Some(uuid),
+            ElectricalConductor::Pin(..) => None,
+        }).collect()
+    }
+
+    /// Returns the `Uuid`s of all the components, wires, labels, junctions, bus entries, notes and note lines selected by the rectangle `aabb` in the given `mode`.
+    pub fn get_items_in_rect(&self, aabb: &AABB, mode: SelectionMode) -> Vec<Uuid> {
+        let mut result = self.get_component_uuids_in_rect(aabb);
+        result.extend(self.get_conductor_uuids_in_rect(aabb));
+        {
+            let mut visitor = BoundingVolumeInterferencesCollector::new(aabb, &mut result);
+            self.graphic_world.read().unwrap().visit(&mut visitor);
+        }
+        if mode == SelectionMode::Crossing {
+            return result;
+        }
+
+        let collision_world = self.collision_world.read().unwrap();
+        let wire_net = self.wire_net.read().unwrap();
+        let graphic_world = self.graphic_world.read().unwrap();
+        result.into_iter().filter(|uuid| {
+            let bounding_volume = if let Some(leaf_id) = self.leaf_map.get(uuid) {
+                &collision_world[*leaf_id].bounding_volume
+            } else if let Some(leaf_id) = self.graphic_leaf_map.get(uuid) {
+                &graphic_world[*leaf_id].bounding_volume
+            } else if let Some(leaf_id) = self.wire_leaf_map.get(uuid)
+                .or_else(|| self.label_leaf_map.get(uuid))
+                .or_else(|| self.junction_leaf_map.get(uuid))
+                .or_else(|| self.bus_entry_leaf_map.get(uuid)) {
+                &wire_net[*leaf_id].bounding_volume
+            } else {
+                return false;
+            };
+            aabb.contains(bounding_volume)
+        }).collect()
+    }
+
     pub fn update_currently_hovered_component(&mut self) {
         let _schema = self.schema.write().unwrap();
         let mut view_state = self.view_state.write().unwrap();
 
         // In any case, forget the current item.
         view_state.hovered_items.clear();
-        // If we actually hover an item, remember it
+        // If we actually hover an item, remember it. Pins and fields stand for their component.
         if view_state.grabbed_items.is_empty() {
-            if let Some(component_uuid) = self.get_currently_hovered_component_uuid(view_state.get_cursor_in_schema_space()) {
-                view_state.hovered_items.insert(component_uuid);
-                //schema.get_component_instance(&component_uuid).reference.clone()
+            let tolerance = view_state.get_schema_distance_from_screen_distance(&Vector2::new(PICK_TOLERANCE, 0.0)).norm();
+            if let Some(item) = self.pick(&view_state.get_cursor_in_schema_space(), tolerance) {
+                view_state.hovered_items.insert(item.get_uuid());
             }
         }
+
+        // Remember the name of the net underneath the cursor. Tracing it is costly, so it is only done when the cursor enters another conductor.
+        let cursor = view_state.get_cursor_in_schema_space();
+        let conductor = self.get_currently_hovered_conductor_uuid(&cursor).into_iter().next();
+        if let Some(conductor) = conductor {
+            let is_cached = self.hovered_net.as_ref().map_or(false, |(cached, _)| *cached == conductor);
+            if !is_cached {
+                let name = self.get_net(&conductor).get_display_name();
+                self.hovered_net = Some((conductor, name));
+            }
+            view_state.hovered_net = self.hovered_net.as_ref().map(|(_, name)| name.clone());
+        } else {
+            view_state.hovered_net = None;
+        }
+    }
+
+    /// Returns true if the conductor is still part of the schema.
+    pub fn has_conductor(&self, conductor: &ElectricalConductor) -> bool {
+        match conductor {
+            ElectricalConductor::Wire(uuid) => self.wires.contains_key(uuid),
+            ElectricalConductor::Pin(uuid, index) => self.pin_ends.get(uuid).map_or(false, |pins| pins.contains_key(index)),
+            ElectricalConductor::Label(uuid) => self.labels.contains_key(uuid),
+            ElectricalConductor::Junction(uuid) => self.junctions.contains_key(uuid),
+            ElectricalConductor::BusEntry(uuid) => self.bus_entries.contains_key(uuid),
+        }
     }
 
     /// Returns all electrical conductor `Uuid`s which correspond to an element in the schema.
     /// Returns an empty `Vec<T>` if no electrical conductor is found underneath the cursor.
-    /// Can be used for snapping in wire drawing for example.
+    /// Wire drawing snaps with `get_wire_snap_point`, which runs the same query with a larger tolerance.
     pub fn get_currently_hovered_conductor_uuid(&self, cursor: &Point2) -> Vec<ElectricalConductor> {
+        self.get_conductors_near(cursor, PIN_RADIUS / 2.0)
+    }
+
+    /// Returns all electrical conductors which are closer to `point` than `tolerance`.
+    fn get_conductors_near(&self, point: &Point2, tolerance: f32) -> Vec<ElectricalConductor> {
+        let area = segment_aabb(point, point, tolerance);
         let mut result = Vec::new();
         {
-            let mut visitor = PointInterferencesCollector::new(cursor, &mut result);
+            let mut visitor = BoundingVolumeInterferencesCollector::new(&area, &mut result);
             self.wire_net.read().unwrap().visit(&mut visitor);
         }
+        // The AABB of a diagonal wire covers a lot more than the wire itself, so check the actual distance.
+        result.retain(|conductor| self.is_conductor_near(conductor, point, tolerance));
         result
     }
+
+    /// Returns the pin end or wire end which is nearest to `cursor` and closer than `tolerance`.
+    /// Pins are often off the grid, so the cursor is snapped onto them when drawing wires.
+    pub fn get_nearest_conductor_end(&self, cursor: &Point2, tolerance: f32) -> Option<Point2> {
+        // This is the query of `get_currently_hovered_conductor_uuid` with a snapping sized tolerance.
+        self.get_conductors_near(cursor, tolerance).iter()
+            .filter(|conductor| match conductor {
+                ElectricalConductor::Wire(_) | ElectricalConductor::Pin(..) => true,
+                _ => false,
+            })
+            .flat_map(|conductor| self.get_connection_points(conductor))
+            .map(|end| (end, (end - cursor).norm()))
+            .filter(|&(_, distance)| distance <= tolerance)
+            .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
+            .map(|(end, _)| end)
+    }
+
+    /// Returns the ends of the pins and wires which are not connected to any other conductor.
+    pub fn get_unconnected_ends(&self) -> UnconnectedEnds {
+        let mut ends = UnconnectedEnds::default();
+        for (uuid, pins) in &self.pin_ends {
+            for (i, pin) in pins {
+                if !self.is_end_connected(&ElectricalConductor::Pin(*uuid, *i), &pin.position) {
+                    ends.pins.push(pin.position);
+                }
+            }
+        }
+        for wire in self.wires.values() {
+            let conductor = ElectricalConductor::Wire(wire.uuid);
+            for end in &[wire.start, wire.end] {
+                if !self.is_end_connected(&conductor, end) {
+                    ends.wire_ends.push(*end);
+                }
+            }
+        }
+        ends
+    }
+
+    /// Returns true and resets the flag if conductors were added, moved or removed since the last call.
+    pub fn take_connectivity_changed(&mut self) -> bool {
+        let changed = self.connectivity_changed;
+        self.connectivity_changed = false;
+        changed
+    }
+
+    /// Returns true if another conductor which can connect to `conductor` touches it at its `end`.
+    fn is_end_connected(&self, conductor: &ElectricalConductor, end: &Point2) -> bool {
+        self.get_conductors_near(end, CONNECTION_TOLERANCE).iter()
+            .any(|other| other != conductor && self.are_compatible(conductor, other))
+    }
+
+    /// Returns the point a wire drawn at `cursor` snaps onto if there is one closer than `tolerance`.
+    /// Pin and wire ends win over the wires themselves, such that a new wire connects at an existing end whenever it can.
+    pub fn get_wire_snap_point(&self, cursor: &Point2, tolerance: f32) -> Option<SnapPoint> {
+        if let Some(end) = self.get_nearest_conductor_end(cursor, tolerance) {
+            return Some(SnapPoint::End(end));
+        }
+        self.get_conductors_near(cursor, tolerance).iter()
+            .filter_map(|conductor| match conductor {
+                ElectricalConductor::Wire(uuid) => self.wires.get(uuid),
+                _ => None,
+            })
+            .map(|wire| closest_point_segment(cursor, &wire.start, &wire.end))
+            .map(|point| (point, (point - cursor).norm()))
+            .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
+            .map(|(point, _)| SnapPoint::Segment(point))
+    }
+
+    /// Returns every wire which ends on a pin of the given component instance together with the position of that pin.
+    pub fn get_wires_attached_to_pins(&self, instance_uuid: &Uuid) -> Vec<(Uuid, Point2)> {
+        let mut attached = Vec::new();
+        if let Some(pins) = self.pin_ends.get(instance_uuid) {
+            for pin in pins.values() {
+                let mut candidates = Vec::new();
+                {
+                    let mut visitor = PointInterferencesCollector::new(&pin.position, &mut candidates);
+                    self.wire_net.read().unwrap().visit(&mut visitor);
+                }
+                for candidate in candidates {
+                    if let ElectricalConductor::Wire(uuid) = candidate {
+                        let ends_on_pin = self.wires.get(&uuid).map_or(false, |w| {
+                            (w.start - pin.position).norm() <= CONNECTION_TOLERANCE || (w.end - pin.position).norm() <= CONNECTION_TOLERANCE
+                        });
+                        if ends_on_pin {
+                            attached.push((uuid, pin.position));
+                        }
+                    }
+                }
+            }
+        }
+        attached
+    }
+
+    /// Returns every wire which ends at one of the ends of the given wire together with the position of that end.
+    pub fn get_wires_attached_to_wire(&self, wire_uuid: &Uuid) -> Vec<(Uuid, Point2)> {
+        match self.wires.get(wire_uuid) {
+            Some(wire) => [wire.start, wire.end].iter()
+                .flat_map(|end| {
+                    self.get_wires_ending_at(end).into_iter()
+                        .filter(|uuid| uuid != wire_uuid)
+                        .map(|uuid| (uuid, *end))
+                        .collect::<Vec<_>>()
+                })
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Returns every wire which has an end at `point`.
+    pub fn get_wires_ending_at(&self, point: &Point2) -> Vec<Uuid> {
+        let mut candidates = Vec::new();
+        {
+            let mut visitor = PointInterferencesCollector::new(point, &mut candidates);
+            self.wire_net.read().unwrap().visit(&mut visitor);
+        }
+        candidates.into_iter().filter_map(|candidate| match candidate {
+            ElectricalConductor::Wire(uuid) => self.wires.get(&uuid).and_then(|w| {
+                let ends_here = (w.start - point).norm() <= CONNECTION_TOLERANCE || (w.end - point).norm() <= CONNECTION_TOLERANCE;
+                if ends_here { Some(uuid) } else { None }
+            }),
+            _ => None,
+        }).collect()
+    }
+
+    /// Returns the end of the given wire which lies within `tolerance` of `point`, preferring the closer one.
+    pub fn get_wire_end_near(&self, wire_uuid: &Uuid, point: &Point2, tolerance: f32) -> Option<Point2> {
+        self.wires.get(wire_uuid).and_then(|w| {
+            let (start, end) = ((w.start - point).norm(), (w.end - point).norm());
+            if start <= tolerance && start <= end {
+                Some(w.start)
+            } else if end <= tolerance {
+                Some(w.end)
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Computes an orthogonal path on the grid from `start` to `end`.
+    /// Both ends first snap onto the nearest end of a pin or a wire within `tolerance`, so the path connects to them even if they are off the grid.
+    /// It avoids the bodies of the components and runs along or bends on existing wires only if there is no other way.
+    /// Returns the corners of the path or `None` if no path could be found.
+    pub fn route_wire(&self, start: &Point2, end: &Point2, grid: &Vector2, tolerance: f32) -> Option<Vec<Point2>> {
+        let start = self.get_nearest_conductor_end(start, tolerance).unwrap_or(*start);
+        let end = self.get_nearest_conductor_end(end, tolerance).unwrap_or(*end);
+        let obstacles = {
+            let collision_world = self.collision_world.read().unwrap();
+            self.leaf_map.values().map(|leaf_id| collision_world[*leaf_id].bounding_volume.clone()).collect::<Vec<_>>()
+        };
+        let wires = self.wires.values()
+            .map(|w| (w.start, w.end))
+            .collect::<Vec<_>>();
+        route(&start, &end, grid, &obstacles, &wires)
+    }
+
+    /// Traces the net the given conductor belongs to.
+    /// Conductors are connected if the end of one of them touches the other one.
+    /// Labels with the same text are connected too.
+    /// A bus is joined with the nets named after its members and a net is joined with the buses it is a member of.
+    /// The name and the members of the net are those of the conductor's own net though.
+    pub fn get_net(&self, conductor: &ElectricalConductor) -> Net {
+        let own = self.trace(vec![conductor.clone()]);
+
+        // Only buses have members. A label on a plain wire which happens to look like a bus is just a name.
+        let mut members = Vec::new();
+        let is_bus = own.iter().any(|c| self.is_bus(c));
+        if is_bus {
+            for conductor in &own {
+                if let ElectricalConductor::Label(uuid) = conductor {
+                    let expanded = self.labels.get(uuid).and_then(|l| expand_bus_label(&l.text, &self.bus_aliases));
+                    for member in expanded.unwrap_or_else(Vec::new) {
+                        if !members.contains(&member) {
+                            members.push(member);
+                        }
+                    }
+                }
+            }
+        }
+        let name = self.get_net_name(&own.iter().cloned().collect::<Vec<_>>());
+
+        let joined = if is_bus {
+            self.labels.values()
+                .filter(|l| members.contains(&l.text))
+                .map(|l| ElectricalConductor::Label(l.uuid))
+                .collect::<Vec<_>>()
+        } else {
+            let names = own.iter().filter_map(|c| match c {
+                ElectricalConductor::Label(uuid) => self.labels.get(uuid).map(|l| l.text.clone()),
+                _ => None,
+            }).collect::<Vec<_>>();
+            self.labels.values()
+                .filter(|l| self.is_bus_label(l))
+                .filter(|l| {
+                    expand_bus_label(&l.text, &self.bus_aliases)
+                        .map_or(false, |expanded| expanded.iter().any(|member| names.contains(member)))
+                })
+                .map(|l| ElectricalConductor::Label(l.uuid))
+                .collect::<Vec<_>>()
+        };
+        let mut conductors = own;
+        let joined = joined.into_iter().filter(|c| !conductors.contains(c)).collect::<Vec<_>>();
+        if !joined.is_empty() {
+            conductors.extend(self.trace(joined));
+        }
+
+        let conductors = conductors.into_iter().collect::<Vec<_>>();
+        let mut segments = Vec::new();
+        let mut points = Vec::new();
+        for conductor in &conductors {
+            match conductor {
+                ElectricalConductor::Wire(uuid) => {
+                    self.wires.get(uuid).map(|w| segments.push((w.start, w.end)));
+                },
+                ElectricalConductor::BusEntry(uuid) => {
+                    self.bus_entries.get(uuid).map(|e| segments.push((e.start, e.end)));
+                },
+                _ => points.extend(self.get_connection_points(conductor)),
+            }
+        }
+
+        Net {
+            name: name,
+            conductors: conductors,
+            segments: segments,
+            points: points,
+            members: members,
+        }
+    }
+
+    /// Collects all the conductors which are connected to one of the `start` conductors, either directly or by a label with the same text.
+    fn trace(&self, start: Vec<ElectricalConductor>) -> HashSet<ElectricalConductor> {
+        let mut visited = start.iter().cloned().collect::<HashSet<_>>();
+        let mut queue = start.into_iter().collect::<VecDeque<_>>();
+
+        while let Some(current) = queue.pop_front() {
+            let mut neighbours = self.get_connected_conductors(&current);
+            if let ElectricalConductor::Label(uuid) = &current {
+                if let Some(label) = self.labels.get(uuid) {
+                    neighbours.extend(
+                        self.labels.values()
+                            .filter(|l| l.text == label.text)
+                            .map(|l| ElectricalConductor::Label(l.uuid))
+                    );
+                }
+            }
+            for neighbour in neighbours {
+                if visited.insert(neighbour.clone()) {
+                    queue.push_back(neighbour);
+                }
+            }
+        }
+        visited
+    }
+
+    /// Returns true if the label sits on a bus, which makes its text a list of members instead of just a name.
+    fn is_bus_label(&self, label: &Label) -> bool {
+        self.get_connected_conductors(&ElectricalConductor::Label(label.uuid)).iter().any(|c| self.is_bus(c))
+    }
+
+    /// Returns all the conductors which are directly connected to the given conductor.
+    fn get_connected_conductors(&self, conductor: &ElectricalConductor) -> Vec<ElectricalConductor> {
+        let aabb = match self.get_conductor_aabb(conductor, CONNECTION_TOLERANCE) {
+            Some(aabb) => aabb,
+            None => return Vec::new(),
+        };
+        let mut candidates = Vec::new();
+        {
+            let mut visitor = BoundingVolumeInterferencesCollector::new(&aabb, &mut candidates);
+            self.wire_net.read().unwrap().visit(&mut visitor);
+        }
+        candidates.into_iter()
+            .filter(|candidate| candidate != conductor && self.are_compatible(conductor, candidate) && self.are_connected(conductor, candidate))
+            .collect()
+    }
+
+    /// Returns true if the conductor is a bus segment or a bus to bus entry.
+    fn is_bus(&self, conductor: &ElectricalConductor) -> bool {
+        match conductor {
+            ElectricalConductor::Wire(uuid) => self.wires.get(uuid).map_or(false, |w| w.kind == WireType::Bus),
+            // A wire to bus entry belongs to the wire. It joins the bus by the name of the wire, not by touching it.
+            ElectricalConductor::BusEntry(uuid) => self.bus_entries.get(uuid).map_or(false, |e| e.kind == BusEntryType::BusToBus),
+            _ => false,
+        }
+    }
+
+    /// Returns true if the two conductors can be electrically connected.
+    /// Buses only connect to other buses, labels and junctions, they never connect to plain wires or pins directly.
+    fn are_compatible(&self, a: &ElectricalConductor, b: &ElectricalConductor) -> bool {
+        match (a, b) {
+            (ElectricalConductor::Label(_), _) | (_, ElectricalConductor::Label(_)) => true,
+            (ElectricalConductor::Junction(_), _) | (_, ElectricalConductor::Junction(_)) => true,
+            _ => self.is_bus(a) == self.is_bus(b),
+        }
+    }
+
+    /// Returns true if an end of one of the conductors lies on the other one.
+    fn are_connected(&self, a: &ElectricalConductor, b: &ElectricalConductor) -> bool {
+        self.get_connection_points(a).iter().any(|p| self.is_conductor_near(b, p, CONNECTION_TOLERANCE))
+        || self.get_connection_points(b).iter().any(|p| self.is_conductor_near(a, p, CONNECTION_TOLERANCE))
+    }
+
+    /// Returns true if the distance between the conductor and `point` is smaller than `tolerance`.
+    fn is_conductor_near(&self, conductor: &ElectricalConductor, point: &Point2, tolerance: f32) -> bool {
+        match conductor {
+            ElectricalConductor::Wire(uuid) => self.wires.get(uuid).map_or(false, |w| {
+                distance_point_segment(point, &w.start, &w.end) <= tolerance
+            }),
+            ElectricalConductor::BusEntry(uuid) => self.bus_entries.get(uuid).map_or(false, |e| {
+                distance_point_segment(point, &e.start, &e.end) <= tolerance
+            }),
+            _ => self.get_connection_points(conductor).iter().any(|p| (p - point).norm() <= tolerance),
+        }
+    }
+
+    /// Returns the points at which other conductors can attach to the given conductor.
+    fn get_connection_points(&self, conductor: &ElectricalConductor) -> Vec<Point2> {
+        let points = match conductor {
+            ElectricalConductor::Wire(uuid) => self.wires.get(uuid).map(|w| vec![w.start, w.end]),
+            ElectricalConductor::Pin(uuid, i) => self.pin_ends.get(uuid).and_then(|pins| pins.get(i)).map(|p| vec![p.position]),
+            ElectricalConductor::Label(uuid) => self.labels.get(uuid).map(|l| vec![l.position]),
+            ElectricalConductor::Junction(uuid) => self.junctions.get(uuid).map(|j| vec![j.position]),
+            ElectricalConductor::BusEntry(uuid) => self.bus_entries.get(uuid).map(|e| vec![e.start, e.end]),
+        };
+        points.unwrap_or_else(Vec::new)
+    }
+
+    fn get_conductor_aabb(&self, conductor: &ElectricalConductor, margin: f32) -> Option<AABB> {
+        match conductor {
+            ElectricalConductor::Wire(uuid) => self.wires.get(uuid).map(|w| segment_aabb(&w.start, &w.end, margin)),
+            ElectricalConductor::BusEntry(uuid) => self.bus_entries.get(uuid).map(|e| segment_aabb(&e.start, &e.end, margin)),
+            _ => self.get_connection_points(conductor).first().map(|p| segment_aabb(p, p, margin)),
+        }
+    }
+
+    /// Names a net after its alphabetically first label.
+    /// If the net has no labels it is named after one of its pins like KiCad does.
+    fn get_net_name(&self, conductors: &[ElectricalConductor]) -> Option<String> {
+        let mut label_names = conductors.iter().filter_map(|c| match c {
+            ElectricalConductor::Label(uuid) => self.labels.get(uuid).map(|l| l.text.clone()),
+            _ => None,
+        }).collect::<Vec<_>>();
+        label_names.sort();
+
+        let mut pin_names = conductors.iter().filter_map(|c| match c {
+            ElectricalConductor::Pin(uuid, i) => {
+                let pin = self.pin_ends.get(uuid).and_then(|pins| pins.get(i));
+                match (self.references.get(uuid), pin) {
+                    (Some(reference), Some(pin)) => Some(format!("Net-({}-Pad{})", reference, pin.number)),
+                    _ => None,
+                }
+            },
+            _ => None,
+        }).collect::<Vec<_>>();
+        pin_names.sort();
+
+        label_names.into_iter().next().or_else(|| pin_names.into_iter().next())
+    }
+
+    /// Adds the pins of a component instance to the wire net.
+    fn insert_pins(&mut self, instance: &ComponentInstance, component: &Component) {
+        let mut pins = HashMap::new();
+        let mut pin_ends = HashMap::new();
+        {
+            let mut wire_net = self.wire_net.write().unwrap();
+            for (i, number, pos) in instance.get_pin_positions(component) {
+                let aabb = segment_aabb(&pos, &pos, PIN_RADIUS / 2.0);
+                pins.insert(i, wire_net.insert(DBVTLeaf::new(aabb, ElectricalConductor::Pin(instance.uuid, i))));
+                pin_ends.insert(i, PinEnd { position: pos, number: number });
+            }
+        }
+        self.pin_leaf_map.insert(instance.uuid, pins);
+        self.pin_ends.insert(instance.uuid, pin_ends);
+        self.references.insert(instance.uuid, instance.reference.clone());
+    }
+
+    /// Removes the pins of a component instance from the wire net.
+    fn remove_pins(&mut self, instance_uuid: &Uuid) {
+        if let Some(pin_leaf_ids) = self.pin_leaf_map.remove(instance_uuid) {
+            let mut wire_net = self.wire_net.write().unwrap();
+            for pin_leaf_id in pin_leaf_ids.values() {
+                wire_net.remove(*pin_leaf_id);
+            }
+        }
+        self.pin_ends.remove(instance_uuid);
+        self.references.remove(instance_uuid);
+    }
+
+    /// Adds a wire to the wire net.
+    fn insert_wire(&mut self, wire: &WireSegment) {
+        let aabb = segment_aabb(&wire.start, &wire.end, PIN_RADIUS / 2.0);
+        let leaf_id = self.wire_net.write().unwrap().insert(DBVTLeaf::new(aabb, ElectricalConductor::Wire(wire.uuid)));
+        self.wire_leaf_map.insert(wire.uuid, leaf_id);
+        self.wires.insert(wire.uuid, wire.clone());
+    }
+
+    /// Removes a wire from the wire net.
+    fn remove_wire(&mut self, wire_uuid: &Uuid) {
+        if let Some(leaf_id) = self.wire_leaf_map.remove(wire_uuid) {
+            self.wire_net.write().unwrap().remove(leaf_id);
+        }
+        self.wires.remove(wire_uuid);
+    }
+
+    /// Adds a label to the wire net.
+    fn insert_label(&mut self, label: &Label) {
+        let aabb = segment_aabb(&label.position, &label.position, PIN_RADIUS / 2.0);
+        let leaf_id = self.wire_net.write().unwrap().insert(DBVTLeaf::new(aabb, ElectricalConductor::Label(label.uuid)));
+        self.label_leaf_map.insert(label.uuid, leaf_id);
+        self.labels.insert(label.uuid, label.clone());
+    }
+
+    /// Removes a label from the wire net.
+    fn remove_label(&mut self, label_uuid: &Uuid) {
+        if let Some(leaf_id) = self.label_leaf_map.remove(label_uuid) {
+            self.wire_net.write().unwrap().remove(leaf_id);
+        }
+        self.labels.remove(label_uuid);
+    }
+
+    /// Adds a junction to the wire net.
+    fn insert_junction(&mut self, junction: &Junction) {
+        let aabb = segment_aabb(&junction.position, &junction.position, PIN_RADIUS / 2.0);
+        let leaf_id = self.wire_net.write().unwrap().insert(DBVTLeaf::new(aabb, ElectricalConductor::Junction(junction.uuid)));
+        self.junction_leaf_map.insert(junction.uuid, leaf_id);
+        self.junctions.insert(junction.uuid, junction.clone());
+    }
+
+    /// Removes a junction from the wire net.
+    fn remove_junction(&mut self, junction_uuid: &Uuid) {
+        if let Some(leaf_id) = self.junction_leaf_map.remove(junction_uuid) {
+            self.wire_net.write().unwrap().remove(leaf_id);
+        }
+        self.junctions.remove(junction_uuid);
+    }
+
+    /// Adds a bus entry to the wire net.
+    fn insert_bus_entry(&mut self, entry: &BusEntry) {
+        let aabb = segment_aabb(&entry.start, &entry.end, PIN_RADIUS / 2.0);
+        let leaf_id = self.wire_net.write().unwrap().insert(DBVTLeaf::new(aabb, ElectricalConductor::BusEntry(entry.uuid)));
+        self.bus_entry_leaf_map.insert(entry.uuid, leaf_id);
+        self.bus_entries.insert(entry.uuid, entry.clone());
+    }
+
+    /// Removes a bus entry from the wire net.
+    fn remove_bus_entry(&mut self, entry_uuid: &Uuid) {
+        if let Some(leaf_id) = self.bus_entry_leaf_map.remove(entry_uuid) {
+            self.wire_net.write().unwrap().remove(leaf_id);
+        }
+        self.bus_entries.remove(entry_uuid);
+    }
+
+    /// Adds a note to the graphic world.
+    fn insert_note(&mut self, note: &Note) {
+        let leaf_id = self.graphic_world.write().unwrap().insert(DBVTLeaf::new(note.get_boundingbox(), note.uuid));
+        self.graphic_leaf_map.insert(note.uuid, leaf_id);
+        self.notes.insert(note.uuid, note.clone());
+    }
+
+    /// Adds a note line to the graphic world.
+    fn insert_note_line(&mut self, line: &NoteLine) {
+        let aabb = segment_aabb(&line.start, &line.end, PIN_RADIUS / 2.0);
+        let leaf_id = self.graphic_world.write().unwrap().insert(DBVTLeaf::new(aabb, line.uuid));
+        self.graphic_leaf_map.insert(line.uuid, leaf_id);
+        self.note_lines.insert(line.uuid, line.clone());
+    }
+
+    /// Removes a note or a note line from the graphic world.
+    fn remove_graphic(&mut self, uuid: &Uuid) {
+        if let Some(leaf_id) = self.graphic_leaf_map.remove(uuid) {
+            self.graphic_world.write().unwrap().remove(leaf_id);
+        }
+        self.notes.remove(uuid);
+        self.note_lines.remove(uuid);
+    }
 }
 
 impl Listener for SchemaViewer {
     fn receive(&mut self, msg: &EventMessage) {
+        match msg {
+            EventMessage::AddComponent(_) | EventMessage::UpdateComponent(_) | EventMessage::RemoveComponent(_)
+            | EventMessage::AddWire(_) | EventMessage::UpdateWire(_) | EventMessage::RemoveWire(_)
+            | EventMessage::AddLabel(_) | EventMessage::UpdateLabel(_) | EventMessage::RemoveLabel(_)
+            | EventMessage::AddJunction(_) | EventMessage::RemoveJunction(_)
+            | EventMessage::AddBusEntry(_) | EventMessage::UpdateBusEntry(_) | EventMessage::RemoveBusEntry(_) => {
+                self.connectivity_changed = true;
+                self.hovered_net = None;
+            },
+            EventMessage::AddBusAlias(_) | EventMessage::RemoveBusAlias(_) => self.hovered_net = None,
+            _ => (),
+        }
         match msg {
             EventMessage::AddComponent(instance) => {
                 // TODO: This is an ugly fix, remove ASAP
                 // Retrieve component data from the lib.
-                let libraries = self.libraries.write().unwrap();
+                let libraries = self.libraries.clone();
+                let libraries = libraries.read().unwrap();
                 let component = libraries.get_component_by_name(&instance.name);
 
+                self.instances.insert(instance.uuid, instance.clone());
                 if let Some(c) = component {
                     // Add component to the necessary BVT.
                     let aabb = instance.get_boundingbox(c).clone();
                     self.leaf_map.insert(instance.uuid, self.collision_world.write().unwrap().insert(DBVTLeaf::new(aabb, instance.uuid)));
 
                     // Add the pins of the component to the necessary BVT.
-                    // Check for intersections with other pins or wires and remember those connections.
-                    let mut pins = HashMap::new();
-                    for potential_pin in c.graphic_elements.iter().enumerate() {
-                        if let (i, GraphicElement::Pin{ uuid, position, .. }) = potential_pin {
-                            let pos = instance.position + point_to_vector_2d(position);
-                            let half_width = Vector2::new(PIN_RADIUS / 2.0, PIN_RADIUS / 2.0);
-                            let aabb = AABB::new(pos - half_width, pos + half_width);
-                            pins.insert(i,self.wire_net.write().unwrap().insert(DBVTLeaf::new(aabb, ElectricalConductor::Pin(uuid.clone(), i))));
-                        }
-                    }
-                    self.pin_leaf_map.insert(
-                        instance.uuid,
-                        pins,
-                    );
+                    self.insert_pins(instance, c);
                 }
             },
             EventMessage::UpdateComponent(instance) => {
-                let libraries = self.libraries.write().unwrap();
+                let libraries = self.libraries.clone();
+                let libraries = libraries.read().unwrap();
                 let component = libraries.get_component_by_name(&instance.name);
-
-                let mut collision_world = self.collision_world.write().unwrap();
+                self.instances.insert(instance.uuid, instance.clone());
 
                 // Update the pins of the component in the necessary BVT.
-                // Check for intersections with other pins or wires and remember those connections.
                 if let Some(c) = component {
-                    // Remove the old collision data for the component in the BVT.
-                    let leaf_id = self.leaf_map.get(&instance.uuid);
-                    collision_world.remove(*leaf_id.unwrap());
+                    {
+                        let mut collision_world = self.collision_world.write().unwrap();
 
-                    // Update component in the necessary BVT.
-                    let aabb = instance.get_boundingbox(c).clone();
-                    self.leaf_map.insert(instance.uuid, collision_world.insert(DBVTLeaf::new(aabb, instance.uuid)));
+                        // Remove the old collision data for the component in the BVT.
+                        let leaf_id = self.leaf_map.get(&instance.uuid);
+                        collision_world.remove(*leaf_id.unwrap());
 
-                    // Remove the old collision data for the components pins in the BVT.
-                    let pin_leaf_ids = self.pin_leaf_map.get(&instance.uuid);
-                    for pin_leaf_id in pin_leaf_ids.unwrap().values() {
-                        self.wire_net.write().unwrap().remove(*pin_leaf_id);
+                        // Update component in the necessary BVT.
+                        let aabb = instance.get_boundingbox(c).clone();
+                        self.leaf_map.insert(instance.uuid, collision_world.insert(DBVTLeaf::new(aabb, instance.uuid)));
                     }
 
-                    let mut pins = HashMap::new();
-                    for potential_pin in c.graphic_elements.iter().enumerate() {
-                        if let (i, GraphicElement::Pin{ uuid, position, .. }) = potential_pin {
-                            let pos = instance.position + point_to_vector_2d(position);
-                            let half_width = Vector2::new(PIN_RADIUS / 2.0, PIN_RADIUS / 2.0);
-                            let aabb = AABB::new(pos - half_width, pos + half_width);
-                            pins.insert(i,self.wire_net.write().unwrap().insert(DBVTLeaf::new(aabb, ElectricalConductor::Pin(uuid.clone(), i))));
-                        }
-                    }
-                    self.pin_leaf_map.insert(
-                        instance.uuid,
-                        pins,
-                    );
+                    // Replace the old collision data for the components pins in the BVT.
+                    self.remove_pins(&instance.uuid);
+                    self.insert_pins(instance, c);
                 }
             },
+            EventMessage::RemoveComponent(instance) => {
+                self.instances.remove(&instance.uuid);
+                if let Some(leaf_id) = self.leaf_map.remove(&instance.uuid) {
+                    self.collision_world.write().unwrap().remove(leaf_id);
+                }
+                self.remove_pins(&instance.uuid);
+            },
             EventMessage::AddWire(instance) => {
-                // Add the pins of the component to the necessary BVT.
-                // Check for intersections with other pins or wires and remember those connections.
-                let half_width = Vector2::new(PIN_RADIUS / 2.0, PIN_RADIUS / 2.0);
-                let aabb = AABB::new(instance.start - half_width, instance.end + half_width);
-                self.wire_leaf_map.insert(
-                    instance.uuid,
-                    self.wire_net.write().unwrap().insert(DBVTLeaf::new(aabb, ElectricalConductor::Wire(instance.uuid.clone()))),
-                );
+                self.insert_wire(instance);
+            },
+            EventMessage::RemoveWire(instance) => {
+                self.remove_wire(&instance.uuid);
             },
             EventMessage::UpdateWire(instance) => {
-                let mut wire_net = self.wire_net.write().unwrap();
-
-                // Remove the old collision data for the wire in the BVT.
-                let leaf_id = self.wire_leaf_map.get(&instance.uuid);
-                wire_net.remove(*leaf_id.unwrap());
-
-                // Add the pins of the component to the necessary BVT.
-                // Check for intersections with other pins or wires and remember those connections.
-                let half_width = Vector2::new(PIN_RADIUS / 2.0, PIN_RADIUS / 2.0);
-                let aabb = AABB::new(instance.start - half_width, instance.end + half_width);
-                self.wire_leaf_map.insert(
-                    instance.uuid,
-                    wire_net.insert(DBVTLeaf::new(aabb, ElectricalConductor::Wire(instance.uuid.clone()))),
-                );
+                // Replace the old collision data for the wire in the BVT.
+                self.remove_wire(&instance.uuid);
+                self.insert_wire(instance);
+            },
+            EventMessage::AddLabel(label) => {
+                self.insert_label(label);
+            },
+            EventMessage::RemoveLabel(label) => {
+                self.remove_label(&label.uuid);
+            },
+            EventMessage::UpdateLabel(label) => {
+                // Replace the old collision data for the label in the BVT.
+                self.remove_label(&label.uuid);
+                self.insert_label(label);
+            },
+            EventMessage::AddJunction(junction) => {
+                self.insert_junction(junction);
+            },
+            EventMessage::RemoveJunction(junction) => {
+                self.remove_junction(&junction.uuid);
+            },
+            EventMessage::AddBusEntry(entry) => {
+                self.insert_bus_entry(entry);
+            },
+            EventMessage::RemoveBusEntry(entry) => {
+                self.remove_bus_entry(&entry.uuid);
+            },
+            EventMessage::UpdateBusEntry(entry) => {
+                // Replace the old collision data for the bus entry in the BVT.
+                self.remove_bus_entry(&entry.uuid);
+                self.insert_bus_entry(entry);
+            },
+            EventMessage::AddNote(note) => {
+                self.insert_note(note);
+            },
+            EventMessage::UpdateNote(note) => {
+                self.remove_graphic(&note.uuid);
+                self.insert_note(note);
+            },
+            EventMessage::AddNoteLine(line) => {
+                self.insert_note_line(line);
+            },
+            EventMessage::UpdateNoteLine(line) => {
+                self.remove_graphic(&line.uuid);
+                self.insert_note_line(line);
+            },
+            EventMessage::RemoveNote(Note { uuid, .. }) | EventMessage::RemoveNoteLine(NoteLine { uuid, .. }) => {
+                self.remove_graphic(uuid);
+            },
+            EventMessage::AddBusAlias(alias) => {
+                self.bus_aliases.insert(alias.name.clone(), alias.members.clone());
+            },
+            EventMessage::RemoveBusAlias(alias) => {
+                self.bus_aliases.remove(&alias.name);
             },
             EventMessage::SelectComponent(uuid) => self.selected_component = Some(uuid.clone()),
             EventMessage::ViewStateChanged => {
@@ -204,4 +1036,127 @@ impl Listener for SchemaViewer {
             _ => (),
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use state::schema::test_utils::{Fixture, bus, label, wire};
+
+    /// A viewer on an empty schema. Items are fed to it as events.
+    fn viewer() -> SchemaViewer {
+        let Fixture { libraries, schema, .. } = Fixture::new();
+        let view_state = Arc::new(RwLock::new(ViewState::new(1, 1)));
+        SchemaViewer::new(Arc::new(RwLock::new(schema)), view_state, Arc::new(RwLock::new(libraries)))
+    }
+
+    #[test]
+    fn dangling_wire_ends() {
+        let mut viewer = viewer();
+
+        // The second wire ends on the middle of the first one, which connects it there.
+        viewer.receive(&EventMessage::AddWire(wire((0.0, 0.0), (100.0, 0.0))));
+        viewer.receive(&EventMessage::AddWire(wire((50.0, 0.0), (50.0, 100.0))));
+        assert!(viewer.take_connectivity_changed());
+        assert!(!viewer.take_connectivity_changed());
+
+        let ends = viewer.get_unconnected_ends();
+        assert!(ends.pins.is_empty());
+        assert_eq!(ends.wire_ends.len(), 3);
+        assert!(!ends.wire_ends.contains(&Point2::new(50.0, 0.0)));
+
+        // A note line touching a dangling end connects nothing, but can be picked.
+        let line = NoteLine { uuid: Uuid::new_v4(), start: Point2::new(100.0, 0.0), end: Point2::new(100.0, 200.0) };
+        viewer.receive(&EventMessage::AddNoteLine(line.clone()));
+        assert!(!viewer.take_connectivity_changed());
+        assert_eq!(viewer.get_unconnected_ends().wire_ends.len(), 3);
+        assert_eq!(viewer.pick(&Point2::new(102.0, 150.0), 10.0), Some(PickedItem::NoteLine(line.uuid)));
+    }
+
+    #[test]
+    fn bus_members_join_the_nets_named_after_them() {
+        let mut viewer = viewer();
+
+        let data = bus((0.0, 0.0), (0.0, 400.0));
+        let d2 = wire((100.0, 100.0), (300.0, 100.0));
+        let d7 = wire((500.0, 0.0), (600.0, 0.0));
+        let entry = BusEntry { uuid: Uuid::new_v4(), kind: BusEntryType::WireToBus, start: Point2::new(0.0, 200.0), end: Point2::new(100.0, 100.0) };
+        viewer.receive(&EventMessage::AddWire(data.clone()));
+        viewer.receive(&EventMessage::AddWire(d2.clone()));
+        viewer.receive(&EventMessage::AddWire(d7.clone()));
+        viewer.receive(&EventMessage::AddBusEntry(entry.clone()));
+        viewer.receive(&EventMessage::AddLabel(label("D[0..3]", (0.0, 50.0))));
+        viewer.receive(&EventMessage::AddLabel(label("D2", (300.0, 100.0))));
+        viewer.receive(&EventMessage::AddLabel(label("D7", (600.0, 0.0))));
+
+        let bus_net = viewer.get_net(&ElectricalConductor::Wire(data.uuid));
+        assert_eq!(bus_net.name, Some("D[0..3]".to_owned()));
+        assert_eq!(bus_net.members, vec!["D0", "D1", "D2", "D3"]);
+        assert!(bus_net.contains(&ElectricalConductor::Wire(d2.uuid)));
+        assert!(!bus_net.contains(&ElectricalConductor::Wire(d7.uuid)));
+
+        // The entry belongs to the wire, which keeps its own name.
+        let member_net = viewer.get_net(&ElectricalConductor::Wire(d2.uuid));
+        assert_eq!(member_net.name, Some("D2".to_owned()));
+        assert!(!member_net.is_bus());
+        assert!(member_net.contains(&ElectricalConductor::BusEntry(entry.uuid)));
+        assert!(member_net.contains(&ElectricalConductor::Wire(data.uuid)));
+
+        let other_net = viewer.get_net(&ElectricalConductor::Wire(d7.uuid));
+        assert!(!other_net.contains(&ElectricalConductor::Wire(data.uuid)));
+    }
+
+    #[test]
+    fn wires_ending_on_bus_entries_are_connected() {
+        let mut viewer = viewer();
+
+        viewer.receive(&EventMessage::AddWire(bus((0.0, 0.0), (0.0, 400.0))));
+        viewer.receive(&EventMessage::AddWire(wire((100.0, 100.0), (300.0, 100.0))));
+        let entry = BusEntry { uuid: Uuid::new_v4(), kind: BusEntryType::WireToBus, start: Point2::new(0.0, 200.0), end: Point2::new(100.0, 100.0) };
+        viewer.receive(&EventMessage::AddBusEntry(entry));
+        assert!(viewer.take_connectivity_changed());
+
+        let ends = viewer.get_unconnected_ends().wire_ends;
+        assert!(!ends.contains(&Point2::new(100.0, 100.0)));
+        assert!(ends.contains(&Point2::new(300.0, 100.0)));
+    }
+
+    #[test]
+    fn edits_drop_the_hovered_net() {
+        let mut viewer = viewer();
+
+        let first = wire((0.0, 0.0), (100.0, 0.0));
+        viewer.receive(&EventMessage::AddWire(first.clone()));
+        assert!(viewer.has_conductor(&ElectricalConductor::Wire(first.uuid)));
+        viewer.hovered_net = Some((ElectricalConductor::Wire(first.uuid), "Net-1".to_owned()));
+
+        // Moving the cursor keeps the cached net, but any change of the conductors drops it.
+        viewer.receive(&EventMessage::ViewStateChanged);
+        assert!(viewer.hovered_net.is_some());
+        viewer.receive(&EventMessage::AddLabel(label("CLK", (100.0, 0.0))));
+        assert!(viewer.hovered_net.is_none());
+
+        viewer.hovered_net = Some((ElectricalConductor::Wire(first.uuid), "CLK".to_owned()));
+        viewer.receive(&EventMessage::RemoveWire(first.clone()));
+        assert!(viewer.hovered_net.is_none());
+        assert!(!viewer.has_conductor(&ElectricalConductor::Wire(first.uuid)));
+    }
+
+    #[test]
+    fn routes_end_on_nearby_wire_ends() {
+        let mut viewer = viewer();
+
+        // Both wire ends lie off the grid of the route.
+        viewer.receive(&EventMessage::AddWire(wire((-100.0, 3.0), (-2.0, 3.0))));
+        viewer.receive(&EventMessage::AddWire(wire((203.0, 98.0), (300.0, 98.0))));
+
+        let path = viewer.route_wire(&Point2::new(0.0, 0.0), &Point2::new(200.0, 100.0), &Vector2::new(50.0, 50.0), 10.0).unwrap();
+        assert_eq!(path.first(), Some(&Point2::new(-2.0, 3.0)));
+        assert_eq!(path.last(), Some(&Point2::new(203.0, 98.0)));
+
+        // Ends too far away are left alone.
+        let path = viewer.route_wire(&Point2::new(0.0, 0.0), &Point2::new(200.0, 100.0), &Vector2::new(50.0, 50.0), 1.0).unwrap();
+        assert_eq!(path.first(), Some(&Point2::new(0.0, 0.0)));
+        assert_eq!(path.last(), Some(&Point2::new(200.0, 100.0)));
+    }
+}
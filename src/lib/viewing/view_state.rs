@@ -3,6 +3,20 @@ use std::f32;
 use geometry::*;
 use utils::geometry::*;
 use super::item_group::ItemGroup;
+use super::grid::GridSettings;
+
+/// The bounds of the zoom scale.
+const MIN_SCALE: f32 = 1.0 / 60000.0;
+const MAX_SCALE: f32 = 0.3;
+/// The number of views which can be gone back to.
+const VIEW_HISTORY_DEPTH: usize = 50;
+
+/// A section of the schema shown on the canvas, such that it can be returned to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SavedView {
+    pub center: Point2,
+    pub scale: f32,
+}
 
 /// A struct which holds all the information about the current view into the current schema.
 /// This struct also stores the information about visual tooling such as the cursor location,
@@ -25,9 +39,15 @@ pub struct ViewState {
     /// `grabbed_items` contains all the currently grabbed items. This is the actively being manipulated group of items.
     /// If the cursor is moved or different modifications are performed, the modifications are always performed on the items contained in this group.
     pub grabbed_items: ItemGroup,
-    grid_size: Point2,
-    pub wire_snap_to_grid: bool,
-    pub component_snap_to_grid: bool,
+    /// `hovered_net` contains the name of the net underneath the cursor if there is any.
+    pub hovered_net: Option<String>,
+    /// `highlighted_net` contains the name of the net which was clicked last and is highlighted on the schema.
+    pub highlighted_net: Option<String>,
+    /// `grid` describes the grid the cursor snaps to and how it is displayed.
+    pub grid: GridSettings,
+    /// The views before and after the current one, the most recent ones last.
+    view_history: Vec<SavedView>,
+    view_future: Vec<SavedView>,
 }
 
 /// Defines flags to describe the mouse state.
@@ -56,9 +76,11 @@ impl ViewState {
             hovered_items: ItemGroup::new(),
             selected_items: ItemGroup::new(),
             grabbed_items: ItemGroup::new(),
-            grid_size: Point2::new(1000.0, 1000.0),
-            wire_snap_to_grid: true,
-            component_snap_to_grid: true,
+            hovered_net: None,
+            highlighted_net: None,
+            grid: GridSettings::new(),
+            view_history: Vec::new(),
+            view_future: Vec::new(),
         };
         vs.update_perspective();
         vs
@@ -75,13 +97,7 @@ impl ViewState {
     /// Update the `ViewState` when zooming the canvas.
     /// Updates the internal perspective.
     pub fn update_from_zoom(&mut self, delta: f32) {
-        self.scale += delta / 10000.0;
-        if self.scale < 1.0 / 60000.0 {
-            self.scale = 1.0 / 60000.0;
-        }
-        if self.scale > 0.3 {
-            self.scale = 0.3;
-        }
+        self.scale = (self.scale + delta / 10000.0).max(MIN_SCALE).min(MAX_SCALE);
         self.update_perspective();
     }
 
@@ -92,11 +108,63 @@ impl ViewState {
         let ratio_w = 2.0 / (rect.maxs().x - rect.mins().x) as f32;
         let ratio_h = aspect_ratio * 2.0 / (rect.maxs().y - rect.mins().y) as f32;
 
-        self.scale = ratio_w.min(ratio_h) * 0.9;
+        // An empty box, like the one of a single label, would zoom in infinitely.
+        self.scale = (ratio_w.min(ratio_h) * 0.9).max(MIN_SCALE).min(MAX_SCALE);
         self.center = rect.center();
         self.update_perspective();
     }
 
+    /// Fits the given `AABB` into the canvas like `update_from_box_pan` and remembers the previous view, such that it can be gone back to.
+    pub fn jump_to_box(&mut self, rect: AABB) {
+        self.view_history.push(self.get_view());
+        if self.view_history.len() > VIEW_HISTORY_DEPTH {
+            self.view_history.remove(0);
+        }
+        self.view_future.clear();
+        self.update_from_box_pan(rect);
+    }
+
+    /// Returns the section of the schema which is currently shown.
+    pub fn get_view(&self) -> SavedView {
+        SavedView {
+            center: self.center,
+            scale: self.scale,
+        }
+    }
+
+    /// Shows a section of the schema again.
+    fn set_view(&mut self, view: SavedView) {
+        self.center = view.center;
+        self.scale = view.scale;
+        self.update_perspective();
+    }
+
+    /// Returns to the view before the last jump. Returns false if there is no such view.
+    pub fn go_back(&mut self) -> bool {
+        match self.view_history.pop() {
+            Some(view) => {
+                let current = self.get_view();
+                self.view_future.push(current);
+                self.set_view(view);
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Returns to the view a `go_back` left. Returns false if there is no such view.
+    pub fn go_forward(&mut self) -> bool {
+        match self.view_future.pop() {
+            Some(view) => {
+                let current = self.get_view();
+                self.view_history.push(current);
+                self.set_view(view);
+                true
+            },
+            None => false,
+        }
+    }
+
     /// Moves the viewport into the canvas by the `translation` vector.
     pub fn move_viewport(&mut self, translation: Vector2) {
         self.center += &transform_vector_2d(&translation, &Matrix4::new_nonuniform_scaling(
@@ -171,20 +239,13 @@ impl ViewState {
     /// Returns the current cursor position but with respect to the grid.
     /// This copies the current cursor, snaps it to the grid and returns it.
     pub fn get_grid_snapped_cursor(&self) -> Point2 {
-        Point2::new(
-            (self.cursor.x / self.grid_size.x).round() * self.grid_size.x,
-            (self.cursor.y / self.grid_size.y).round() * self.grid_size.y
-        )
+        self.grid.snap(&self.cursor)
     }
 
     /// Returns the current cursor position but with respect to the grid.
     /// This copies the current cursor, snaps it to the grid and returns it.
     pub fn get_grid_snapped_cursor_in_schema_space(&self) -> Point2 {
-        let ciss = self.get_cursor_in_schema_space();
-        Point2::new(
-            (ciss.x / self.grid_size.x).round() * self.grid_size.x,
-            (ciss.y / self.grid_size.y).round() * self.grid_size.y
-        )
+        self.grid.snap(&self.get_cursor_in_schema_space())
     }
 
     pub fn get_schema_distance_from_screen_distance(&self, d: &Vector2) -> Vector2 {
@@ -233,14 +294,16 @@ impl ViewState {
         )).try_inverse().expect("World transform has no inverse. This is a bug. Please report this event."))
     }
 
-    /// Gets the current grid size.
-    pub fn get_grid_size(&self) -> &Point2 {
-        &self.grid_size
+    /// Gets the size of the grid which is currently in use.
+    pub fn get_grid_size(&self) -> Point2 {
+        let size = self.grid.get_active_size();
+        Point2::new(size, size)
     }
 
-    /// Sets the current grid size.
-    pub fn set_grid_size(&mut self, x: i32, y: i32) {
-        self.grid_size = Point2::new(x as f32, y as f32);
+    /// Returns the spacing of the grid lines to draw at the current zoom, see `GridSettings::get_line_spacing`.
+    pub fn get_grid_line_spacing(&self) -> f32 {
+        let pixels_per_unit = self.get_screen_distance_from_schema_distance(&Vector2::new(1.0, 0.0)).norm();
+        self.grid.get_line_spacing(pixels_per_unit)
     }
 
     /// Adds the currently hovered item to the currently selected ones.
@@ -257,10 +320,48 @@ impl ViewState {
         }
     }
 
+    /// Adds the currently selected items to the currently grabbed ones.
+    pub fn add_selected_items_to_grabbed_items(&mut self) {
+        for &item in self.selected_items.get_items_mut() {
+            self.grabbed_items.insert(item.clone());
+        }
+    }
+
     /// Adds the currently hovered item to the currently selected ones.
     pub fn add_hovered_item_to_selected_items(&mut self) {
         for &item in self.hovered_items.get_items_mut() {
             self.selected_items.insert(item.clone());
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn go_back_and_forward_through_jumps() {
+        let mut view_state = ViewState::new(800, 600);
+        let start = view_state.get_view();
+
+        view_state.jump_to_box(AABB::new(Point2::new(0.0, 0.0), Point2::new(1000.0, 1000.0)));
+        let first = view_state.get_view();
+        view_state.jump_to_box(AABB::new(Point2::new(5000.0, 5000.0), Point2::new(5000.0, 5000.0)));
+        let second = view_state.get_view();
+        assert_eq!(second.center, Point2::new(5000.0, 5000.0));
+        assert!(second.scale <= MAX_SCALE);
+
+        assert!(view_state.go_back());
+        assert_eq!(view_state.get_view(), first);
+        assert!(view_state.go_back());
+        assert_eq!(view_state.get_view(), start);
+        assert!(!view_state.go_back());
+
+        assert!(view_state.go_forward());
+        assert_eq!(view_state.get_view(), first);
+
+        // A new jump forgets the views which were gone back from.
+        view_state.jump_to_box(AABB::new(Point2::new(0.0, 0.0), Point2::new(10.0, 10.0)));
+        assert!(!view_state.go_forward());
+    }
+}